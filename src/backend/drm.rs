@@ -41,15 +41,28 @@ impl Drm {
         parent: Option<Drm>,
         render_setup_func: Option<UnsafeRenderSetupFunction>
     ) -> Self {
+        Drm::try_new(display, session, gpu_fd, parent, render_setup_func)
+            .unwrap_or_else(|| panic!("Could not construct DRM backend"))
+    }
+
+    /// Like `new`, but returns `None` if the backend could not be created.
+    pub unsafe fn try_new(
+        display: *mut wl_display,
+        session: Session,
+        gpu_fd: c_int,
+        parent: Option<Drm>,
+        render_setup_func: Option<UnsafeRenderSetupFunction>
+    ) -> Option<Self> {
         let parent_ptr = parent
             .map(|backend| backend.as_ptr())
             .unwrap_or_else(ptr::null_mut);
         let backend =
             wlr_drm_backend_create(display, session.as_ptr(), gpu_fd, parent_ptr, render_setup_func);
         if backend.is_null() {
-            panic!("Could not construct X11 backend");
+            None
+        } else {
+            Some(Drm { backend })
         }
-        Drm { backend }
     }
 
     pub fn output_is_drm(&self, output: &Output) -> bool {
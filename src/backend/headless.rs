@@ -32,11 +32,21 @@ impl Headless {
         display: *mut wl_display,
         render_setup_func: Option<UnsafeRenderSetupFunction>
     ) -> Self {
+        Headless::try_new(display, render_setup_func)
+            .unwrap_or_else(|| panic!("Could not construct Headless backend"))
+    }
+
+    /// Like `new`, but returns `None` if the backend could not be created.
+    pub unsafe fn try_new(
+        display: *mut wl_display,
+        render_setup_func: Option<UnsafeRenderSetupFunction>
+    ) -> Option<Self> {
         let backend = wlr_headless_backend_create(display, render_setup_func);
         if backend.is_null() {
-            panic!("Could not construct Headless backend");
+            None
+        } else {
+            Some(Headless { backend })
         }
-        Headless { backend }
     }
 
     /// Create a new headless output backed by an in-memory EGL framebuffer.
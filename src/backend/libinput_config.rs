@@ -0,0 +1,542 @@
+//! Configuration of libinput devices, e.g. tap-to-click and natural
+//! scrolling on touchpads.
+//!
+//! Get a `DeviceConfig` for an input device with `Libinput::device_config`:
+//!
+//! ```rust,no_run,ignore
+//! fn pointer_added(&mut self, compositor_handle: compositor::Handle,
+//!                  pointer_handle: pointer::Handle) -> Option<Box<pointer::Handler>> {
+//!     pointer_handle.run(|pointer| {
+//!         if let Ok(mut config) = Libinput::device_config(pointer.input_device()) {
+//!             let _ = config.set_tap_enabled(true);
+//!             let _ = config.set_natural_scroll(true);
+//!         }
+//!     }).unwrap();
+//!     // ...
+//! }
+//! ```
+
+use std::{error::Error, fmt, marker::PhantomData};
+
+use crate::libc::c_int;
+use wlroots_sys::{
+    libinput_config_accel_profile::*, libinput_config_click_method::*, libinput_config_dwt_state::*,
+    libinput_config_middle_emulation_state::*, libinput_config_scroll_method::*, libinput_config_status::*,
+    libinput_config_tap_state::*, libinput_device, libinput_device_config_accel_get_profiles,
+    libinput_device_config_accel_get_speed, libinput_device_config_accel_is_available,
+    libinput_device_config_accel_set_speed, libinput_device_config_click_get_methods,
+    libinput_device_config_dwt_is_available, libinput_device_config_left_handed_get,
+    libinput_device_config_left_handed_is_available, libinput_device_config_middle_emulation_is_available,
+    libinput_device_config_scroll_get_methods, libinput_device_config_scroll_get_natural_scroll_enabled,
+    libinput_device_config_scroll_has_natural_scroll, libinput_device_config_tap_get_finger_count,
+    wlr_input_device_is_libinput, wlr_libinput_get_device_handle
+};
+
+use self::raw::*;
+use crate::{backend::Libinput, input};
+
+/// The libinput functions taking or returning C enums, declared with plain
+/// integers instead of the bindgen enums.
+///
+/// libinput can return values newer than the headers the bindings were
+/// generated from, which must not end up in a Rust enum.
+mod raw {
+    use crate::libc::{c_double, c_int};
+    use wlroots_sys::libinput_device;
+
+    extern "C" {
+        #[link_name = "libinput_device_config_tap_get_enabled"]
+        pub fn tap_get_enabled(device: *mut libinput_device) -> u32;
+        #[link_name = "libinput_device_config_tap_set_enabled"]
+        pub fn tap_set_enabled(device: *mut libinput_device, state: u32) -> u32;
+        #[link_name = "libinput_device_config_scroll_set_natural_scroll_enabled"]
+        pub fn scroll_set_natural_scroll_enabled(device: *mut libinput_device, enabled: c_int) -> u32;
+        #[link_name = "libinput_device_config_accel_get_profile"]
+        pub fn accel_get_profile(device: *mut libinput_device) -> u32;
+        #[link_name = "libinput_device_config_accel_set_profile"]
+        pub fn accel_set_profile(device: *mut libinput_device, profile: u32) -> u32;
+        #[link_name = "libinput_device_config_accel_set_speed"]
+        pub fn accel_set_speed(device: *mut libinput_device, speed: c_double) -> u32;
+        #[link_name = "libinput_device_config_left_handed_set"]
+        pub fn left_handed_set(device: *mut libinput_device, left_handed: c_int) -> u32;
+        #[link_name = "libinput_device_config_scroll_get_method"]
+        pub fn scroll_get_method(device: *mut libinput_device) -> u32;
+        #[link_name = "libinput_device_config_scroll_set_method"]
+        pub fn scroll_set_method(device: *mut libinput_device, method: u32) -> u32;
+        #[link_name = "libinput_device_config_click_get_method"]
+        pub fn click_get_method(device: *mut libinput_device) -> u32;
+        #[link_name = "libinput_device_config_click_set_method"]
+        pub fn click_set_method(device: *mut libinput_device, method: u32) -> u32;
+        #[link_name = "libinput_device_config_dwt_get_enabled"]
+        pub fn dwt_get_enabled(device: *mut libinput_device) -> u32;
+        #[link_name = "libinput_device_config_dwt_set_enabled"]
+        pub fn dwt_set_enabled(device: *mut libinput_device, state: u32) -> u32;
+        #[link_name = "libinput_device_config_middle_emulation_get_enabled"]
+        pub fn middle_emulation_get_enabled(device: *mut libinput_device) -> u32;
+        #[link_name = "libinput_device_config_middle_emulation_set_enabled"]
+        pub fn middle_emulation_set_enabled(device: *mut libinput_device, state: u32) -> u32;
+    }
+}
+
+/// The pointer acceleration profile of a device.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum AccelProfile {
+    /// The device has no acceleration profile, acceleration is unsupported.
+    None,
+    /// A constant acceleration factor, set by the acceleration speed.
+    Flat,
+    /// An acceleration factor that depends on how fast the device moves.
+    Adaptive,
+    /// A profile newer than these bindings, with its raw libinput value.
+    Unknown(u32)
+}
+
+/// How a device generates scroll events.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum ScrollMethod {
+    /// The device never sends scroll events.
+    NoScroll,
+    /// Moving two fingers on a touchpad scrolls.
+    TwoFinger,
+    /// Moving a finger along the edges of a touchpad scrolls.
+    Edge,
+    /// Moving the device while holding down a button scrolls.
+    OnButtonDown,
+    /// A method newer than these bindings, with its raw libinput value.
+    Unknown(u32)
+}
+
+/// How a touchpad without physical buttons generates button events.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum ClickMethod {
+    /// Clicking does not generate button events.
+    None,
+    /// The button depends on the area of the touchpad that was clicked.
+    ButtonAreas,
+    /// The button depends on how many fingers clicked.
+    Clickfinger,
+    /// A method newer than these bindings, with its raw libinput value.
+    Unknown(u32)
+}
+
+/// Errors that can occur while configuring a device.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum DeviceConfigError {
+    /// The input device is not driven by libinput.
+    NotLibinput,
+    /// The device does not support the option.
+    Unsupported,
+    /// The value is out of range for the option.
+    Invalid,
+    /// libinput returned a status newer than these bindings, with its raw
+    /// value.
+    Unknown(u32)
+}
+
+/// The libinput configuration of an input device.
+///
+/// This borrows the input device, so it can't outlive it.
+#[derive(Debug)]
+pub struct DeviceConfig<'device> {
+    device: *mut libinput_device,
+    phantom: PhantomData<&'device input::Device>
+}
+
+impl Libinput {
+    /// Gets the libinput configuration of the input device.
+    ///
+    /// Fails with `DeviceConfigError::NotLibinput` if the device is not
+    /// from the libinput backend.
+    pub fn device_config(input_device: &input::Device) -> Result<DeviceConfig, DeviceConfigError> {
+        unsafe {
+            if !wlr_input_device_is_libinput(input_device.as_ptr()) {
+                return Err(DeviceConfigError::NotLibinput);
+            }
+            let device = wlr_libinput_get_device_handle(input_device.as_ptr());
+            if device.is_null() {
+                return Err(DeviceConfigError::NotLibinput);
+            }
+            Ok(DeviceConfig {
+                device,
+                phantom: PhantomData
+            })
+        }
+    }
+}
+
+impl<'device> DeviceConfig<'device> {
+    /// Gets the pointer to the `libinput_device`.
+    pub unsafe fn as_ptr(&self) -> *mut libinput_device {
+        self.device
+    }
+
+    /// Gets the number of fingers that can be used for tapping.
+    ///
+    /// A count of zero means the device does not support tapping.
+    pub fn tap_finger_count(&self) -> u32 {
+        unsafe { libinput_device_config_tap_get_finger_count(self.device).max(0) as u32 }
+    }
+
+    /// Determines if tapping generates button events.
+    pub fn tap_enabled(&self) -> bool {
+        unsafe { tap_get_enabled(self.device) == LIBINPUT_CONFIG_TAP_ENABLED as u32 }
+    }
+
+    /// Enables or disables tap-to-click.
+    pub fn set_tap_enabled(&mut self, enabled: bool) -> Result<(), DeviceConfigError> {
+        if self.tap_finger_count() == 0 {
+            return Err(DeviceConfigError::Unsupported);
+        }
+        let state = if enabled {
+            LIBINPUT_CONFIG_TAP_ENABLED
+        } else {
+            LIBINPUT_CONFIG_TAP_DISABLED
+        };
+        unsafe { check_status(tap_set_enabled(self.device, state as u32)) }
+    }
+
+    /// Determines if the device supports natural scrolling.
+    pub fn has_natural_scroll(&self) -> bool {
+        unsafe { libinput_device_config_scroll_has_natural_scroll(self.device) != 0 }
+    }
+
+    /// Determines if natural scrolling is enabled.
+    pub fn natural_scroll(&self) -> bool {
+        unsafe { libinput_device_config_scroll_get_natural_scroll_enabled(self.device) != 0 }
+    }
+
+    /// Enables or disables natural scrolling, which inverts the scroll
+    /// direction so the content follows the fingers.
+    pub fn set_natural_scroll(&mut self, enabled: bool) -> Result<(), DeviceConfigError> {
+        if !self.has_natural_scroll() {
+            return Err(DeviceConfigError::Unsupported);
+        }
+        unsafe { check_status(scroll_set_natural_scroll_enabled(self.device, enabled as c_int)) }
+    }
+
+    /// Determines if the device supports pointer acceleration.
+    pub fn has_accel(&self) -> bool {
+        unsafe { libinput_device_config_accel_is_available(self.device) != 0 }
+    }
+
+    /// Gets the acceleration profiles the device supports.
+    pub fn accel_profiles(&self) -> Vec<AccelProfile> {
+        let profiles = unsafe { libinput_device_config_accel_get_profiles(self.device) };
+        [AccelProfile::Flat, AccelProfile::Adaptive]
+            .iter()
+            .cloned()
+            .filter(|&profile| profiles & profile.as_raw() != 0)
+            .collect()
+    }
+
+    /// Gets the current acceleration profile.
+    pub fn accel_profile(&self) -> AccelProfile {
+        unsafe { AccelProfile::from_raw(accel_get_profile(self.device)) }
+    }
+
+    /// Changes the acceleration profile.
+    pub fn set_accel_profile(&mut self, profile: AccelProfile) -> Result<(), DeviceConfigError> {
+        if !self.accel_profiles().contains(&profile) {
+            return Err(DeviceConfigError::Unsupported);
+        }
+        unsafe { check_status(accel_set_profile(self.device, profile.as_raw())) }
+    }
+
+    /// Gets the acceleration speed, in the range [-1, 1].
+    pub fn accel_speed(&self) -> f64 {
+        unsafe { libinput_device_config_accel_get_speed(self.device) }
+    }
+
+    /// Changes the acceleration speed, which must be in the range [-1, 1].
+    ///
+    /// -1 is the slowest and 1 the fastest, 0 is the device's default.
+    pub fn set_accel_speed(&mut self, speed: f64) -> Result<(), DeviceConfigError> {
+        if !self.has_accel() {
+            return Err(DeviceConfigError::Unsupported);
+        }
+        if !(-1.0..=1.0).contains(&speed) {
+            return Err(DeviceConfigError::Invalid);
+        }
+        unsafe { check_status(accel_set_speed(self.device, speed)) }
+    }
+
+    /// Determines if the device supports left-handed mode.
+    pub fn has_left_handed(&self) -> bool {
+        unsafe { libinput_device_config_left_handed_is_available(self.device) != 0 }
+    }
+
+    /// Determines if left-handed mode is enabled.
+    pub fn left_handed(&self) -> bool {
+        unsafe { libinput_device_config_left_handed_get(self.device) != 0 }
+    }
+
+    /// Enables or disables left-handed mode, which swaps the left and right
+    /// buttons.
+    pub fn set_left_handed(&mut self, enabled: bool) -> Result<(), DeviceConfigError> {
+        if !self.has_left_handed() {
+            return Err(DeviceConfigError::Unsupported);
+        }
+        unsafe { check_status(left_handed_set(self.device, enabled as c_int)) }
+    }
+
+    /// Gets the scroll methods the device supports, other than
+    /// `ScrollMethod::NoScroll` which is always supported.
+    pub fn scroll_methods(&self) -> Vec<ScrollMethod> {
+        let methods = unsafe { libinput_device_config_scroll_get_methods(self.device) };
+        [
+            ScrollMethod::TwoFinger,
+            ScrollMethod::Edge,
+            ScrollMethod::OnButtonDown
+        ]
+        .iter()
+        .cloned()
+        .filter(|&method| methods & method.as_raw() != 0)
+        .collect()
+    }
+
+    /// Gets the current scroll method.
+    pub fn scroll_method(&self) -> ScrollMethod {
+        unsafe { ScrollMethod::from_raw(scroll_get_method(self.device)) }
+    }
+
+    /// Changes the scroll method.
+    pub fn set_scroll_method(&mut self, method: ScrollMethod) -> Result<(), DeviceConfigError> {
+        if method != ScrollMethod::NoScroll && !self.scroll_methods().contains(&method) {
+            return Err(DeviceConfigError::Unsupported);
+        }
+        unsafe { check_status(scroll_set_method(self.device, method.as_raw())) }
+    }
+
+    /// Gets the click methods the device supports, other than
+    /// `ClickMethod::None` which is always supported.
+    pub fn click_methods(&self) -> Vec<ClickMethod> {
+        let methods = unsafe { libinput_device_config_click_get_methods(self.device) };
+        [ClickMethod::ButtonAreas, ClickMethod::Clickfinger]
+            .iter()
+            .cloned()
+            .filter(|&method| methods & method.as_raw() != 0)
+            .collect()
+    }
+
+    /// Gets the current click method.
+    pub fn click_method(&self) -> ClickMethod {
+        unsafe { ClickMethod::from_raw(click_get_method(self.device)) }
+    }
+
+    /// Changes the click method.
+    pub fn set_click_method(&mut self, method: ClickMethod) -> Result<(), DeviceConfigError> {
+        if method != ClickMethod::None && !self.click_methods().contains(&method) {
+            return Err(DeviceConfigError::Unsupported);
+        }
+        unsafe { check_status(click_set_method(self.device, method.as_raw())) }
+    }
+
+    /// Determines if the device supports disabling itself while typing.
+    pub fn has_disable_while_typing(&self) -> bool {
+        unsafe { libinput_device_config_dwt_is_available(self.device) != 0 }
+    }
+
+    /// Determines if the device is disabled while typing.
+    pub fn disable_while_typing(&self) -> bool {
+        unsafe { dwt_get_enabled(self.device) == LIBINPUT_CONFIG_DWT_ENABLED as u32 }
+    }
+
+    /// Enables or disables ignoring the device while the user is typing.
+    pub fn set_disable_while_typing(&mut self, enabled: bool) -> Result<(), DeviceConfigError> {
+        if !self.has_disable_while_typing() {
+            return Err(DeviceConfigError::Unsupported);
+        }
+        let state = if enabled {
+            LIBINPUT_CONFIG_DWT_ENABLED
+        } else {
+            LIBINPUT_CONFIG_DWT_DISABLED
+        };
+        unsafe { check_status(dwt_set_enabled(self.device, state as u32)) }
+    }
+
+    /// Determines if the device supports middle button emulation.
+    pub fn has_middle_emulation(&self) -> bool {
+        unsafe { libinput_device_config_middle_emulation_is_available(self.device) != 0 }
+    }
+
+    /// Determines if middle button emulation is enabled.
+    pub fn middle_emulation(&self) -> bool {
+        unsafe {
+            middle_emulation_get_enabled(self.device) == LIBINPUT_CONFIG_MIDDLE_EMULATION_ENABLED as u32
+        }
+    }
+
+    /// Enables or disables middle button emulation, which generates a middle
+    /// click when the left and right buttons are pressed together.
+    pub fn set_middle_emulation(&mut self, enabled: bool) -> Result<(), DeviceConfigError> {
+        if !self.has_middle_emulation() {
+            return Err(DeviceConfigError::Unsupported);
+        }
+        let state = if enabled {
+            LIBINPUT_CONFIG_MIDDLE_EMULATION_ENABLED
+        } else {
+            LIBINPUT_CONFIG_MIDDLE_EMULATION_DISABLED
+        };
+        unsafe { check_status(middle_emulation_set_enabled(self.device, state as u32)) }
+    }
+}
+
+impl AccelProfile {
+    fn from_raw(profile: u32) -> Self {
+        match profile {
+            _ if profile == LIBINPUT_CONFIG_ACCEL_PROFILE_NONE as u32 => AccelProfile::None,
+            _ if profile == LIBINPUT_CONFIG_ACCEL_PROFILE_FLAT as u32 => AccelProfile::Flat,
+            _ if profile == LIBINPUT_CONFIG_ACCEL_PROFILE_ADAPTIVE as u32 => AccelProfile::Adaptive,
+            _ => AccelProfile::Unknown(profile)
+        }
+    }
+
+    fn as_raw(self) -> u32 {
+        match self {
+            AccelProfile::None => LIBINPUT_CONFIG_ACCEL_PROFILE_NONE as u32,
+            AccelProfile::Flat => LIBINPUT_CONFIG_ACCEL_PROFILE_FLAT as u32,
+            AccelProfile::Adaptive => LIBINPUT_CONFIG_ACCEL_PROFILE_ADAPTIVE as u32,
+            AccelProfile::Unknown(profile) => profile
+        }
+    }
+}
+
+impl ScrollMethod {
+    fn from_raw(method: u32) -> Self {
+        match method {
+            _ if method == LIBINPUT_CONFIG_SCROLL_NO_SCROLL as u32 => ScrollMethod::NoScroll,
+            _ if method == LIBINPUT_CONFIG_SCROLL_2FG as u32 => ScrollMethod::TwoFinger,
+            _ if method == LIBINPUT_CONFIG_SCROLL_EDGE as u32 => ScrollMethod::Edge,
+            _ if method == LIBINPUT_CONFIG_SCROLL_ON_BUTTON_DOWN as u32 => ScrollMethod::OnButtonDown,
+            _ => ScrollMethod::Unknown(method)
+        }
+    }
+
+    fn as_raw(self) -> u32 {
+        match self {
+            ScrollMethod::NoScroll => LIBINPUT_CONFIG_SCROLL_NO_SCROLL as u32,
+            ScrollMethod::TwoFinger => LIBINPUT_CONFIG_SCROLL_2FG as u32,
+            ScrollMethod::Edge => LIBINPUT_CONFIG_SCROLL_EDGE as u32,
+            ScrollMethod::OnButtonDown => LIBINPUT_CONFIG_SCROLL_ON_BUTTON_DOWN as u32,
+            ScrollMethod::Unknown(method) => method
+        }
+    }
+}
+
+impl ClickMethod {
+    fn from_raw(method: u32) -> Self {
+        match method {
+            _ if method == LIBINPUT_CONFIG_CLICK_METHOD_NONE as u32 => ClickMethod::None,
+            _ if method == LIBINPUT_CONFIG_CLICK_METHOD_BUTTON_AREAS as u32 => ClickMethod::ButtonAreas,
+            _ if method == LIBINPUT_CONFIG_CLICK_METHOD_CLICKFINGER as u32 => ClickMethod::Clickfinger,
+            _ => ClickMethod::Unknown(method)
+        }
+    }
+
+    fn as_raw(self) -> u32 {
+        match self {
+            ClickMethod::None => LIBINPUT_CONFIG_CLICK_METHOD_NONE as u32,
+            ClickMethod::ButtonAreas => LIBINPUT_CONFIG_CLICK_METHOD_BUTTON_AREAS as u32,
+            ClickMethod::Clickfinger => LIBINPUT_CONFIG_CLICK_METHOD_CLICKFINGER as u32,
+            ClickMethod::Unknown(method) => method
+        }
+    }
+}
+
+fn check_status(status: u32) -> Result<(), DeviceConfigError> {
+    match status {
+        _ if status == LIBINPUT_CONFIG_STATUS_SUCCESS as u32 => Ok(()),
+        _ if status == LIBINPUT_CONFIG_STATUS_UNSUPPORTED as u32 => Err(DeviceConfigError::Unsupported),
+        _ if status == LIBINPUT_CONFIG_STATUS_INVALID as u32 => Err(DeviceConfigError::Invalid),
+        _ => Err(DeviceConfigError::Unknown(status))
+    }
+}
+
+impl fmt::Display for DeviceConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DeviceConfigError::Unknown(status) => write!(f, "Unknown libinput status {}", status),
+            _ => write!(f, "{}", self.description())
+        }
+    }
+}
+
+impl Error for DeviceConfigError {
+    fn description(&self) -> &str {
+        use self::DeviceConfigError::*;
+        match *self {
+            NotLibinput => "Input device is not a libinput device",
+            Unsupported => "Option is not supported by the device",
+            Invalid => "Value is invalid for the option",
+            Unknown(_) => "Unknown libinput status"
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accel_profile_raw() {
+        for &profile in &[AccelProfile::None, AccelProfile::Flat, AccelProfile::Adaptive] {
+            assert_eq!(AccelProfile::from_raw(profile.as_raw()), profile);
+        }
+        assert_eq!(
+            AccelProfile::from_raw(LIBINPUT_CONFIG_ACCEL_PROFILE_FLAT as u32),
+            AccelProfile::Flat
+        );
+        // e.g. the custom profile of newer libinput versions.
+        assert_eq!(AccelProfile::from_raw(1 << 2), AccelProfile::Unknown(1 << 2));
+        assert_eq!(AccelProfile::Unknown(1 << 2).as_raw(), 1 << 2);
+    }
+
+    #[test]
+    fn scroll_method_raw() {
+        for &method in &[
+            ScrollMethod::NoScroll,
+            ScrollMethod::TwoFinger,
+            ScrollMethod::Edge,
+            ScrollMethod::OnButtonDown
+        ] {
+            assert_eq!(ScrollMethod::from_raw(method.as_raw()), method);
+        }
+        assert_eq!(
+            ScrollMethod::from_raw(LIBINPUT_CONFIG_SCROLL_ON_BUTTON_DOWN as u32),
+            ScrollMethod::OnButtonDown
+        );
+        assert_eq!(ScrollMethod::from_raw(1 << 3), ScrollMethod::Unknown(1 << 3));
+        assert_eq!(ScrollMethod::Unknown(1 << 3).as_raw(), 1 << 3);
+    }
+
+    #[test]
+    fn click_method_raw() {
+        for &method in &[
+            ClickMethod::None,
+            ClickMethod::ButtonAreas,
+            ClickMethod::Clickfinger
+        ] {
+            assert_eq!(ClickMethod::from_raw(method.as_raw()), method);
+        }
+        assert_eq!(
+            ClickMethod::from_raw(LIBINPUT_CONFIG_CLICK_METHOD_CLICKFINGER as u32),
+            ClickMethod::Clickfinger
+        );
+        assert_eq!(ClickMethod::from_raw(1 << 2), ClickMethod::Unknown(1 << 2));
+        assert_eq!(ClickMethod::Unknown(1 << 2).as_raw(), 1 << 2);
+    }
+
+    #[test]
+    fn status_raw() {
+        assert_eq!(check_status(LIBINPUT_CONFIG_STATUS_SUCCESS as u32), Ok(()));
+        assert_eq!(
+            check_status(LIBINPUT_CONFIG_STATUS_UNSUPPORTED as u32),
+            Err(DeviceConfigError::Unsupported)
+        );
+        assert_eq!(
+            check_status(LIBINPUT_CONFIG_STATUS_INVALID as u32),
+            Err(DeviceConfigError::Invalid)
+        );
+        assert_eq!(check_status(42), Err(DeviceConfigError::Unknown(42)));
+    }
+}
@@ -3,6 +3,7 @@ mod backend;
 mod drm;
 mod headless;
 mod libinput;
+mod libinput_config;
 mod multi;
 mod session;
 mod wayland;
@@ -12,6 +13,7 @@ pub use self::backend::*;
 pub use self::drm::*;
 pub use self::headless::*;
 pub use self::libinput::*;
+pub use self::libinput_config::*;
 pub use self::multi::*;
 pub use self::session::*;
 pub use self::wayland::*;
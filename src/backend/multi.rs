@@ -18,11 +18,21 @@ impl Multi {
         display: *mut wl_display,
         render_setup_func: Option<UnsafeRenderSetupFunction>
     ) -> Self {
+        Multi::try_auto_create(display, render_setup_func)
+            .unwrap_or_else(|| panic!("Could not auto construct backend"))
+    }
+
+    /// Like `auto_create`, but returns `None` if no backend could be created.
+    pub unsafe fn try_auto_create(
+        display: *mut wl_display,
+        render_setup_func: Option<UnsafeRenderSetupFunction>
+    ) -> Option<Self> {
         let backend = wlr_backend_autocreate(display, render_setup_func);
         if backend.is_null() {
-            panic!("Could not auto construct backend");
+            None
+        } else {
+            Some(Multi { backend })
         }
-        Multi { backend }
     }
 
     /// Adds the given backend to the multi backend.
@@ -34,6 +34,17 @@ impl Wayland {
         remote: Option<String>,
         render_setup_func: Option<UnsafeRenderSetupFunction>
     ) -> Self {
+        Wayland::try_new(display, remote, render_setup_func)
+            .unwrap_or_else(|| panic!("Could not construct Wayland backend"))
+    }
+
+    /// Like `new`, but returns `None` if the backend could not be created
+    /// (e.g. because the remote compositor is not running).
+    pub unsafe fn try_new(
+        display: *mut wl_display,
+        remote: Option<String>,
+        render_setup_func: Option<UnsafeRenderSetupFunction>
+    ) -> Option<Self> {
         let remote_cstr = remote.map(safe_as_cstring);
         let remote_ptr = remote_cstr
             .as_ref()
@@ -41,9 +52,10 @@ impl Wayland {
             .unwrap_or_else(|| ptr::null_mut());
         let backend = wlr_wl_backend_create(display, remote_ptr, render_setup_func);
         if backend.is_null() {
-            panic!("Could not construct Wayland backend");
+            None
+        } else {
+            Some(Wayland { backend })
         }
-        Wayland { backend }
     }
 
     /// Adds a new output to this backend.
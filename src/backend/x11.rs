@@ -28,6 +28,17 @@ impl X11 {
         x11_display: Option<String>,
         render_setup_func: Option<UnsafeRenderSetupFunction>
     ) -> Self {
+        X11::try_new(display, x11_display, render_setup_func)
+            .unwrap_or_else(|| panic!("Could not construct X11 backend"))
+    }
+
+    /// Like `new`, but returns `None` if the backend could not be created
+    /// (e.g. because the X server is not running).
+    pub unsafe fn try_new(
+        display: *mut wl_display,
+        x11_display: Option<String>,
+        render_setup_func: Option<UnsafeRenderSetupFunction>
+    ) -> Option<Self> {
         let x11_display_cstr = x11_display.map(safe_as_cstring);
         let x11_display_ptr = x11_display_cstr
             .as_ref()
@@ -35,9 +46,10 @@ impl X11 {
             .unwrap_or_else(|| ptr::null_mut());
         let backend = wlr_x11_backend_create(display, x11_display_ptr, render_setup_func);
         if backend.is_null() {
-            panic!("Could not construct X11 backend");
+            None
+        } else {
+            Some(X11 { backend })
         }
-        X11 { backend }
     }
 
     pub fn create_output(&self) -> Option<output::Handle> {
@@ -0,0 +1,247 @@
+//! Lets other threads run code on the compositor thread.
+//!
+//! Handles can't be sent to other threads, so a thread that needs the
+//! compositor to do something (e.g. one serving D-Bus or IPC requests)
+//! posts a closure through a `Proxy` instead. The closure is run on the
+//! event loop with the `Compositor`, and its result can be sent back:
+//!
+//! ```rust,no_run,ignore
+//! let commands = CommandQueue::new(&compositor.loop_handle())?;
+//! let proxy = commands.proxy();
+//! thread::spawn(move || {
+//!     let reply = proxy.call(|compositor| compositor.socket_name().to_string()).unwrap();
+//!     println!("Running on {}", reply.wait().unwrap());
+//!     proxy.send(|_| compositor::terminate()).unwrap();
+//! });
+//! ```
+//!
+//! Commands that have not been run when the queue is dropped are dropped
+//! without running. The queue stops running commands once the `Compositor`
+//! is destroyed.
+
+use std::{
+    error::Error,
+    fmt, io, mem,
+    sync::{mpsc, Arc, Mutex},
+    time::Duration
+};
+
+use crate::libc::{self, c_void};
+
+use crate::{
+    compositor::{self, Compositor},
+    event_loop::{clear_eventfd, EventLoop, FdEvents, FdSource}
+};
+
+type Command = Box<FnOnce(&mut Compositor) + Send>;
+
+/// Runs the commands posted by `Proxy`s on the event loop.
+///
+/// Dropping the queue drops all of the commands that have not been run, and
+/// makes posting more fail.
+pub struct CommandQueue {
+    _source: FdSource,
+    shared: Arc<Shared>
+}
+
+/// Posts commands to a `CommandQueue` from any thread.
+#[derive(Clone)]
+pub struct Proxy {
+    shared: Arc<Shared>
+}
+
+/// The result of a command posted with `Proxy::call`.
+#[derive(Debug)]
+pub struct Reply<R> {
+    receiver: mpsc::Receiver<R>
+}
+
+/// The ways posting a command or waiting for its result can fail.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum CommandError {
+    /// The command queue was dropped.
+    Disconnected,
+    /// The command was dropped without running, or it panicked.
+    Canceled
+}
+
+/// The state proxies have access to, from any thread.
+struct Shared {
+    /// The commands to run. `None` once the queue was dropped.
+    commands: Mutex<Option<Vec<Command>>>,
+    /// An eventfd used to wake up the event loop.
+    fd: libc::c_int
+}
+
+impl CommandQueue {
+    /// Makes a command queue that runs its commands on the given event loop.
+    pub fn new(event_loop: &EventLoop) -> io::Result<Self> {
+        let fd = unsafe { libc::eventfd(0, libc::EFD_CLOEXEC | libc::EFD_NONBLOCK) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let shared = Arc::new(Shared {
+            commands: Mutex::new(Some(Vec::new())),
+            fd
+        });
+        let source_shared = shared.clone();
+        let source = event_loop.add_fd(fd, FdEvents::READABLE, move |compositor_handle, fd, _| {
+            clear_eventfd(fd);
+            source_shared.run_pending(compositor_handle)
+        });
+        match source {
+            Some(source) => Ok(CommandQueue {
+                _source: source,
+                shared
+            }),
+            None => Err(io::Error::new(
+                io::ErrorKind::Other,
+                "Could not add the command queue to the event loop"
+            ))
+        }
+    }
+
+    /// Makes a proxy posting commands to this queue.
+    pub fn proxy(&self) -> Proxy {
+        Proxy {
+            shared: self.shared.clone()
+        }
+    }
+}
+
+impl Drop for CommandQueue {
+    fn drop(&mut self) {
+        let commands = match self.shared.commands.lock() {
+            Ok(mut commands) => commands.take(),
+            Err(_) => None
+        };
+        // NOTE Dropped outside of the lock, as dropping a command could post
+        // another one.
+        drop(commands)
+    }
+}
+
+impl Proxy {
+    /// Posts a command to run on the compositor thread.
+    ///
+    /// Fails if the command queue was dropped.
+    pub fn send<F>(&self, command: F) -> Result<(), CommandError>
+    where
+        F: FnOnce(&mut Compositor) + Send + 'static
+    {
+        self.shared.push(Box::new(command))
+    }
+
+    /// Posts a command to run on the compositor thread, returning a `Reply`
+    /// its result is sent back through.
+    ///
+    /// Fails if the command queue was dropped.
+    pub fn call<F, R>(&self, command: F) -> Result<Reply<R>, CommandError>
+    where
+        F: FnOnce(&mut Compositor) -> R + Send + 'static,
+        R: Send + 'static
+    {
+        let (sender, receiver) = mpsc::sync_channel(1);
+        self.send(move |compositor| {
+            // The reply might have been dropped, in which case the result is
+            // not needed.
+            sender.send(command(compositor)).ok();
+        })?;
+        Ok(Reply { receiver })
+    }
+}
+
+impl fmt::Debug for Proxy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Proxy to eventfd {}", self.shared.fd)
+    }
+}
+
+impl<R> Reply<R> {
+    /// Blocks until the command has run, returning its result.
+    ///
+    /// This must not be called on the compositor thread, as the command can
+    /// only run once control returns to the event loop.
+    pub fn wait(self) -> Result<R, CommandError> {
+        self.receiver.recv().map_err(|_| CommandError::Canceled)
+    }
+
+    /// Like `wait`, but gives up after `timeout`, returning `None`.
+    pub fn wait_timeout(&self, timeout: Duration) -> Result<Option<R>, CommandError> {
+        match self.receiver.recv_timeout(timeout) {
+            Ok(result) => Ok(Some(result)),
+            Err(mpsc::RecvTimeoutError::Timeout) => Ok(None),
+            Err(mpsc::RecvTimeoutError::Disconnected) => Err(CommandError::Canceled)
+        }
+    }
+
+    /// Gets the result of the command without blocking, returning `None` if
+    /// it has not run yet.
+    pub fn try_get(&self) -> Result<Option<R>, CommandError> {
+        match self.receiver.try_recv() {
+            Ok(result) => Ok(Some(result)),
+            Err(mpsc::TryRecvError::Empty) => Ok(None),
+            Err(mpsc::TryRecvError::Disconnected) => Err(CommandError::Canceled)
+        }
+    }
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CommandError::Disconnected => write!(f, "The command queue was dropped"),
+            CommandError::Canceled => write!(f, "The command was dropped without running")
+        }
+    }
+}
+
+impl Error for CommandError {
+    fn description(&self) -> &str {
+        match *self {
+            CommandError::Disconnected => "The command queue was dropped",
+            CommandError::Canceled => "The command was dropped without running"
+        }
+    }
+}
+
+impl Shared {
+    fn push(&self, command: Command) -> Result<(), CommandError> {
+        {
+            let mut commands = self.commands.lock().map_err(|_| CommandError::Disconnected)?;
+            match *commands {
+                Some(ref mut commands) => commands.push(command),
+                None => return Err(CommandError::Disconnected)
+            }
+        }
+        let value: u64 = 1;
+        unsafe {
+            libc::write(self.fd, &value as *const u64 as *const c_void, 8);
+        }
+        Ok(())
+    }
+
+    /// Runs the commands posted so far.
+    ///
+    /// Commands posted while they run are left for the next wake up.
+    fn run_pending(&self, compositor_handle: compositor::Handle) {
+        let commands = match self.commands.lock() {
+            Ok(mut commands) => commands
+                .as_mut()
+                .map(|commands| mem::replace(commands, Vec::new())),
+            Err(_) => return
+        };
+        for command in commands.unwrap_or_default() {
+            if let Err(err) = compositor_handle.run(|compositor| command(compositor)) {
+                wlr_log!(WLR_ERROR, "Could not run command on the compositor: {:?}", err)
+            }
+        }
+    }
+}
+
+impl Drop for Shared {
+    fn drop(&mut self) {
+        unsafe {
+            libc::close(self.fd);
+        }
+    }
+}
@@ -4,24 +4,25 @@
 use std::{
     any::Any,
     cell::{Cell, UnsafeCell},
-    env,
+    error::Error,
     ffi::CStr,
-    panic,
+    fmt, panic,
     ptr::{self, NonNull},
-    rc::{Rc, Weak},
-    sync::atomic::{AtomicBool, Ordering}
+    rc::{Rc, Weak}
 };
 
 use crate::libc;
 use crate::wayland_sys::server::{signal::wl_signal_add, wl_display, wl_event_loop, WAYLAND_SERVER_HANDLE};
 use wlroots_sys::{
-    wlr_backend_destroy, wlr_backend_start, wlr_compositor, wlr_compositor_create, wlr_compositor_destroy,
-    wlr_xdg_shell, wlr_xdg_shell_create, wlr_xdg_shell_v6, wlr_xdg_shell_v6_create
+    wlr_backend, wlr_backend_destroy, wlr_backend_start, wlr_compositor, wlr_compositor_create,
+    wlr_compositor_destroy, wlr_xdg_shell, wlr_xdg_shell_create, wlr_xdg_shell_v6, wlr_xdg_shell_v6_create
 };
 
+#[cfg(feature = "output_power_management")]
+use crate::extensions::output_power_management;
 use crate::{
     backend::{self, Backend, Session, UnsafeRenderSetupFunction},
-    data_device,
+    cursor, data_device, dmabuf, event_loop,
     extensions::{
         gamma_control, gtk_primary_selection, idle, idle_inhibit, input_inhibit, screencopy, screenshooter,
         server_decoration
@@ -34,32 +35,35 @@ use crate::{
     xwayland
 };
 
-/// Global compositor pointer, used to refer to the compositor state unsafely.
-pub(crate) static mut COMPOSITOR_PTR: *mut Compositor = 0 as *mut _;
-
-/// Callback that's triggered when a surface is provided to the compositor.
-pub type NewSurface = fn(compositor_handle: Handle, surface_handle: surface::Handle);
+thread_local! {
+    /// The compositor running on this thread, used to refer to the compositor
+    /// state unsafely.
+    ///
+    /// This is thread local so that each thread can run its own compositor.
+    static COMPOSITOR_PTR: Cell<*mut Compositor> = Cell::new(ptr::null_mut());
+}
 
-/// Callback that's triggered during shutdown.
-pub type OnShutdown = fn();
+callback_trait! {
+    /// Callback that's triggered when a surface is provided to the compositor.
+    pub trait NewSurface = FnMut(compositor_handle: Handle, surface_handle: surface::Handle);
+}
 
-/// A check to ensure that we only have one builder at a time.
-/// This is necessary because it uses global state to keep track
-/// of callback pointers.
-///
-/// Once the builder has been built with `Build` then this will
-/// only be set to false once the `Compositor` is dropped.
-static mut BUILDER_ACTIVE: AtomicBool = AtomicBool::new(false);
+callback_trait! {
+    /// Callback that's triggered during shutdown.
+    pub trait OnShutdown = FnMut();
+}
 
 wayland_listener_static! {
-    static mut INTERNAL_COMPOSITOR;
     (InternalCompositor, EventBuilder): [
         (NewSurface, new_surface_listener, surface_added) => (add_notify, surface_added):
         |handler: &mut InternalCompositor, data: *mut libc::c_void,| unsafe {
             let surface_ptr = data as _;
-            let compositor = (&*COMPOSITOR_PTR).weak_reference();
+            let compositor = match handle() {
+                Some(handle) => handle,
+                None => return
+            };
             let surface = Surface::new(surface_ptr);
-            if let Some(surface_added) = handler.surface_added {
+            if let Some(ref mut surface_added) = handler.surface_added {
                 surface_added(compositor.clone(), surface.weak_reference())
             }
             let mut internal_surface = InternalSurface::new((surface, Box::new(())));
@@ -75,7 +79,9 @@ wayland_listener_static! {
 
         (OnShutdown, shutdown_listener, on_shutdown) => (shutdown_notify, on_shutdown):
         |handler: &mut InternalCompositor, _data: *mut libc::c_void,| unsafe {
-            handler.on_shutdown.map(|f| f())
+            // The wlr_compositor is being destroyed along with its signals.
+            handler.remove_listeners();
+            handler.on_shutdown.as_mut().map(|f| f())
         };
     ]
 }
@@ -95,15 +101,15 @@ pub struct Compositor {
     /// User data.
     pub data: Box<Any>,
     /// Internal compositor handler
-    compositor_handler: Option<&'static mut InternalCompositor>,
+    compositor_handler: Option<Box<InternalCompositor>>,
     /// Manager for the inputs.
-    input_manager: Option<&'static mut input::Manager>,
+    input_manager: Option<Box<input::Manager>>,
     /// Manager for the outputs.
-    output_manager: Option<&'static mut output::Manager>,
+    output_manager: Option<Box<output::Manager>>,
     /// Manager for stable XDG shells.
-    xdg_shell_manager: Option<&'static mut xdg_shell::Manager>,
+    xdg_shell_manager: Option<Box<xdg_shell::Manager>>,
     /// Manager for XDG shells v6.
-    xdg_v6_shell_manager: Option<&'static mut xdg_shell_v6::Manager>,
+    xdg_v6_shell_manager: Option<Box<xdg_shell_v6::Manager>>,
     /// Pointer to the xdg_shell global.
     /// If xdg_shell_manager is `None`, this value will be `NULL`.
     xdg_shell_global: *mut wlr_xdg_shell,
@@ -118,6 +124,9 @@ pub struct Compositor {
     pub display: *mut wl_display,
     /// Pointer to the event loop.
     pub event_loop: *mut wl_event_loop,
+    /// Dropped when the event loop is destroyed, so the event sources know
+    /// not to remove themselves from it.
+    event_loop_liveliness: Rc<()>,
     /// Shared memory buffer file descriptor. If the feature was not activated,
     /// this will be None.
     wl_shm_fd: Option<i32>,
@@ -133,12 +142,17 @@ pub struct Compositor {
     pub idle_inhibit_manager: Option<idle_inhibit::ZManagerV1>,
     /// Optional input inhibit manager extension.
     pub input_inhibit_manager: Option<input_inhibit::ZManagerV1>,
+    /// Optional output power management extension
+    #[cfg(feature = "output_power_management")]
+    pub output_power_manager: Option<output_power_management::ZManagerV1>,
     /// Optional GTK primary selection manager
     pub gtk_primary_selection_manager: Option<gtk_primary_selection::Manager>,
     /// Optional screencopy manager extension
     pub screencopy_manager: Option<screencopy::ZManagerV1>,
     /// Optional screenshooter manager extension
     pub screenshooter: Option<screenshooter::Screenshooter>,
+    /// Optional linux-dmabuf extension
+    pub linux_dmabuf: Option<dmabuf::Dmabuf>,
     /// The renderer used to draw things to the screen.
     pub renderer: Option<GenericRenderer>,
     /// XWayland server, only Some if it is enabled
@@ -159,6 +173,7 @@ pub struct Compositor {
 pub struct Builder {
     compositor_event_builder: Option<EventBuilder>,
     input_manager_builder: Option<input::manager::Builder>,
+    device_rules: Option<(input::rules::DeviceRules, cursor::Handle, output::layout::Handle)>,
     output_manager_builder: Option<output::manager::Builder>,
     xdg_shell_manager_builder: Option<xdg_shell::manager::Builder>,
     xdg_v6_shell_manager_builder: Option<xdg_shell_v6::manager::Builder>,
@@ -170,9 +185,12 @@ pub struct Builder {
     idle_manager: bool,
     idle_inhibit_manager: bool,
     input_inhibit_manager: bool,
+    #[cfg(feature = "output_power_management")]
+    output_power_manager: Option<Box<output_power_management::Handler>>,
     gtk_primary_selection_manager: bool,
     screencopy_manager: bool,
     screenshooter: bool,
+    linux_dmabuf: bool,
     wayland_remote: Option<String>,
     x11_display: Option<String>,
     data_device_manager: bool,
@@ -180,27 +198,52 @@ pub struct Builder {
     user_terminate: Option<fn()>
 }
 
+/// The stage at which building or starting the compositor failed.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum BuildError {
+    /// The Wayland display could not be created.
+    Display,
+    /// The session could not be opened.
+    Session,
+    /// The backend could not be created.
+    Backend,
+    /// The GLES2 renderer could not be created.
+    Renderer,
+    /// The `wl_compositor` global could not be created.
+    Compositor,
+    /// The global of an optional extension could not be created. Contains
+    /// the name of the `Builder` option enabling it, e.g. `"idle_manager"`.
+    Global(&'static str),
+    /// An extension was enabled without the GLES2 renderer it requires.
+    /// Contains the name of the `Builder` option enabling it, e.g.
+    /// `"linux_dmabuf"`.
+    RendererRequired(&'static str),
+    /// The XWayland server could not be started.
+    Xwayland,
+    /// The socket clients connect to could not be opened.
+    Socket,
+    /// Another compositor is already running on this thread.
+    AlreadyRunning,
+    /// The backend could not be started.
+    BackendStart
+}
+
+/// Destroys the display and backend if building the compositor fails
+/// part way.
+struct BuildGuard {
+    display: *mut wl_display,
+    backend: *mut wlr_backend,
+    renderer: Option<GenericRenderer>
+}
+
 impl Builder {
     /// Make a new compositor builder.
     ///
     /// Unless otherwise noted, each option is `false`/`None`.
     ///
-    /// # Panicking
-    /// There can only be one `compositor::Builder` per process. If you
-    /// construct a `compositor::Builder` with any of the `build` operations
-    /// then another `compositor::Builder` cannot be constructed until the
-    /// built `Compositor` is dropped.
-    ///
-    /// This requirement is enforced by a check that will panic if this
-    /// constraint is broken. This applies across threads.
+    /// Several compositors can be built in the same process, but only one of
+    /// them can run on each thread at a time.
     pub fn new() -> Self {
-        unsafe {
-            assert_eq!(
-                BUILDER_ACTIVE.compare_and_swap(false, true, Ordering::AcqRel),
-                false,
-                "A compositor builder already exists or has already been built"
-            );
-        }
         Builder::default()
     }
 
@@ -216,6 +259,25 @@ impl Builder {
         self
     }
 
+    /// Applies the rules to input devices as they are added, before the
+    /// callbacks of the input manager are called.
+    ///
+    /// Pointers, touch devices and tablet tools get their libinput settings
+    /// before `PointerAdded`, `TouchAdded` and `TabletToolAdded`, and are
+    /// then mapped to the output of their rule in the layout, using the
+    /// cursor the callbacks attached them to. Keyboards get their keymap
+    /// before `KeyboardAdded`, and other devices get their libinput settings
+    /// before `InputAdded`.
+    pub fn device_rules(
+        mut self,
+        device_rules: input::rules::DeviceRules,
+        cursor_handle: cursor::Handle,
+        layout_handle: output::layout::Handle
+    ) -> Self {
+        self.device_rules = Some((device_rules, cursor_handle, layout_handle));
+        self
+    }
+
     /// Set callbacks for managing output resources.
     pub fn output_manager(mut self, output_manager_builder: output::manager::Builder) -> Self {
         self.output_manager_builder = Some(output_manager_builder);
@@ -295,6 +357,23 @@ impl Builder {
         self
     }
 
+    /// Enable the output power management protocol extension, using the
+    /// handler to decide if client requests to power outputs on and off are
+    /// allowed.
+    ///
+    /// To allow every request, use `Box::new(())` as the handler.
+    ///
+    /// Only available with the `output_power_management` feature, which
+    /// needs a wlroots providing `wlr_output_power_management_v1.h`.
+    #[cfg(feature = "output_power_management")]
+    pub fn output_power_manager(
+        mut self,
+        output_power_handler: Box<output_power_management::Handler>
+    ) -> Self {
+        self.output_power_manager = Some(output_power_handler);
+        self
+    }
+
     /// Decide whether or not to enable the GTK primary selection manager
     /// protocol extension.
     pub fn gtk_primary_selection_manager(mut self, gtk_primary_selection_manager: bool) -> Self {
@@ -316,6 +395,16 @@ impl Builder {
         self
     }
 
+    /// Decide whether or not to enable the linux-dmabuf protocol
+    /// extension.
+    ///
+    /// This requires the GLES2 renderer to be enabled, otherwise building
+    /// the compositor fails with `BuildError::RendererRequired`.
+    pub fn linux_dmabuf(mut self, linux_dmabuf: bool) -> Self {
+        self.linux_dmabuf = linux_dmabuf;
+        self
+    }
+
     /// Set callbacks for managing XDG shell v6 resources.
     ///
     /// If this function is not called then the xwayland server does not run.
@@ -343,18 +432,29 @@ impl Builder {
     ///
     /// Also automatically opens the socket for clients to communicate to the
     /// compositor with.
+    ///
+    /// # Panicking
+    /// Panics if any stage of the setup fails, see `try_build_auto` for a
+    /// fallible version.
     pub fn build_auto<D>(self, data: D) -> Compositor
+    where
+        D: Any + 'static
+    {
+        self.try_build_auto(data).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Like `build_auto`, but returns the stage that failed instead of
+    /// panicking.
+    pub fn try_build_auto<D>(self, data: D) -> Result<Compositor, BuildError>
     where
         D: Any + 'static
     {
         unsafe {
-            let display = ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_display_create,) as *mut wl_display;
-            let event_loop = ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_display_get_event_loop, display);
-            let backend = Backend::Multi(backend::Multi::auto_create(
-                display as *mut _,
-                self.render_setup_function
-            ));
-            self.finish_build(data, display, event_loop, backend)
+            let guard = BuildGuard::new()?;
+            let backend =
+                backend::Multi::try_auto_create(guard.display as *mut _, self.render_setup_function)
+                    .ok_or(BuildError::Backend)?;
+            self.try_finish_build(data, guard, Backend::Multi(backend))
         }
     }
 
@@ -374,19 +474,28 @@ impl Builder {
         self
     }
 
-    pub fn build_x11<D>(mut self, data: D) -> Compositor
+    pub fn build_x11<D>(self, data: D) -> Compositor
+    where
+        D: Any + 'static
+    {
+        self.try_build_x11(data).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Like `build_x11`, but returns the stage that failed instead of
+    /// panicking.
+    pub fn try_build_x11<D>(mut self, data: D) -> Result<Compositor, BuildError>
     where
         D: Any + 'static
     {
         unsafe {
-            let display = ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_display_create,) as *mut wl_display;
-            let event_loop = ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_display_get_event_loop, display);
-            let backend = Backend::X11(backend::X11::new(
-                display as *mut _,
+            let guard = BuildGuard::new()?;
+            let backend = backend::X11::try_new(
+                guard.display as *mut _,
                 self.x11_display.take(),
                 self.render_setup_function
-            ));
-            self.finish_build(data, display, event_loop, backend)
+            )
+            .ok_or(BuildError::Backend)?;
+            self.try_finish_build(data, guard, Backend::X11(backend))
         }
     }
 
@@ -394,19 +503,29 @@ impl Builder {
     /// backend.
     ///
     /// The instance starts with no outputs.
-    pub fn build_wayland<D>(mut self, data: D) -> Compositor
+    pub fn build_wayland<D>(self, data: D) -> Compositor
+    where
+        D: Any + 'static
+    {
+        self.try_build_wayland(data)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Like `build_wayland`, but returns the stage that failed instead of
+    /// panicking.
+    pub fn try_build_wayland<D>(mut self, data: D) -> Result<Compositor, BuildError>
     where
         D: Any + 'static
     {
         unsafe {
-            let display = ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_display_create,) as *mut wl_display;
-            let event_loop = ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_display_get_event_loop, display);
-            let backend = Backend::Wayland(backend::Wayland::new(
-                display as *mut _,
+            let guard = BuildGuard::new()?;
+            let backend = backend::Wayland::try_new(
+                guard.display as *mut _,
                 self.wayland_remote.take(),
                 self.render_setup_function
-            ));
-            self.finish_build(data, display, event_loop, backend)
+            )
+            .ok_or(BuildError::Backend)?;
+            self.try_finish_build(data, guard, Backend::Wayland(backend))
         }
     }
 
@@ -420,115 +539,223 @@ impl Builder {
     where
         D: Any + 'static
     {
-        unsafe {
-            let display = ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_display_create,) as *mut wl_display;
-            let event_loop = ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_display_get_event_loop, display);
-            let backend = Backend::DRM(backend::Drm::new(
-                display as *mut _,
-                session,
-                gpu_fd,
-                parent,
-                self.render_setup_function
-            ));
-            self.finish_build(data, display, event_loop, backend)
-        }
+        let guard = BuildGuard::new().unwrap_or_else(|err| panic!("{}", err));
+        self.try_build_drm_with(data, guard, session, gpu_fd, parent)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Like `build_drm`, but opens the session itself and returns the stage
+    /// that failed instead of panicking.
+    ///
+    /// See `Session::new` for the requirements on opening a session.
+    pub unsafe fn try_build_drm<D>(
+        self,
+        data: D,
+        gpu_fd: libc::c_int,
+        parent: Option<backend::Drm>
+    ) -> Result<Compositor, BuildError>
+    where
+        D: Any + 'static
+    {
+        let guard = BuildGuard::new()?;
+        // The session is destroyed along with the display, so it is cleaned
+        // up by the guard whichever later stage fails.
+        let session = Session::new(guard.display).ok_or(BuildError::Session)?;
+        self.try_build_drm_with(data, guard, session, gpu_fd, parent)
+    }
+
+    unsafe fn try_build_drm_with<D>(
+        self,
+        data: D,
+        guard: BuildGuard,
+        session: Session,
+        gpu_fd: libc::c_int,
+        parent: Option<backend::Drm>
+    ) -> Result<Compositor, BuildError>
+    where
+        D: Any + 'static
+    {
+        let backend = backend::Drm::try_new(
+            guard.display as *mut _,
+            session,
+            gpu_fd,
+            parent,
+            self.render_setup_function
+        )
+        .ok_or(BuildError::Backend)?;
+        self.try_finish_build(data, guard, Backend::DRM(backend))
     }
 
     pub fn build_headless<D>(self, data: D) -> Compositor
+    where
+        D: Any + 'static
+    {
+        self.try_build_headless(data)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Like `build_headless`, but returns the stage that failed instead of
+    /// panicking.
+    pub fn try_build_headless<D>(self, data: D) -> Result<Compositor, BuildError>
     where
         D: Any + 'static
     {
         unsafe {
-            let display = ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_display_create,) as *mut wl_display;
-            let event_loop = ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_display_get_event_loop, display);
-            let backend = Backend::Headless(backend::Headless::new(
-                display as *mut _,
-                self.render_setup_function
-            ));
-            self.finish_build(data, display, event_loop, backend)
+            let guard = BuildGuard::new()?;
+            let backend = backend::Headless::try_new(guard.display as *mut _, self.render_setup_function)
+                .ok_or(BuildError::Backend)?;
+            self.try_finish_build(data, guard, Backend::Headless(backend))
         }
     }
 
-    unsafe fn finish_build<D>(
+    unsafe fn try_finish_build<D>(
         mut self,
         data: D,
-        display: *mut wl_display,
-        event_loop: *mut wl_event_loop,
+        mut guard: BuildGuard,
         backend: Backend
-    ) -> Compositor
+    ) -> Result<Compositor, BuildError>
     where
         D: Any + 'static
     {
+        guard.backend = backend.as_ptr();
+        let display = guard.display;
+        let event_loop = ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_display_get_event_loop, display);
+
         // Set up the wl_compositor and wl_subcompositor globals,
         // along with gles2 if that was enabled.
-        let (compositor, renderer) = if self.gles2 {
-            let gles2 = GenericRenderer::gles2_renderer(backend.as_ptr());
-            (
-                wlr_compositor_create(display as *mut _, gles2.as_ptr()),
-                Some(gles2)
-            )
+        let renderer_ptr = if self.gles2 {
+            let gles2 = GenericRenderer::try_gles2_renderer(backend.as_ptr()).ok_or(BuildError::Renderer)?;
+            let renderer_ptr = gles2.as_ptr();
+            guard.renderer = Some(gles2);
+            renderer_ptr
         } else {
-            (wlr_compositor_create(display as *mut _, ptr::null_mut()), None)
+            ptr::null_mut()
         };
+        let compositor = wlr_compositor_create(display as *mut _, renderer_ptr);
+        if compositor.is_null() {
+            return Err(BuildError::Compositor);
+        }
 
         // Set up shared memory buffer for Wayland clients.
         let wl_shm_fd = if self.wl_shm {
-            Some(ffi_dispatch!(
-                WAYLAND_SERVER_HANDLE,
-                wl_display_init_shm,
-                display as *mut _
-            ))
+            let result = ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_display_init_shm, display as *mut _);
+            if result != 0 {
+                return Err(BuildError::Global("wl_shm"));
+            }
+            Some(result)
         } else {
             None
         };
 
         // Create optional extensions.
         let server_decoration_manager = if self.server_decoration_manager {
-            server_decoration::Manager::new(display)
+            Some(
+                server_decoration::Manager::new(display)
+                    .ok_or(BuildError::Global("server_decoration_manager"))?
+            )
         } else {
             None
         };
         let gamma_control_manager = if self.gamma_control_manager {
-            gamma_control::ZManagerV1::new(display)
+            Some(gamma_control::ZManagerV1::new(display).ok_or(BuildError::Global("gamma_control_manager"))?)
         } else {
             None
         };
         let idle_manager = if self.idle_manager {
-            idle::Manager::new(display)
+            Some(idle::Manager::new(display).ok_or(BuildError::Global("idle_manager"))?)
         } else {
             None
         };
         let idle_inhibit_manager = if self.idle_inhibit_manager {
-            idle_inhibit::ZManagerV1::new(display)
+            Some(idle_inhibit::ZManagerV1::new(display).ok_or(BuildError::Global("idle_inhibit_manager"))?)
         } else {
             None
         };
         let input_inhibit_manager = if self.input_inhibit_manager {
-            input_inhibit::ZManagerV1::new(display)
+            Some(input_inhibit::ZManagerV1::new(display).ok_or(BuildError::Global("input_inhibit_manager"))?)
         } else {
             None
         };
+        #[cfg(feature = "output_power_management")]
+        let output_power_manager = match self.output_power_manager.take() {
+            Some(handler) => Some(
+                output_power_management::ZManagerV1::new(display, handler)
+                    .ok_or(BuildError::Global("output_power_manager"))?
+            ),
+            None => None
+        };
         let gtk_primary_selection_manager = if self.gtk_primary_selection_manager {
-            gtk_primary_selection::Manager::new(display)
+            Some(
+                gtk_primary_selection::Manager::new(display)
+                    .ok_or(BuildError::Global("gtk_primary_selection_manager"))?
+            )
         } else {
             None
         };
         let screencopy_manager = if self.screencopy_manager {
-            screencopy::ZManagerV1::new(display)
+            Some(screencopy::ZManagerV1::new(display).ok_or(BuildError::Global("screencopy_manager"))?)
         } else {
             None
         };
         let screenshooter = if self.screenshooter {
-            screenshooter::Screenshooter::new(display)
+            Some(screenshooter::Screenshooter::new(display).ok_or(BuildError::Global("screenshooter"))?)
         } else {
             None
         };
+        let linux_dmabuf = match (self.linux_dmabuf, guard.renderer.as_ref()) {
+            (true, Some(renderer)) => Some(
+                dmabuf::Dmabuf::from_renderer(display, renderer).ok_or(BuildError::Global("linux_dmabuf"))?
+            ),
+            (true, None) => return Err(BuildError::RendererRequired("linux_dmabuf")),
+            (false, _) => None
+        };
         let data_device_manager = if self.data_device_manager {
-            data_device::Manager::new(display as _)
+            Some(data_device::Manager::new(display as _).ok_or(BuildError::Global("data_device"))?)
         } else {
             None
         };
 
+        // Create the xdg_shell globals, if the user provided a manager for
+        // them.
+        let xdg_shell_global = if self.xdg_shell_manager_builder.is_some() {
+            let global = wlr_xdg_shell_create(display as *mut _);
+            if global.is_null() {
+                return Err(BuildError::Global("xdg_shell"));
+            }
+            global
+        } else {
+            ptr::null_mut()
+        };
+        let xdg_v6_shell_global = if self.xdg_v6_shell_manager_builder.is_some() {
+            let global = wlr_xdg_shell_v6_create(display as *mut _);
+            if global.is_null() {
+                return Err(BuildError::Global("xdg_shell_v6"));
+            }
+            global
+        } else {
+            ptr::null_mut()
+        };
+
+        // Set up the XWayland server, if the user wants it.
+        let xwayland = match self.xwayland.take() {
+            Some(builder) => Some(
+                xwayland::Server::try_new(display as _, compositor, builder, false)
+                    .ok_or(BuildError::Xwayland)?
+            ),
+            None => None
+        };
+
+        // Open the socket to the Wayland server.
+        let socket = ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_display_add_socket_auto, display);
+        if socket.is_null() {
+            return Err(BuildError::Socket);
+        }
+        let socket_name = CStr::from_ptr(socket).to_string_lossy().into_owned();
+
+        // Nothing can fail past this point.
+        let renderer = guard.renderer.take();
+        std::mem::forget(guard);
+
         // Set up compositor event callbacks, if the user provided it.
         let compositor_handler = self.compositor_event_builder.take()
             // NOTE if it's not defined, we still need to have it execute
@@ -536,9 +763,9 @@ impl Builder {
             .or_else(|| Some(EventBuilder::default()))
             .map(|mut builder| {
                 if builder.surface_added.is_none() {
-                    builder = builder.surface_added(|_,_|{});
+                    builder = builder.surface_added(|_: Handle, _: surface::Handle| {});
                 }
-                let compositor_handler = InternalCompositor::build(builder);
+                let mut compositor_handler = InternalCompositor::build(builder);
                 wl_signal_add(&mut (*compositor).events.new_surface as *mut _ as _,
                               (&mut compositor_handler.new_surface_listener) as *mut _ as _);
                 wl_signal_add(&mut (*compositor).events.destroy as *mut _ as _,
@@ -546,9 +773,14 @@ impl Builder {
                 compositor_handler
         });
 
-        // Set up input manager, if the user provided it.
-        let input_manager = self.input_manager_builder.take().map(|builder| {
-            let input_manager = input::Manager::build(builder);
+        // Set up input manager, if the user provided it or device rules.
+        let mut input_manager_builder = self.input_manager_builder.take();
+        if let Some((device_rules, cursor_handle, layout_handle)) = self.device_rules.take() {
+            let builder = input_manager_builder.unwrap_or_default();
+            input_manager_builder = Some(device_rules.wrap_callbacks(builder, cursor_handle, layout_handle));
+        }
+        let input_manager = input_manager_builder.map(|builder| {
+            let mut input_manager = input::Manager::build(builder);
             wl_signal_add(
                 &mut (*backend.as_ptr()).events.new_input as *mut _ as _,
                 (&mut input_manager.add_listener) as *mut _ as _
@@ -558,7 +790,7 @@ impl Builder {
 
         // Set up output manager, if the user provided it.
         let output_manager = self.output_manager_builder.take().map(|builder| {
-            let output_manager = output::Manager::build(builder);
+            let mut output_manager = output::Manager::build(builder);
             wl_signal_add(
                 &mut (*backend.as_ptr()).events.new_output as *mut _ as _,
                 (&mut output_manager.add_listener) as *mut _ as _
@@ -566,12 +798,9 @@ impl Builder {
             output_manager
         });
 
-        // Set up the xdg_shell handler and associated Wayland global,
-        // if user provided a manager for it.
-        let mut xdg_shell_global = ptr::null_mut();
+        // Set up the xdg_shell handler, if user provided a manager for it.
         let xdg_shell_manager = self.xdg_shell_manager_builder.take().map(|builder| {
-            xdg_shell_global = wlr_xdg_shell_create(display as *mut _);
-            let xdg_shell_manager = xdg_shell::Manager::build(builder);
+            let mut xdg_shell_manager = xdg_shell::Manager::build(builder);
             wl_signal_add(
                 &mut (*xdg_shell_global).events.new_surface as *mut _ as _,
                 (&mut xdg_shell_manager.add_listener) as *mut _ as _
@@ -579,12 +808,9 @@ impl Builder {
             xdg_shell_manager
         });
 
-        // Set up the xdg_shell_v6 handler and associated Wayland global,
-        // if user provided a manager for it.
-        let mut xdg_v6_shell_global = ptr::null_mut();
+        // Set up the xdg_shell_v6 handler, if user provided a manager for it.
         let xdg_v6_shell_manager = self.xdg_v6_shell_manager_builder.take().map(|builder| {
-            xdg_v6_shell_global = wlr_xdg_shell_v6_create(display as *mut _);
-            let xdg_v6_shell_manager = xdg_shell_v6::Manager::build(builder);
+            let mut xdg_v6_shell_manager = xdg_shell_v6::Manager::build(builder);
             wl_signal_add(
                 &mut (*xdg_v6_shell_global).events.new_surface as *mut _ as _,
                 (&mut xdg_v6_shell_manager.add_listener) as *mut _ as _
@@ -592,25 +818,8 @@ impl Builder {
             xdg_v6_shell_manager
         });
 
-        // Set up the XWayland server, if the user wants it.
-        let xwayland = self
-            .xwayland
-            .take()
-            .and_then(|builder| Some(xwayland::Server::new(display as _, compositor, builder, false)));
-
         let user_terminate = self.user_terminate;
 
-        // Open the socket to the Wayland server.
-        let socket = ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_display_add_socket_auto, display);
-        if socket.is_null() {
-            // NOTE Rationale for panicking:
-            // * Won't be in C land just yet, so it's safe to panic
-            // * Can always be returned in a Result instead, but for now if you auto create
-            //   it's assumed you can't recover.
-            panic!("Unable to open wayland socket");
-        }
-        let socket_name = CStr::from_ptr(socket).to_string_lossy().into_owned();
-        env::set_var("_WAYLAND_DISPLAY", socket_name.clone());
         let compositor = Compositor {
             data: Box::new(data),
             compositor_handler,
@@ -626,39 +835,56 @@ impl Builder {
             backend,
             display,
             event_loop,
+            event_loop_liveliness: Rc::new(()),
             wl_shm_fd,
             server_decoration_manager,
             gamma_control_manager,
             idle_manager,
             idle_inhibit_manager,
             input_inhibit_manager,
+            #[cfg(feature = "output_power_management")]
+            output_power_manager,
             gtk_primary_selection_manager,
             screencopy_manager,
             screenshooter,
+            linux_dmabuf,
             renderer,
             xwayland,
             user_terminate,
             panic_error: None,
             lock: Rc::new(Cell::new(false))
         };
-        // Forget so we can't construct another builder.
-        std::mem::forget(self);
         compositor.set_lock(true);
-        compositor
+        Ok(compositor)
     }
 }
 
-impl Drop for Builder {
+impl BuildGuard {
+    /// Creates the display the compositor is built on.
+    unsafe fn new() -> Result<Self, BuildError> {
+        let display = ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_display_create,) as *mut wl_display;
+        if display.is_null() {
+            return Err(BuildError::Display);
+        }
+        Ok(BuildGuard {
+            display,
+            backend: ptr::null_mut(),
+            renderer: None
+        })
+    }
+}
+
+impl Drop for BuildGuard {
     fn drop(&mut self) {
         unsafe {
-            // NOTE This will only happen if dropped outside of `finish_build`,
-            // which mem::forgets(self) in order to not be able to use a builder
-            // while the compositor is running.
-            assert_eq!(
-                BUILDER_ACTIVE.compare_and_swap(true, false, Ordering::AcqRel),
-                true,
-                "Builder was in improper state"
-            );
+            // The renderer belongs to the backend, which destroys it.
+            if let Some(renderer) = self.renderer.take() {
+                std::mem::forget(renderer)
+            }
+            if !self.backend.is_null() {
+                wlr_backend_destroy(self.backend)
+            }
+            ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_display_destroy, self.display);
         }
     }
 }
@@ -693,18 +919,61 @@ impl Compositor {
         self.socket_name.as_str()
     }
 
+    /// Gets a handle to the Wayland event loop, which can be used to add
+    /// timers, file descriptors, signals and idle callbacks to it.
+    pub fn loop_handle(&self) -> event_loop::EventLoop {
+        unsafe { event_loop::EventLoop::from_ptr(self.event_loop, &self.event_loop_liveliness) }
+    }
+
+    /// Sends the queued events to every client.
+    ///
+    /// When driving the event loop with `run_with`, call this after each
+    /// `EventLoop::dispatch`.
+    pub fn flush_clients(&self) {
+        unsafe { ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_display_flush_clients, self.display) }
+    }
+
     /// Enters the wayland event loop. Won't return until the compositor is
     /// shut off.
+    ///
+    /// # Panicking
+    /// Panics if the compositor could not be started, see `try_run` for a
+    /// fallible version.
     pub fn run(self) {
-        self.run_with(|_| unsafe {
-            ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_display_run, (*COMPOSITOR_PTR).display);
+        self.try_run().unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Like `run`, but returns an error instead of panicking if the
+    /// compositor could not be started.
+    pub fn try_run(self) -> Result<(), BuildError> {
+        self.try_run_with(|_| unsafe {
+            ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_display_run, (*compositor_ptr()).display);
         })
     }
 
     /// Prepare to enter the wayland event loop. Instead of calling
     /// `wl_display_run`, the provided callback function is invoked. Allows
     /// integration with a different event loop.
+    ///
+    /// The callback should poll `EventLoop::fd` and call
+    /// `EventLoop::dispatch` followed by `Compositor::flush_clients` whenever
+    /// it's readable. Use `custom_terminate` to find out when to stop.
+    ///
+    /// # Panicking
+    /// Panics if the compositor could not be started, see `try_run_with` for
+    /// a fallible version.
     pub fn run_with<F>(self, runner: F)
+    where
+        F: FnOnce(&Compositor)
+    {
+        self.try_run_with(runner).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Like `run_with`, but returns an error instead of panicking if the
+    /// compositor could not be started.
+    ///
+    /// The compositor is dropped when this returns, whether it ran or not.
+    pub fn try_run_with<F>(self, runner: F) -> Result<(), BuildError>
     where
         F: FnOnce(&Compositor)
     {
@@ -714,25 +983,19 @@ impl Compositor {
             self.socket_name
         );
         unsafe {
+            if !compositor_ptr().is_null() {
+                return Err(BuildError::AlreadyRunning);
+            }
             self.set_lock(false);
+            // NOTE The pointer is reset when the compositor is dropped at the
+            // end of this function, even if the runner panics.
             let compositor = UnsafeCell::new(self);
-            if COMPOSITOR_PTR != 0 as _ {
-                // NOTE Rationale for panicking:
-                // * Nicer than an abort
-                // * Not yet in C land
-                panic!("A compositor is already running!")
-            }
-            COMPOSITOR_PTR = compositor.get();
+            set_compositor_ptr(compositor.get());
             wlr_log!(WLR_INFO, "Starting compositor");
             if !wlr_backend_start((*compositor.get()).backend.as_ptr()) {
-                wlr_backend_destroy((*compositor.get()).backend.as_ptr());
-                // NOTE Rationale for panicking:
-                // * Won't be in C land just yet, so it's safe to panic
-                // * Can always be returned in a Result instead, but for now if you auto create
-                //   it's assumed you can't recover.
-                panic!("Failed to start backend");
+                return Err(BuildError::BackendStart);
             }
-            runner(&*COMPOSITOR_PTR);
+            runner(&*compositor.get());
             match (*compositor.get()).panic_error.take() {
                 None => {},
                 Some(err) => {
@@ -741,6 +1004,7 @@ impl Compositor {
                 }
             }
         }
+        Ok(())
     }
 
     /// Shutdown the wayland server
@@ -769,13 +1033,84 @@ impl Compositor {
 impl Drop for Compositor {
     fn drop(&mut self) {
         unsafe {
-            assert_eq!(
-                BUILDER_ACTIVE.compare_and_swap(true, false, Ordering::AcqRel),
-                true,
-                "Builder was in improper state"
-            );
+            // The destroy listeners that run during teardown need the
+            // compositor pointer to clean up and notify the user, so it points
+            // to this compositor until the display is gone, even if another
+            // one is running on this thread. Handles can't be upgraded in the
+            // meantime, since the compositor is being torn down.
+            self.set_lock(true);
+            let previous = compositor_ptr();
+            set_compositor_ptr(self);
+            // The user data could hold resources (e.g. timers) that must be
+            // freed before the display is destroyed.
+            self.data = Box::new(());
             ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_display_destroy_clients, self.display);
-            wlr_compositor_destroy(self.compositor)
+            // Remove the listeners before the objects they listen to are
+            // destroyed.
+            self.input_manager = None;
+            self.output_manager = None;
+            self.xdg_shell_manager = None;
+            self.xdg_v6_shell_manager = None;
+            self.xwayland = None;
+            // The extensions are destroyed along with the display otherwise,
+            // which would make dropping them later a double free.
+            self.server_decoration_manager = None;
+            self.gamma_control_manager = None;
+            self.idle_manager = None;
+            self.idle_inhibit_manager = None;
+            self.input_inhibit_manager = None;
+            #[cfg(feature = "output_power_management")]
+            {
+                self.output_power_manager = None;
+            }
+            self.gtk_primary_selection_manager = None;
+            self.screencopy_manager = None;
+            self.screenshooter = None;
+            self.linux_dmabuf = None;
+            self.data_device_manager = None;
+            wlr_compositor_destroy(self.compositor);
+            self.compositor_handler = None;
+            // The renderer belongs to the backend, which destroys it.
+            if let Some(renderer) = self.renderer.take() {
+                std::mem::forget(renderer)
+            }
+            wlr_backend_destroy(self.backend.as_ptr());
+            // The event loop is destroyed along with the display.
+            self.event_loop_liveliness = Rc::new(());
+            ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_display_destroy, self.display);
+            if previous == self as *mut _ {
+                set_compositor_ptr(ptr::null_mut())
+            } else {
+                set_compositor_ptr(previous)
+            }
+        }
+    }
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BuildError::Global(name) => write!(f, "Could not create the {} global", name),
+            BuildError::RendererRequired(name) => write!(f, "{} requires the GLES2 renderer", name),
+            _ => write!(f, "{}", self.description())
+        }
+    }
+}
+
+impl Error for BuildError {
+    fn description(&self) -> &str {
+        match *self {
+            BuildError::Display => "Could not create the Wayland display",
+            BuildError::Session => "Could not open the session",
+            BuildError::Backend => "Could not construct backend",
+            BuildError::Renderer => "Could not construct GLES2 renderer",
+            BuildError::Compositor => "Could not create the compositor global",
+            BuildError::Global(_) => "Could not create an extension global",
+            BuildError::RendererRequired(_) => "An extension requires the GLES2 renderer",
+            BuildError::Xwayland => "Could not start XWayland server",
+            BuildError::Socket => "Unable to open wayland socket",
+            BuildError::AlreadyRunning => "A compositor is already running on this thread",
+            BuildError::BackendStart => "Failed to start backend"
         }
     }
 }
@@ -808,11 +1143,14 @@ impl Handle {
                 if check.get() {
                     return Err(HandleErr::AlreadyBorrowed)
                 }
-                if COMPOSITOR_PTR.is_null() {
+                // The handle can only be upgraded while its compositor is
+                // running on this thread.
+                let compositor = compositor_ptr();
+                if compositor.is_null() || !Rc::ptr_eq(&check, &(*compositor).lock) {
                     return Err(HandleErr::AlreadyDropped)
                 }
                 check.set(true);
-                Ok(&mut *COMPOSITOR_PTR)
+                Ok(&mut *compositor)
             })
     }
 
@@ -857,11 +1195,13 @@ impl Handle {
     }
 }
 
-/// Terminates the compositor and execute any user clean up code.
+/// Terminates the compositor running on this thread and execute any user
+/// clean up code.
 pub fn terminate() {
     unsafe {
-        if COMPOSITOR_PTR != 0 as _ {
-            let compositor = &mut *COMPOSITOR_PTR;
+        let compositor = compositor_ptr();
+        if !compositor.is_null() {
+            let compositor = &mut *compositor;
             compositor.terminate();
             if let Some(user_terminate) = compositor.user_terminate {
                 user_terminate()
@@ -870,16 +1210,58 @@ pub fn terminate() {
     }
 }
 
-/// Gets a handle to the compositor.
+/// Gets a handle to the compositor running on this thread.
 ///
-/// If the compositor has not started running yet, or if it has stopped,
-/// then this function will return None.
+/// If the compositor has not started running yet, or if it has been
+/// destroyed, then this function will return None. While the compositor is
+/// being destroyed the handle is returned, but can't be upgraded.
 pub fn handle() -> Option<Handle> {
     unsafe {
-        if COMPOSITOR_PTR.is_null() {
+        let compositor = compositor_ptr();
+        if compositor.is_null() {
             None
         } else {
-            Some((&*COMPOSITOR_PTR).weak_reference())
+            Some((&*compositor).weak_reference())
+        }
+    }
+}
+
+/// Gets the compositor running on this thread, or null if there is none.
+pub(crate) fn compositor_ptr() -> *mut Compositor {
+    COMPOSITOR_PTR.with(|compositor| compositor.get())
+}
+
+fn set_compositor_ptr(compositor: *mut Compositor) {
+    COMPOSITOR_PTR.with(|ptr| ptr.set(compositor))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    fn build_and_drop() {
+        let compositor = Builder::new().build_headless(());
+        assert!(!compositor.socket_name().is_empty());
+        drop(compositor);
+        assert!(handle().is_none());
+    }
+
+    // NOTE These need a headless backend and renderer to be usable, run them
+    // with `cargo test -- --ignored`.
+    #[test]
+    #[ignore]
+    fn build_and_drop_sequentially() {
+        build_and_drop();
+        build_and_drop();
+    }
+
+    #[test]
+    #[ignore]
+    fn build_and_drop_on_two_threads() {
+        let threads: Vec<_> = (0..2).map(|_| thread::spawn(build_and_drop)).collect();
+        for thread in threads {
+            thread.join().expect("Building a compositor panicked");
         }
     }
 }
@@ -0,0 +1,472 @@
+//! Safe access to the Wayland event loop the compositor runs on.
+//!
+//! Timers, file descriptors, POSIX signals and idle callbacks can be added to
+//! the event loop through an `EventLoop`, which is obtained with
+//! `Compositor::loop_handle`.
+//!
+//! Adding a source returns a value that owns it: the source is removed from
+//! the event loop once that value is dropped. Sources can outlive the
+//! `Compositor`, in which case they are never called again and dropping them
+//! only frees their callback.
+//!
+//! The callbacks are given a `compositor::Handle`. If one of them panics the
+//! compositor is terminated and the panic is re-thrown once the event loop
+//! returns, like with any other callback in wlroots-rs.
+
+use std::{
+    cell::{Cell, RefCell},
+    io, mem,
+    os::unix::io::RawFd,
+    panic,
+    rc::{Rc, Weak},
+    time::Duration
+};
+
+use crate::libc::{self, c_int, c_void};
+use crate::wayland_sys::server::{wl_event_loop, wl_event_source, WAYLAND_SERVER_HANDLE};
+
+use crate::{
+    compositor,
+    utils::{self, ToMs}
+};
+
+bitflags! {
+    /// The events a file descriptor source can wait for, or that occurred.
+    ///
+    /// `HANGUP` and `ERROR` are always reported, even when not asked for.
+    pub struct FdEvents: u32 {
+        const READABLE = 0x01;
+        const WRITABLE = 0x02;
+        const HANGUP = 0x04;
+        const ERROR = 0x08;
+    }
+}
+
+/// A handle to the Wayland event loop, used to add event sources to it.
+#[derive(Debug, Clone)]
+pub struct EventLoop {
+    event_loop: *mut wl_event_loop,
+    /// Dangles once the event loop was destroyed.
+    liveliness: Weak<()>
+}
+
+/// A timer on the event loop.
+///
+/// The timer fires once each time it is armed with `Timer::update`.
+#[derive(Debug)]
+pub struct Timer {
+    source: Source
+}
+
+/// A file descriptor watched by the event loop.
+///
+/// The file descriptor is not closed when the source is dropped.
+#[derive(Debug)]
+pub struct FdSource {
+    source: Source,
+    fd: RawFd
+}
+
+/// A POSIX signal handled by the event loop.
+///
+/// The signal is delivered through a signalfd, so it must be blocked in
+/// every thread of the process for the callback to be called.
+#[derive(Debug)]
+pub struct Signal {
+    source: Source,
+    signal: c_int
+}
+
+/// A callback called once the next time the event loop is idle.
+///
+/// Dropping an `Idle` before it was called cancels it.
+#[derive(Debug)]
+pub struct Idle {
+    source: Source
+}
+
+type TimerCallback = Box<FnMut(compositor::Handle)>;
+type FdCallback = Box<FnMut(compositor::Handle, RawFd, FdEvents)>;
+type SignalCallback = Box<FnMut(compositor::Handle, c_int)>;
+type IdleCallback = Box<FnMut(compositor::Handle)>;
+
+/// The state shared between a source and libwayland.
+///
+/// It's reference counted so that a source can be dropped from within
+/// its own callback.
+struct SourceData<T> {
+    /// Set once libwayland has removed the source on its own, which
+    /// happens to idle sources once they have been dispatched.
+    removed: Cell<bool>,
+    callback: RefCell<T>
+}
+
+/// An event source and the data given to libwayland with it.
+#[derive(Debug)]
+struct Source {
+    source: *mut wl_event_source,
+    /// Dangles once the event loop was destroyed, along with the source.
+    liveliness: Weak<()>,
+    data: *mut c_void,
+    /// Frees `data`, which is type erased.
+    free: unsafe fn(*mut c_void),
+    /// Determines if libwayland already removed the source.
+    removed: unsafe fn(*mut c_void) -> bool
+}
+
+impl PartialEq for EventLoop {
+    fn eq(&self, other: &EventLoop) -> bool {
+        self.event_loop == other.event_loop
+    }
+}
+
+impl Eq for EventLoop {}
+
+impl EventLoop {
+    /// Makes a handle to the event loop, which is considered destroyed once
+    /// every strong reference to `liveliness` is dropped.
+    pub(crate) unsafe fn from_ptr(event_loop: *mut wl_event_loop, liveliness: &Rc<()>) -> Self {
+        EventLoop {
+            event_loop,
+            liveliness: Rc::downgrade(liveliness)
+        }
+    }
+
+    /// Determines if the event loop still exists.
+    pub fn is_alive(&self) -> bool {
+        self.liveliness.upgrade().is_some()
+    }
+
+    /// Gets the pointer to the `wl_event_loop`.
+    pub unsafe fn as_ptr(&self) -> *mut wl_event_loop {
+        self.event_loop
+    }
+
+    /// Gets the file descriptor of the event loop.
+    ///
+    /// It becomes readable whenever there are events to dispatch, so it can
+    /// be polled by another event loop which then calls `dispatch`.
+    ///
+    /// Returns -1 if the event loop was destroyed.
+    pub fn fd(&self) -> RawFd {
+        if !self.is_alive() {
+            return -1;
+        }
+        unsafe { ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_event_loop_get_fd, self.event_loop) }
+    }
+
+    /// Dispatches the pending events, waiting up to `timeout` for some to
+    /// arrive. A timeout of `None` waits forever.
+    ///
+    /// Use `Compositor::flush_clients` afterwards to send the resulting
+    /// events to the clients.
+    ///
+    /// Fails if the event loop was destroyed.
+    pub fn dispatch(&self, timeout: Option<Duration>) -> io::Result<()> {
+        if !self.is_alive() {
+            return Err(io::Error::new(io::ErrorKind::NotFound, "The event loop was destroyed"));
+        }
+        let timeout = match timeout {
+            Some(timeout) => timeout.to_ms().min(c_int::max_value() as u32) as c_int,
+            None => -1
+        };
+        let res = unsafe {
+            ffi_dispatch!(
+                WAYLAND_SERVER_HANDLE,
+                wl_event_loop_dispatch,
+                self.event_loop,
+                timeout
+            )
+        };
+        if res < 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Adds a timer that calls the callback after `delay`.
+    ///
+    /// A delay of zero adds the timer disarmed.
+    pub fn add_timer<F>(&self, delay: Duration, callback: F) -> Option<Timer>
+    where
+        F: FnMut(compositor::Handle) + 'static
+    {
+        if !self.is_alive() {
+            return None;
+        }
+        unsafe {
+            let data = new_data::<TimerCallback>(Box::new(callback));
+            let source = ffi_dispatch!(
+                WAYLAND_SERVER_HANDLE,
+                wl_event_loop_add_timer,
+                self.event_loop,
+                timer_notify,
+                data
+            );
+            let mut timer = Timer {
+                source: Source::new::<TimerCallback>(source, data, &self.liveliness)?
+            };
+            timer.update(delay);
+            Some(timer)
+        }
+    }
+
+    /// Watches a file descriptor, calling the callback whenever one of the
+    /// requested events occurs.
+    pub fn add_fd<F>(&self, fd: RawFd, events: FdEvents, callback: F) -> Option<FdSource>
+    where
+        F: FnMut(compositor::Handle, RawFd, FdEvents) + 'static
+    {
+        if !self.is_alive() {
+            return None;
+        }
+        unsafe {
+            let data = new_data::<FdCallback>(Box::new(callback));
+            let source = ffi_dispatch!(
+                WAYLAND_SERVER_HANDLE,
+                wl_event_loop_add_fd,
+                self.event_loop,
+                fd,
+                events.bits(),
+                fd_notify,
+                data
+            );
+            Some(FdSource {
+                source: Source::new::<FdCallback>(source, data, &self.liveliness)?,
+                fd
+            })
+        }
+    }
+
+    /// Calls the callback whenever the process receives the signal.
+    pub fn add_signal<F>(&self, signal: c_int, callback: F) -> Option<Signal>
+    where
+        F: FnMut(compositor::Handle, c_int) + 'static
+    {
+        if !self.is_alive() {
+            return None;
+        }
+        unsafe {
+            let data = new_data::<SignalCallback>(Box::new(callback));
+            let source = ffi_dispatch!(
+                WAYLAND_SERVER_HANDLE,
+                wl_event_loop_add_signal,
+                self.event_loop,
+                signal,
+                signal_notify,
+                data
+            );
+            Some(Signal {
+                source: Source::new::<SignalCallback>(source, data, &self.liveliness)?,
+                signal
+            })
+        }
+    }
+
+    /// Calls the callback once, the next time the event loop has nothing
+    /// else to do.
+    pub fn add_idle<F>(&self, callback: F) -> Option<Idle>
+    where
+        F: FnOnce(compositor::Handle) + 'static
+    {
+        if !self.is_alive() {
+            return None;
+        }
+        let mut callback = Some(callback);
+        let callback: IdleCallback = Box::new(move |compositor| {
+            if let Some(callback) = callback.take() {
+                callback(compositor)
+            }
+        });
+        unsafe {
+            let data = new_data::<IdleCallback>(callback);
+            let source = ffi_dispatch!(
+                WAYLAND_SERVER_HANDLE,
+                wl_event_loop_add_idle,
+                self.event_loop,
+                idle_notify,
+                data
+            );
+            Some(Idle {
+                source: Source::new::<IdleCallback>(source, data, &self.liveliness)?
+            })
+        }
+    }
+}
+
+impl Timer {
+    /// Arms the timer to fire after `delay`, replacing any previous delay.
+    ///
+    /// The delay has millisecond precision. A delay of zero disarms the timer.
+    pub fn update(&mut self, delay: Duration) {
+        let mut delay_ms = delay.to_ms().min(c_int::max_value() as u32) as c_int;
+        if delay_ms == 0 && delay != Duration::from_millis(0) {
+            // Round sub-millisecond delays up so the timer stays armed.
+            delay_ms = 1;
+        }
+        if let Some(source) = self.source.source() {
+            unsafe {
+                ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_event_source_timer_update, source, delay_ms);
+            }
+        }
+    }
+
+    /// Disarms the timer, it won't fire until it is armed again.
+    pub fn disarm(&mut self) {
+        self.update(Duration::from_millis(0))
+    }
+}
+
+impl FdSource {
+    /// Gets the file descriptor being watched.
+    pub fn fd(&self) -> RawFd {
+        self.fd
+    }
+
+    /// Changes the events being waited for.
+    pub fn update(&mut self, events: FdEvents) {
+        if let Some(source) = self.source.source() {
+            unsafe {
+                ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_event_source_fd_update, source, events.bits());
+            }
+        }
+    }
+}
+
+impl Signal {
+    /// Gets the signal number being handled.
+    pub fn signal(&self) -> c_int {
+        self.signal
+    }
+}
+
+impl Idle {
+    /// Determines if the callback was already called.
+    pub fn is_done(&self) -> bool {
+        unsafe { (self.source.removed)(self.source.data) }
+    }
+}
+
+impl Source {
+    unsafe fn new<T>(source: *mut wl_event_source, data: *mut c_void, liveliness: &Weak<()>) -> Option<Self> {
+        if source.is_null() {
+            wlr_log!(WLR_ERROR, "Could not add event source to the event loop");
+            free_data::<T>(data);
+            return None;
+        }
+        Some(Source {
+            source,
+            liveliness: liveliness.clone(),
+            data,
+            free: free_data::<T>,
+            removed: is_removed::<T>
+        })
+    }
+}
+
+impl Source {
+    fn is_alive(&self) -> bool {
+        self.liveliness.upgrade().is_some()
+    }
+
+    /// Gets the source, if it still exists.
+    fn source(&self) -> Option<*mut wl_event_source> {
+        if self.is_alive() {
+            Some(self.source)
+        } else {
+            None
+        }
+    }
+}
+
+impl Drop for Source {
+    fn drop(&mut self) {
+        unsafe {
+            // NOTE The source was freed along with the event loop if that's
+            // gone, so it can't be removed anymore.
+            if self.is_alive() && !(self.removed)(self.data) {
+                ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_event_source_remove, self.source);
+            }
+            (self.free)(self.data)
+        }
+    }
+}
+
+/// Resets the counter of an eventfd so it stops being readable.
+pub(crate) fn clear_eventfd(fd: c_int) {
+    let mut value: u64 = 0;
+    unsafe {
+        libc::read(fd, &mut value as *mut u64 as *mut c_void, 8);
+    }
+}
+
+unsafe fn new_data<T>(callback: T) -> *mut c_void {
+    let data = Rc::new(SourceData {
+        removed: Cell::new(false),
+        callback: RefCell::new(callback)
+    });
+    Rc::into_raw(data) as *mut c_void
+}
+
+unsafe fn free_data<T>(data: *mut c_void) {
+    drop(Rc::from_raw(data as *const SourceData<T>))
+}
+
+unsafe fn is_removed<T>(data: *mut c_void) -> bool {
+    (*(data as *const SourceData<T>)).removed.get()
+}
+
+/// Gets a new reference to the data, so it outlives the source being
+/// dropped by its own callback.
+unsafe fn clone_data<T>(data: *mut c_void) -> Rc<SourceData<T>> {
+    let data = Rc::from_raw(data as *const SourceData<T>);
+    let clone = data.clone();
+    mem::forget(data);
+    clone
+}
+
+/// Calls the callback stored in the data with the compositor handle,
+/// capturing any panic.
+unsafe fn dispatch<T, F>(data: *mut c_void, call: F)
+where
+    F: FnOnce(&mut T, compositor::Handle)
+{
+    let data = clone_data::<T>(data);
+    let compositor = match compositor::handle() {
+        Some(handle) => handle,
+        None => return
+    };
+    let mut callback = match data.callback.try_borrow_mut() {
+        Ok(callback) => callback,
+        Err(_) => {
+            wlr_log!(WLR_ERROR, "Event source callback was called recursively");
+            return;
+        }
+    };
+    utils::handle_unwind(panic::catch_unwind(panic::AssertUnwindSafe(|| {
+        call(&mut *callback, compositor)
+    })));
+}
+
+unsafe extern "C" fn timer_notify(data: *mut c_void) -> c_int {
+    dispatch::<TimerCallback, _>(data, |callback, compositor| callback(compositor));
+    0
+}
+
+unsafe extern "C" fn fd_notify(fd: c_int, mask: u32, data: *mut c_void) -> c_int {
+    let events = FdEvents::from_bits_truncate(mask);
+    dispatch::<FdCallback, _>(data, |callback, compositor| callback(compositor, fd, events));
+    0
+}
+
+unsafe extern "C" fn signal_notify(signal: c_int, data: *mut c_void) -> c_int {
+    dispatch::<SignalCallback, _>(data, |callback, compositor| callback(compositor, signal));
+    0
+}
+
+unsafe extern "C" fn idle_notify(data: *mut c_void) {
+    // libwayland removes idle sources right after calling them.
+    clone_data::<IdleCallback>(data).removed.set(true);
+    dispatch::<IdleCallback, _>(data, |callback, compositor| callback(compositor));
+}
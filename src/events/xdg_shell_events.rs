@@ -5,7 +5,7 @@ use wlroots_sys::{
     wlr_xdg_toplevel_show_window_menu_event
 };
 
-use crate::{output, shell::xdg_shell, utils::edges::Edges};
+use crate::{output, seat, shell::xdg_shell, utils::edges::Edges};
 
 /// Event that triggers when the surface has been moved in coordinate space.
 #[derive(Debug, PartialEq, Eq)]
@@ -42,8 +42,12 @@ impl Move {
         unsafe { xdg_shell::Handle::from_ptr((*self.event).surface) }
     }
 
-    // TODO Get seat client
+    /// Get a handle to the seat the request comes from.
+    pub fn seat(&self) -> seat::Handle {
+        unsafe { seat::Handle::from_ptr((*(*self.event).seat).seat) }
+    }
 
+    /// Get the serial of the input event that triggered the request.
     pub fn serial(&self) -> u32 {
         unsafe { (*self.event).serial }
     }
@@ -59,8 +63,12 @@ impl Resize {
         unsafe { xdg_shell::Handle::from_ptr((*self.event).surface) }
     }
 
-    // TODO Get seat client
+    /// Get a handle to the seat the request comes from.
+    pub fn seat(&self) -> seat::Handle {
+        unsafe { seat::Handle::from_ptr((*(*self.event).seat).seat) }
+    }
 
+    /// Get the serial of the input event that triggered the request.
     pub fn serial(&self) -> u32 {
         unsafe { (*self.event).serial }
     }
@@ -0,0 +1,202 @@
+//! A single threaded executor for `std::future::Future`s that runs on the
+//! Wayland event loop.
+//!
+//! This lets asynchronous code (e.g. an IPC server) live on the same thread
+//! as the compositor, without having to integrate another event loop.
+//! Tasks are polled from an event loop callback, so they can use
+//! `compositor::Handle`s like any other callback.
+//!
+//! Wakers can be sent to other threads: waking a task from there wakes up
+//! the event loop.
+//!
+//! Only available with the `executor` feature.
+
+use std::{
+    cell::RefCell,
+    future::Future,
+    io, mem,
+    pin::Pin,
+    rc::{Rc, Weak},
+    sync::{Arc, Mutex},
+    task::{Context, Poll, RawWaker, RawWakerVTable, Waker}
+};
+
+use crate::libc::{self, c_void};
+
+use crate::event_loop::{clear_eventfd, EventLoop, FdEvents, FdSource};
+
+type Task = Pin<Box<Future<Output = ()>>>;
+
+/// Runs futures on the Wayland event loop.
+///
+/// Dropping the executor drops all of the tasks that have not completed.
+pub struct Executor {
+    _source: FdSource,
+    inner: Rc<Inner>
+}
+
+struct Inner {
+    /// The tasks, indexed by their id. A slot is `None` while its task is
+    /// being polled or once it completed.
+    tasks: RefCell<Vec<Option<Task>>>,
+    free: RefCell<Vec<usize>>,
+    shared: Arc<Shared>
+}
+
+/// The state wakers have access to, from any thread.
+struct Shared {
+    /// The ids of the tasks to poll.
+    ready: Mutex<Vec<usize>>,
+    /// An eventfd used to wake up the event loop.
+    fd: libc::c_int
+}
+
+/// The data behind a `Waker`.
+struct TaskWaker {
+    id: usize,
+    shared: Arc<Shared>
+}
+
+impl Executor {
+    /// Makes an executor that polls its tasks on the given event loop.
+    pub fn new(event_loop: &EventLoop) -> io::Result<Self> {
+        let fd = unsafe { libc::eventfd(0, libc::EFD_CLOEXEC | libc::EFD_NONBLOCK) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let inner = Rc::new(Inner {
+            tasks: RefCell::new(Vec::new()),
+            free: RefCell::new(Vec::new()),
+            shared: Arc::new(Shared {
+                ready: Mutex::new(Vec::new()),
+                fd
+            })
+        });
+        let weak_inner = Rc::downgrade(&inner);
+        let source = event_loop.add_fd(fd, FdEvents::READABLE, move |_, fd, _| {
+            clear_eventfd(fd);
+            if let Some(inner) = Weak::upgrade(&weak_inner) {
+                inner.run_ready()
+            }
+        });
+        match source {
+            Some(source) => Ok(Executor {
+                _source: source,
+                inner
+            }),
+            None => Err(io::Error::new(
+                io::ErrorKind::Other,
+                "Could not add the executor to the event loop"
+            ))
+        }
+    }
+
+    /// Spawns a task that will be polled to completion by the event loop.
+    ///
+    /// This can be called from within another task.
+    pub fn spawn<F>(&self, future: F)
+    where
+        F: Future<Output = ()> + 'static
+    {
+        let task: Task = Box::pin(future);
+        let id = match self.inner.free.borrow_mut().pop() {
+            Some(id) => {
+                self.inner.tasks.borrow_mut()[id] = Some(task);
+                id
+            },
+            None => {
+                let mut tasks = self.inner.tasks.borrow_mut();
+                tasks.push(Some(task));
+                tasks.len() - 1
+            }
+        };
+        self.inner.shared.wake(id);
+    }
+}
+
+impl Inner {
+    /// Polls the tasks that were woken up, until there are none left.
+    fn run_ready(&self) {
+        loop {
+            let ready = match self.shared.ready.lock() {
+                Ok(mut ready) => ready.drain(..).collect::<Vec<_>>(),
+                Err(_) => return
+            };
+            if ready.is_empty() {
+                return;
+            }
+            for id in ready {
+                // NOTE The task is taken out of its slot while it's polled
+                // so that it can spawn other tasks.
+                let task = self.tasks.borrow_mut().get_mut(id).and_then(Option::take);
+                let mut task = match task {
+                    Some(task) => task,
+                    None => continue
+                };
+                let waker = TaskWaker::waker(id, self.shared.clone());
+                let mut context = Context::from_waker(&waker);
+                match task.as_mut().poll(&mut context) {
+                    Poll::Ready(()) => self.free.borrow_mut().push(id),
+                    Poll::Pending => self.tasks.borrow_mut()[id] = Some(task)
+                }
+            }
+        }
+    }
+}
+
+impl Shared {
+    fn wake(&self, id: usize) {
+        if let Ok(mut ready) = self.ready.lock() {
+            if !ready.contains(&id) {
+                ready.push(id);
+            }
+        }
+        let value: u64 = 1;
+        unsafe {
+            libc::write(self.fd, &value as *const u64 as *const c_void, 8);
+        }
+    }
+}
+
+impl Drop for Shared {
+    fn drop(&mut self) {
+        unsafe {
+            libc::close(self.fd);
+        }
+    }
+}
+
+impl TaskWaker {
+    const VTABLE: RawWakerVTable = RawWakerVTable::new(
+        TaskWaker::clone_raw,
+        TaskWaker::wake_raw,
+        TaskWaker::wake_by_ref_raw,
+        TaskWaker::drop_raw
+    );
+
+    fn waker(id: usize, shared: Arc<Shared>) -> Waker {
+        let data = Arc::into_raw(Arc::new(TaskWaker { id, shared }));
+        unsafe { Waker::from_raw(RawWaker::new(data as *const (), &TaskWaker::VTABLE)) }
+    }
+
+    unsafe fn clone_raw(data: *const ()) -> RawWaker {
+        let waker = Arc::from_raw(data as *const TaskWaker);
+        let clone = waker.clone();
+        mem::forget(waker);
+        RawWaker::new(Arc::into_raw(clone) as *const (), &TaskWaker::VTABLE)
+    }
+
+    unsafe fn wake_raw(data: *const ()) {
+        let waker = Arc::from_raw(data as *const TaskWaker);
+        waker.shared.wake(waker.id);
+    }
+
+    unsafe fn wake_by_ref_raw(data: *const ()) {
+        let waker = &*(data as *const TaskWaker);
+        waker.shared.wake(waker.id);
+    }
+
+    unsafe fn drop_raw(data: *const ()) {
+        drop(Arc::from_raw(data as *const TaskWaker));
+    }
+}
@@ -3,6 +3,8 @@ pub mod gtk_primary_selection;
 pub mod idle;
 pub mod idle_inhibit;
 pub mod input_inhibit;
+#[cfg(feature = "output_power_management")]
+pub mod output_power_management;
 pub mod screencopy;
 pub mod screenshooter;
 pub mod server_decoration;
@@ -0,0 +1,112 @@
+//! Support for the wlroots Output Power Management Protocol
+//!
+//! Warning: This protocol is unstable and can change in the future
+//! Current Protocol: https://github.com/swaywm/wlroots/blob/master/protocol/wlr-output-power-management-unstable-v1.xml
+//!
+//! Only available with the `output_power_management` feature. The bundled
+//! wlroots can't build it, as it lacks `wlr_output_power_management_v1.h`:
+//! link against a newer system wlroots instead of using the `static` feature.
+
+use crate::libc;
+use crate::wayland_sys::server::{
+    signal::wl_signal_add, wl_display as wl_server_display, WAYLAND_SERVER_HANDLE
+};
+use wlroots_sys::{
+    wl_display, wlr_output_power_manager_v1, wlr_output_power_manager_v1_create,
+    wlr_output_power_v1_set_mode_event, zwlr_output_power_v1_mode
+};
+
+use crate::{compositor, output};
+
+/// Handles requests from clients to change the power mode of an output.
+#[allow(unused_variables)]
+pub trait Handler {
+    /// Called when a client requests that an output is powered on or off.
+    ///
+    /// Return `false` to veto the request, leaving the output in its current
+    /// power mode. By default every request is allowed.
+    fn set_mode(
+        &mut self,
+        compositor_handle: compositor::Handle,
+        output_handle: output::Handle,
+        mode: output::PowerMode
+    ) -> bool {
+        true
+    }
+}
+
+impl Handler for () {}
+
+wayland_listener!(pub(crate) InternalManager, Box<Handler>, [
+    set_mode_listener => set_mode_notify: |this: &mut InternalManager, data: *mut libc::c_void,|
+    unsafe {
+        let handler = &mut this.data;
+        let event = &*(data as *mut wlr_output_power_v1_set_mode_event);
+        let compositor = match compositor::handle() {
+            Some(handle) => handle,
+            None => return
+        };
+        // NOTE Read as an integer, a client could send a mode newer than
+        // the bindings.
+        let raw_mode = *(&event.mode as *const _ as *const u32);
+        let mode = match raw_mode {
+            _ if raw_mode == zwlr_output_power_v1_mode::ZWLR_OUTPUT_POWER_V1_MODE_OFF as u32 => {
+                output::PowerMode::Off
+            },
+            _ if raw_mode == zwlr_output_power_v1_mode::ZWLR_OUTPUT_POWER_V1_MODE_ON as u32 => {
+                output::PowerMode::On
+            },
+            _ => {
+                wlr_log!(WLR_ERROR, "Unknown output power mode {}", raw_mode);
+                return
+            }
+        };
+        let output_handle = output::Handle::from_ptr(event.output);
+        if !handler.set_mode(compositor, output_handle.clone(), mode) {
+            wlr_log!(WLR_DEBUG, "Output power mode change to {:?} was vetoed", mode);
+            return
+        }
+        output_handle.run(|output| output.set_power_mode(mode)).ok();
+    };
+]);
+
+/// Manager that lets clients turn outputs on and off
+#[allow(dead_code)]
+pub struct ZManagerV1 {
+    manager: *mut wlr_output_power_manager_v1,
+    handler: Box<InternalManager>
+}
+
+impl ZManagerV1 {
+    pub(crate) unsafe fn new(display: *mut wl_server_display, handler: Box<Handler>) -> Option<Self> {
+        let manager_raw = wlr_output_power_manager_v1_create(display as *mut wl_display);
+
+        if !manager_raw.is_null() {
+            let mut handler = InternalManager::new(handler);
+            wl_signal_add(
+                &mut (*manager_raw).events.set_mode as *mut _ as _,
+                handler.set_mode_listener() as _
+            );
+            Some(ZManagerV1 {
+                manager: manager_raw,
+                handler
+            })
+        } else {
+            None
+        }
+    }
+}
+
+impl Drop for ZManagerV1 {
+    fn drop(&mut self) {
+        // NOTE The manager itself is destroyed by wlroots along with the
+        // display, we just need to make sure our listener doesn't dangle.
+        unsafe {
+            ffi_dispatch!(
+                WAYLAND_SERVER_HANDLE,
+                wl_list_remove,
+                &mut (*self.handler.set_mode_listener()).link as *mut _ as _
+            );
+        }
+    }
+}
@@ -23,15 +23,23 @@ mod macros;
 #[cfg(feature = "unstable")]
 pub mod backend;
 #[cfg(feature = "unstable")]
+pub mod command;
+#[cfg(feature = "unstable")]
 pub mod compositor;
 #[cfg(feature = "unstable")]
+pub mod event_loop;
+#[cfg(feature = "unstable")]
 pub(crate) mod events;
+#[cfg(feature = "executor")]
+pub mod executor;
 #[cfg(feature = "unstable")]
 pub mod extensions;
 #[cfg(feature = "unstable")]
 pub(crate) mod manager;
 #[cfg(feature = "unstable")]
 pub mod render;
+#[cfg(feature = "tiling")]
+pub mod tiling;
 mod types;
 pub mod utils;
 #[cfg(feature = "unstable")]
@@ -198,13 +198,29 @@ macro_rules! wayland_listener {
     }
 }
 
+/// Declares a callback trait, implemented by every closure and function
+/// with the given signature.
+///
+/// Builders take their callbacks generically through these traits and box
+/// them, so both plain functions and closures capturing state can be used.
+/// The argument names are only there for documentation.
+macro_rules! callback_trait {
+    ($(#[$attr: meta])*
+     pub trait $name: ident = FnMut($($arg: ident: $arg_type: ty),*) $(-> $ret: ty)?;
+    ) => {
+        $(#[$attr])*
+        pub trait $name: FnMut($($arg_type),*) $(-> $ret)? {}
+
+        impl<F> $name for F where F: FnMut($($arg_type),*) $(-> $ret)? {}
+    }
+}
+
 macro_rules! wayland_listener_static {
-    (static mut $static_manager: ident;
-     $(($manager: ident, $builder: ident):
+    ($(($manager: ident, $builder: ident):
        $([
            $(
-               $([$($extra_callback_name: ident: $extra_callback_type: ty),+])*
-               ($fn_type: ty, $listener: ident, $builder_func: ident) => ($notify: ident, $callback: ident):
+               $([$($extra_callback_name: ident: $extra_callback_type: ident),+])*
+               ($fn_type: ident, $listener: ident, $builder_func: ident) => ($notify: ident, $callback: ident):
                |$($func_arg: ident: $func_type: ty,)*| unsafe $body: block;
            )*
        ])+
@@ -213,30 +229,36 @@ macro_rules! wayland_listener_static {
         $(
             #[derive(Default)]
             #[allow(dead_code)]
-            /// A builder of static functions to manage and create resources.
+            /// A builder of callbacks to manage and create resources.
             ///
-            /// Implement the functions with the necessary signature, pass them
-            /// to the builder, and then give the builder to the necessary
-            /// structure in order to utilize them (usually it's `compositor::Builder`).
+            /// Implement the callbacks with the necessary signature (either as
+            /// functions or closures), pass them to the builder, and then give
+            /// the builder to the necessary structure in order to utilize them
+            /// (usually it's `compositor::Builder`).
             pub struct $builder {
-                $($(pub(crate) $callback: ::std::option::Option<$fn_type>,)*
-                  $($($($extra_callback_name: ::std::option::Option<$extra_callback_type>,)*)*)*)*
+                $($(pub(crate) $callback: ::std::option::Option<::std::boxed::Box<$fn_type>>,)*
+                  $($($($extra_callback_name:
+                        ::std::option::Option<::std::boxed::Box<$extra_callback_type>>,)*)*)*)*
             }
 
             impl $builder {
                 $($(
                     /// Uses the provided callback as the receiver for the
                     /// event the type signature describes.
-                    pub fn $builder_func(mut self, $callback: $fn_type) -> Self {
-                        self.$callback = ::std::option::Option::Some($callback);
+                    pub fn $builder_func<F>(mut self, $callback: F) -> Self
+                    where F: $fn_type + 'static
+                    {
+                        self.$callback = ::std::option::Option::Some(::std::boxed::Box::new($callback));
                         self
                     }
                     $($(
                         /// Uses the provided callback as the receiver for the
                         /// event the type signature describes.
-                        pub fn $extra_callback_name(mut self, $extra_callback_name: $extra_callback_type)
-                                                    -> Self {
-                            self.$extra_callback_name = ::std::option::Option::Some($extra_callback_name);
+                        pub fn $extra_callback_name<F>(mut self, $extra_callback_name: F) -> Self
+                        where F: $extra_callback_type + 'static
+                        {
+                            self.$extra_callback_name =
+                                ::std::option::Option::Some(::std::boxed::Box::new($extra_callback_name));
                             self
                         }
                     )*)*
@@ -249,52 +271,60 @@ macro_rules! wayland_listener_static {
             pub(crate) struct $manager {
                 $($(
                     pub(crate) $listener: $crate::wlroots_sys::wl_listener,
-                    $callback: ::std::option::Option<$fn_type>,
-                    $($($extra_callback_name: ::std::option::Option<$extra_callback_type>),*)*
+                    $callback: ::std::option::Option<::std::boxed::Box<$fn_type>>,
+                    $($($extra_callback_name:
+                        ::std::option::Option<::std::boxed::Box<$extra_callback_type>>),*)*
                 )*)*
             }
 
-            pub(crate) static mut $static_manager: $manager = $manager {
-                $($(
-                    $listener: $crate::wlroots_sys::wl_listener {
-                        link: {
-                            $crate::wlroots_sys::wl_list {
-                                prev: ::std::ptr::null_mut(),
-                                next: ::std::ptr::null_mut()}},
-                        notify: ::std::option::Option::None },
-                    $callback: ::std::option::Option::None,
-                    $($($extra_callback_name: ::std::option::Option::None),*)*
-                )*)*
-            };
-
             impl $manager {
-                /// Sets the functions on the builder as the global manager functions.
+                /// Makes a manager using the functions on the builder.
                 ///
-                /// # Safety
-                /// Returns a mutable reference to static data, which is unsafe to have
-                /// multiple of. Do all your mutation through this reference and don't
-                /// call this function multiple times.
-                pub(crate) unsafe fn build(builder: $builder) -> &'static mut $manager {
-                    $($(
-                        $static_manager.$listener = {
-                            // NOTE Rationale for zeroed memory:
-                            // * Need to pass a pointer to wl_list_init
-                            // * The list is initialized by Wayland, which doesn't "drop"
-                            // * The listener is written to without dropping any of the data
-                            let mut listener: $crate::wlroots_sys::wl_listener = ::std::mem::zeroed();
-                            use $crate::wlroots_sys::server::WAYLAND_SERVER_HANDLE;
-                            ffi_dispatch!(WAYLAND_SERVER_HANDLE,
-                                          wl_list_init,
-                                          &mut listener.link as *mut _ as _);
-                            ::std::ptr::write(&mut listener.notify, std::option::Option::Some($notify));
-                            listener
-                        };
-                        $static_manager.$callback = builder.$callback;
+                /// The manager is boxed so the listeners don't move once they
+                /// are added to a signal.
+                pub(crate) unsafe fn build(builder: $builder) -> ::std::boxed::Box<$manager> {
+                    use $crate::wlroots_sys::server::WAYLAND_SERVER_HANDLE;
+                    let mut manager = ::std::boxed::Box::new($manager {
                         $($(
-                            $static_manager.$extra_callback_name = builder.$extra_callback_name;
+                            $listener: $crate::wlroots_sys::wl_listener {
+                                link: $crate::wlroots_sys::wl_list {
+                                    prev: ::std::ptr::null_mut(),
+                                    next: ::std::ptr::null_mut()
+                                },
+                                notify: ::std::option::Option::Some($notify)
+                            },
+                            $callback: builder.$callback,
+                            $($($extra_callback_name: builder.$extra_callback_name,)*)*
                         )*)*
+                    });
+                    $($(
+                        ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                                      wl_list_init,
+                                      &mut manager.$listener.link as *mut _ as _);
+                    )*)*
+                    manager
+                }
+
+                /// Removes the listeners from the signals they were added to.
+                ///
+                /// This must be done before the objects owning those signals
+                /// are destroyed. It is safe to call this several times.
+                pub(crate) unsafe fn remove_listeners(&mut self) {
+                    use $crate::wlroots_sys::server::WAYLAND_SERVER_HANDLE;
+                    $($(
+                        ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                                      wl_list_remove,
+                                      &mut self.$listener.link as *mut _ as _);
+                        ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                                      wl_list_init,
+                                      &mut self.$listener.link as *mut _ as _);
                     )*)*
-                    &mut $static_manager
+                }
+            }
+
+            impl Drop for $manager {
+                fn drop(&mut self) {
+                    unsafe { self.remove_listeners() }
                 }
             }
         )*
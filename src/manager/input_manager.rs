@@ -27,62 +27,79 @@ use crate::{
     utils::{safe_as_cstring, Handleable}
 };
 
-/// Callback triggered when an input device is added.
-///
-/// # Panics
-/// Any panic in this function will cause the process to abort.
-pub type InputAdded = fn(compositor_handle: compositor::Handle, device: &mut input::Device);
+callback_trait! {
+    /// Callback triggered when an input device is added.
+    ///
+    /// # Panics
+    /// Any panic in this function will cause the process to abort.
+    pub trait InputAdded = FnMut(compositor_handle: compositor::Handle, device: &mut input::Device);
+}
 
-/// Callback triggered when a keyboard device is added.
-///
-/// # Panics
-/// Any panic in this function will cause the process to abort.
-pub type KeyboardAdded = fn(
-    compositor_handle: compositor::Handle,
-    keyboard_handle: keyboard::Handle
-) -> Option<Box<keyboard::Handler>>;
+callback_trait! {
+    /// Callback triggered when a keyboard device is added.
+    ///
+    /// # Panics
+    /// Any panic in this function will cause the process to abort.
+    pub trait KeyboardAdded = FnMut(
+        compositor_handle: compositor::Handle,
+        keyboard_handle: keyboard::Handle
+    ) -> Option<Box<keyboard::Handler>>;
+}
 
-/// Callback triggered when a pointer device is added.
-///
-/// # Panics
-/// Any panic in this function will cause the process to abort.
-pub type PointerAdded = fn(
-    compositor_handle: compositor::Handle,
-    pointer_handle: pointer::Handle
-) -> Option<Box<pointer::Handler>>;
+callback_trait! {
+    /// Callback triggered when a pointer device is added.
+    ///
+    /// # Panics
+    /// Any panic in this function will cause the process to abort.
+    pub trait PointerAdded = FnMut(
+        compositor_handle: compositor::Handle,
+        pointer_handle: pointer::Handle
+    ) -> Option<Box<pointer::Handler>>;
+}
 
-/// Callback triggered when a touch device is added.
-///
-/// # Panics
-/// Any panic in this function will cause the process to abort.
-pub type TouchAdded =
-    fn(compositor_handle: compositor::Handle, touch_handle: touch::Handle) -> Option<Box<touch::Handler>>;
+callback_trait! {
+    /// Callback triggered when a touch device is added.
+    ///
+    /// # Panics
+    /// Any panic in this function will cause the process to abort.
+    pub trait TouchAdded = FnMut(
+        compositor_handle: compositor::Handle,
+        touch_handle: touch::Handle
+    ) -> Option<Box<touch::Handler>>;
+}
 
-/// Callback triggered when a tablet tool is added.
-///
-///
-/// # Panics
-/// Any panic in this function will cause the process to abort.
-pub type TabletToolAdded = fn(
-    compositor_handle: compositor::Handle,
-    tablet_tool_handle: tablet_tool::Handle
-) -> Option<Box<tablet_tool::Handler>>;
+callback_trait! {
+    /// Callback triggered when a tablet tool is added.
+    ///
+    ///
+    /// # Panics
+    /// Any panic in this function will cause the process to abort.
+    pub trait TabletToolAdded = FnMut(
+        compositor_handle: compositor::Handle,
+        tablet_tool_handle: tablet_tool::Handle
+    ) -> Option<Box<tablet_tool::Handler>>;
+}
 
-/// Callback triggered when a tablet pad is added.
-///
-///
-/// # Panics
-/// Any panic in this function will cause the process to abort.
-pub type TabletPadAdded = fn(
-    compositor_handle: compositor::Handle,
-    tablet_pad_handle: tablet_pad::Handle
-) -> Option<Box<tablet_pad::Handler>>;
+callback_trait! {
+    /// Callback triggered when a tablet pad is added.
+    ///
+    ///
+    /// # Panics
+    /// Any panic in this function will cause the process to abort.
+    pub trait TabletPadAdded = FnMut(
+        compositor_handle: compositor::Handle,
+        tablet_pad_handle: tablet_pad::Handle
+    ) -> Option<Box<tablet_pad::Handler>>;
+}
 
-pub type SwitchAdded =
-    fn(compositor_handle: compositor::Handle, switch_handle: switch::Handle) -> Option<Box<switch::Handler>>;
+callback_trait! {
+    pub trait SwitchAdded = FnMut(
+        compositor_handle: compositor::Handle,
+        switch_handle: switch::Handle
+    ) -> Option<Box<switch::Handler>>;
+}
 
 wayland_listener_static! {
-    static mut MANAGER;
     (Manager, Builder): [
         // NOTE
         // This is a macro hack to add these as arguments to the builder.
@@ -119,7 +136,8 @@ wayland_listener_static! {
                             }
                         };
                         let keyboard_handle = keyboard.weak_reference();
-                        let res = manager.keyboard_added.and_then(|f| f(compositor.clone(), keyboard_handle));
+                        let res = manager.keyboard_added.as_mut()
+                            .and_then(|f| f(compositor.clone(), keyboard_handle));
                         if let Some(keyboard_handler) = res {
                             let mut keyboard = KeyboardWrapper::new((keyboard,
                                                                      keyboard_handler));
@@ -147,7 +165,8 @@ wayland_listener_static! {
                             }
                         };
                         let pointer_handle = pointer.weak_reference();
-                        let res = manager.pointer_added.and_then(|f| f(compositor.clone(), pointer_handle));
+                        let res = manager.pointer_added.as_mut()
+                            .and_then(|f| f(compositor.clone(), pointer_handle));
                         if let Some(pointer_handler) = res {
                             let mut pointer = PointerWrapper::new((pointer, pointer_handler));
                             wl_signal_add(&mut (*dev.dev_union().pointer).events.motion as *mut _ as _,
@@ -173,7 +192,8 @@ wayland_listener_static! {
                             }
                         };
                         let touch_handle = touch.weak_reference();
-                        let res = manager.touch_added.and_then(|f| f(compositor.clone(), touch_handle));
+                        let res = manager.touch_added.as_mut()
+                            .and_then(|f| f(compositor.clone(), touch_handle));
                         if let Some(touch_handler) = res {
                             let mut touch = TouchWrapper::new((touch, touch_handler));
                             wl_signal_add(&mut (*dev.dev_union().touch).events.down as *mut _ as _,
@@ -198,7 +218,7 @@ wayland_listener_static! {
                             }
                         };
                         let tablet_tool_handle = tablet_tool.weak_reference();
-                        let res = manager.tablet_tool_added.and_then(|f| f(compositor.clone(),
+                        let res = manager.tablet_tool_added.as_mut().and_then(|f| f(compositor.clone(),
                                                                            tablet_tool_handle));
                         if let Some(tablet_tool_handler) = res {
                             let mut tablet_tool = TabletToolWrapper::new((tablet_tool,
@@ -226,7 +246,7 @@ wayland_listener_static! {
                             }
                         };
                         let tablet_pad_handle = tablet_pad.weak_reference();
-                        let res = manager.tablet_pad_added.and_then(|f| f(compositor.clone(),
+                        let res = manager.tablet_pad_added.as_mut().and_then(|f| f(compositor.clone(),
                                                                           tablet_pad_handle));
                         if let Some(tablet_pad_handler) = res {
                             let mut tablet_pad = TabletPadWrapper::new((tablet_pad,
@@ -252,7 +272,8 @@ wayland_listener_static! {
                             }
                         };
                         let switch_handle = switch.weak_reference();
-                        let res = manager.switch_added.and_then(|f| f(compositor.clone(), switch_handle));
+                        let res = manager.switch_added.as_mut()
+                            .and_then(|f| f(compositor.clone(), switch_handle));
                         if let Some(switch_handler) = res {
                             let mut switch = SwitchWrapper::new((switch, switch_handler));
                             let switch_ptr = &mut (*dev.dev_union().lid_switch);
@@ -264,7 +285,7 @@ wayland_listener_static! {
                         }
                     }
                 }
-                manager.input_added.map(|f| f(compositor, &mut dev))
+                manager.input_added.as_mut().map(|f| f(compositor, &mut dev))
             }));
             match res {
                 Ok(_) => {},
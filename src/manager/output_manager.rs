@@ -53,11 +53,14 @@ impl Destroyed {
     // TODO Functions which are safe to use
 }
 
-pub type OutputAdded =
-    fn(compositor_handle: compositor::Handle, output_builder: OutputBuilder) -> Option<BuilderResult>;
+callback_trait! {
+    pub trait OutputAdded = FnMut(
+        compositor_handle: compositor::Handle,
+        output_builder: OutputBuilder
+    ) -> Option<BuilderResult>;
+}
 
 wayland_listener_static! {
-    static mut MANAGER;
     (Manager, Builder): [
         (OutputAdded, add_listener, output_added) => (add_notify, add_callback):
         |manager: &mut Manager, data: *mut libc::c_void,| unsafe {
@@ -80,8 +83,8 @@ wayland_listener_static! {
             };
             let res = panic::catch_unwind(
                 panic::AssertUnwindSafe(|| manager.add_callback
-                                        .map(|f| f(compositor, builder))
-                                        .unwrap_or(None)));
+                                        .as_mut()
+                                        .and_then(|f| f(compositor, builder))));
             let build_result = match res {
                 Ok(res) => res,
                 // NOTE
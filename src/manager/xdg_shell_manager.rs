@@ -16,12 +16,15 @@ use crate::{
 
 pub type NewSurfaceResult = (Option<Box<xdg_shell::Handler>>, Option<Box<surface::Handler>>);
 
-/// Callback that is triggered when a new stable XDG shell surface appears.
-pub type NewSurface =
-    fn(compositor_handle: compositor::Handle, xdg_shell_handle: xdg_shell::Handle) -> NewSurfaceResult;
+callback_trait! {
+    /// Callback that is triggered when a new stable XDG shell surface appears.
+    pub trait NewSurface = FnMut(
+        compositor_handle: compositor::Handle,
+        xdg_shell_handle: xdg_shell::Handle
+    ) -> NewSurfaceResult;
+}
 
 wayland_listener_static! {
-    static mut MANAGER;
     (Manager, Builder): [
         (NewSurface, add_listener, surface_added) => (add_notify, surface_added):
         |manager: &mut Manager, data: *mut libc::c_void,|
@@ -52,7 +55,7 @@ wayland_listener_static! {
             let shell_surface = xdg_shell::Surface::new(xdg_surface, state);
 
             let (shell_surface_manager, surface_handler) =
-                match manager.surface_added {
+                match manager.surface_added.as_mut() {
                     None => (None, None),
                     Some(f) => f(compositor, shell_surface.weak_reference())
                 };
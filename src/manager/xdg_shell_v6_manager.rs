@@ -16,12 +16,15 @@ use crate::{
 
 pub type NewSurfaceResult = (Option<Box<xdg_shell_v6::Handler>>, Option<Box<surface::Handler>>);
 
-/// Callback that is triggered when a new XDG shell v6 surface appears.
-pub type NewSurface =
-    fn(compositor_handle: compositor::Handle, xdg_shell_v6_handle: xdg_shell_v6::Handle) -> NewSurfaceResult;
+callback_trait! {
+    /// Callback that is triggered when a new XDG shell v6 surface appears.
+    pub trait NewSurface = FnMut(
+        compositor_handle: compositor::Handle,
+        xdg_shell_v6_handle: xdg_shell_v6::Handle
+    ) -> NewSurfaceResult;
+}
 
 wayland_listener_static! {
-    static mut MANAGER;
     (Manager, Builder): [
         (NewSurface, add_listener, surface_added) => (add_notify, surface_added):
         |manager: &mut Manager, data: *mut libc::c_void,| unsafe {
@@ -52,7 +55,7 @@ wayland_listener_static! {
             let shell_surface = xdg_shell_v6::Surface::new(xdg_v6_surface, state);
 
             let (shell_surface_handler, surface_handler) =
-                match manager.surface_added {
+                match manager.surface_added.as_mut() {
                     None => (None, None),
                     Some(f) => f(compositor, shell_surface.weak_reference())
                 };
@@ -0,0 +1,519 @@
+//! Helpers for drawing server-side window decorations.
+//!
+//! Once a client has agreed to use server-side decorations (see
+//! `extensions::server_decoration`) the compositor has to draw the borders
+//! and title bar itself. A `Decoration` describes the decorations around a
+//! single toplevel, renders them with a `Renderer`, reports which part of
+//! them is under the cursor and tracks the areas that need to be redrawn.
+//!
+//! All coordinates are in output layout space, and the geometry given to a
+//! `Decoration` is the geometry of the toplevel contents (i.e. without any
+//! decorations).
+//!
+//! Text rendering is left to the compositor: render the title into a
+//! `Texture` however you like and pass it to `Decoration::render`.
+
+use crate::libc::c_double;
+use wlroots_sys::wl_output_transform::WL_OUTPUT_TRANSFORM_NORMAL;
+
+use crate::{
+    area::{Area, Origin, Size},
+    output::Output,
+    render::{matrix, Renderer, Texture},
+    utils::edges::Edges
+};
+
+/// The sizes and colors used when drawing decorations.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Theme {
+    /// Width of the border drawn around the toplevel and its title bar.
+    pub border_width: i32,
+    /// Height of the title bar.
+    pub title_height: i32,
+    /// Size of the square buttons in the title bar.
+    pub button_size: i32,
+    /// Space between the buttons and the edges of the title bar.
+    pub button_padding: i32,
+    /// Border and title bar color when the toplevel is focused.
+    pub focused_color: [f32; 4],
+    /// Border and title bar color when the toplevel is not focused.
+    pub unfocused_color: [f32; 4],
+    /// Color of the close button.
+    pub close_color: [f32; 4],
+    /// Color of the maximize button.
+    pub maximize_color: [f32; 4],
+    /// Color of the minimize button.
+    pub minimize_color: [f32; 4]
+}
+
+/// The part of a decoration found by `Decoration::hit_test`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Hit {
+    /// The title bar, usually used to move the toplevel.
+    Title,
+    /// The close button.
+    Close,
+    /// The maximize button.
+    Maximize,
+    /// The minimize button.
+    Minimize,
+    /// The border, usually used to resize the toplevel from these edges.
+    Border(Edges)
+}
+
+/// The server-side decorations of a single toplevel.
+#[derive(Debug, Clone)]
+pub struct Decoration {
+    theme: Theme,
+    geometry: Area,
+    title: String,
+    focused: bool,
+    /// Areas in output layout coordinates that need to be redrawn.
+    damage: Vec<Area>
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            border_width: 2,
+            title_height: 24,
+            button_size: 16,
+            button_padding: 4,
+            focused_color: [0.28, 0.47, 0.65, 1.0],
+            unfocused_color: [0.2, 0.2, 0.2, 1.0],
+            close_color: [0.8, 0.25, 0.25, 1.0],
+            maximize_color: [0.3, 0.7, 0.3, 1.0],
+            minimize_color: [0.85, 0.7, 0.2, 1.0]
+        }
+    }
+}
+
+impl Decoration {
+    /// Makes new decorations around the toplevel contents described by
+    /// `geometry`.
+    ///
+    /// The whole decoration starts out damaged.
+    pub fn new<T: Into<String>>(theme: Theme, geometry: Area, title: T, focused: bool) -> Self {
+        let mut decoration = Decoration {
+            theme,
+            geometry,
+            title: title.into(),
+            focused,
+            damage: Vec::new()
+        };
+        decoration.damage_whole();
+        decoration
+    }
+
+    /// Get the theme used to draw the decorations.
+    pub fn theme(&self) -> Theme {
+        self.theme
+    }
+
+    /// Change the theme used to draw the decorations.
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.damage_whole();
+        self.theme = theme;
+        self.damage_whole();
+    }
+
+    /// Get the geometry of the toplevel contents.
+    pub fn geometry(&self) -> Area {
+        self.geometry
+    }
+
+    /// Move or resize the toplevel contents the decorations are drawn around.
+    pub fn set_geometry(&mut self, geometry: Area) {
+        if self.geometry == geometry {
+            return;
+        }
+        self.damage_whole();
+        self.geometry = geometry;
+        self.damage_whole();
+    }
+
+    /// Get the title of the toplevel.
+    pub fn title(&self) -> &str {
+        self.title.as_str()
+    }
+
+    /// Change the title of the toplevel.
+    ///
+    /// Returns `true` if the title changed, in which case the title texture
+    /// passed to `render` should be updated.
+    pub fn set_title<T: Into<String>>(&mut self, title: T) -> bool {
+        let title = title.into();
+        if self.title == title {
+            return false;
+        }
+        self.title = title;
+        let title_area = self.title_area();
+        self.damage.push(title_area);
+        true
+    }
+
+    /// Determines if the decorations are drawn as focused.
+    pub fn focused(&self) -> bool {
+        self.focused
+    }
+
+    /// Change whether the decorations are drawn as focused.
+    pub fn set_focused(&mut self, focused: bool) {
+        if self.focused == focused {
+            return;
+        }
+        self.focused = focused;
+        self.damage_whole();
+    }
+
+    /// Get the area covered by the toplevel contents and its decorations.
+    pub fn bounds(&self) -> Area {
+        let Theme {
+            border_width,
+            title_height,
+            ..
+        } = self.theme;
+        let Area { origin, size } = self.geometry;
+        Area::new(
+            Origin::new(origin.x - border_width, origin.y - title_height - border_width),
+            Size::new(
+                size.width + 2 * border_width,
+                size.height + title_height + 2 * border_width
+            )
+        )
+    }
+
+    /// Get the area of the title bar, including the buttons.
+    pub fn title_area(&self) -> Area {
+        let Area { origin, size } = self.geometry;
+        Area::new(
+            Origin::new(origin.x, origin.y - self.theme.title_height),
+            Size::new(size.width, self.theme.title_height)
+        )
+    }
+
+    /// Get the area of the close button.
+    pub fn close_area(&self) -> Area {
+        self.button_area(0)
+    }
+
+    /// Get the area of the maximize button.
+    pub fn maximize_area(&self) -> Area {
+        self.button_area(1)
+    }
+
+    /// Get the area of the minimize button.
+    pub fn minimize_area(&self) -> Area {
+        self.button_area(2)
+    }
+
+    /// Get the areas of the borders, along with the edges each of them
+    /// resizes.
+    ///
+    /// Return value is in [top, bottom, left, right] order.
+    pub fn border_areas(&self) -> [(Area, Edges); 4] {
+        let border_width = self.theme.border_width;
+        let Area { origin, size } = self.geometry;
+        let bounds = self.bounds();
+        // NOTE The side borders overlap the top and bottom borders at the
+        // corners, so that hit testing a corner reports both edges.
+        let side_height = bounds.size.height;
+        [
+            (
+                Area::new(bounds.origin, Size::new(bounds.size.width, border_width)),
+                Edges::WLR_EDGE_TOP
+            ),
+            (
+                Area::new(
+                    Origin::new(bounds.origin.x, origin.y + size.height),
+                    Size::new(bounds.size.width, border_width)
+                ),
+                Edges::WLR_EDGE_BOTTOM
+            ),
+            (
+                Area::new(bounds.origin, Size::new(border_width, side_height)),
+                Edges::WLR_EDGE_LEFT
+            ),
+            (
+                Area::new(
+                    Origin::new(origin.x + size.width, bounds.origin.y),
+                    Size::new(border_width, side_height)
+                ),
+                Edges::WLR_EDGE_RIGHT
+            )
+        ]
+    }
+
+    /// Finds the part of the decorations at the given point in output layout
+    /// coordinates.
+    ///
+    /// Returns `None` if the point is over the toplevel contents or outside
+    /// of the decorations.
+    pub fn hit_test(&self, x: c_double, y: c_double) -> Option<Hit> {
+        if !self.bounds().contains_point(x, y) || self.geometry.contains_point(x, y) {
+            return None;
+        }
+        if self.close_area().contains_point(x, y) {
+            return Some(Hit::Close);
+        }
+        if self.maximize_area().contains_point(x, y) {
+            return Some(Hit::Maximize);
+        }
+        if self.minimize_area().contains_point(x, y) {
+            return Some(Hit::Minimize);
+        }
+        if self.title_area().contains_point(x, y) {
+            return Some(Hit::Title);
+        }
+        // Corners belong to two borders, so combine every edge under the point.
+        let edges = self
+            .border_areas()
+            .iter()
+            .filter(|(area, _)| area.contains_point(x, y))
+            .fold(Edges::WLR_EDGE_NONE, |edges, (_, edge)| edges | *edge);
+        if edges.is_empty() {
+            None
+        } else {
+            Some(Hit::Border(edges))
+        }
+    }
+
+    /// Marks the whole decoration as needing to be redrawn.
+    pub fn damage_whole(&mut self) {
+        let bounds = self.bounds();
+        self.damage.push(bounds);
+    }
+
+    /// Get the areas, in output layout coordinates, that need to be redrawn.
+    pub fn damage(&self) -> &[Area] {
+        self.damage.as_slice()
+    }
+
+    /// Adds the areas that need to be redrawn to the damage of the output.
+    ///
+    /// Call this for every output the decorations are on and then call
+    /// `clear_damage`.
+    pub fn damage_output(&self, output: &mut Output) {
+        for area in &self.damage {
+            let area = output.layout_area_to_buffer(*area);
+            output.damage().add_area(area);
+        }
+    }
+
+    /// Takes the areas that need to be redrawn, leaving nothing damaged.
+    pub fn take_damage(&mut self) -> Vec<Area> {
+        self.damage.drain(..).collect()
+    }
+
+    /// Forget about all the areas that need to be redrawn.
+    pub fn clear_damage(&mut self) {
+        self.damage.clear()
+    }
+
+    /// Renders the decorations to the output being rendered to.
+    ///
+    /// If a title texture is provided it is drawn at the start of the title
+    /// bar, clipped so that it does not overlap the buttons.
+    pub fn render(&self, renderer: &mut Renderer, title: Option<&Texture>) {
+        let color = if self.focused {
+            self.theme.focused_color
+        } else {
+            self.theme.unfocused_color
+        };
+        let transform_matrix = renderer.output.transform_matrix();
+        let title_area = renderer.output.layout_area_to_buffer(self.title_area());
+        renderer.render_colored_rect(title_area, color, transform_matrix);
+        for (area, _) in &self.border_areas() {
+            let area = renderer.output.layout_area_to_buffer(*area);
+            renderer.render_colored_rect(area, color, transform_matrix);
+        }
+        let buttons = [
+            (self.close_area(), self.theme.close_color),
+            (self.maximize_area(), self.theme.maximize_color),
+            (self.minimize_area(), self.theme.minimize_color)
+        ];
+        for (area, color) in &buttons {
+            if area.is_empty() {
+                continue;
+            }
+            let area = renderer.output.layout_area_to_buffer(*area);
+            renderer.render_colored_rect(area, *color, transform_matrix);
+        }
+        if let Some(texture) = title {
+            let padding = self.theme.button_padding;
+            let (width, height) = texture.size();
+            let label_width = self.label_width();
+            if label_width <= 0 {
+                return;
+            }
+            let title_area = self.title_area();
+            let label = Area::new(
+                Origin::new(
+                    title_area.origin.x + padding,
+                    title_area.origin.y + (title_area.size.height - height).max(0) / 2
+                ),
+                Size::new(width.min(label_width), height.min(title_area.size.height))
+            );
+            let label = renderer.output.layout_area_to_buffer(label);
+            let texture_area = label.with_size(Size::new(
+                (width as f32 * renderer.output.scale()) as i32,
+                (height as f32 * renderer.output.scale()) as i32
+            ));
+            let matrix = matrix::project_box(texture_area, WL_OUTPUT_TRANSFORM_NORMAL, 0.0, transform_matrix);
+            renderer.render_scissor(label);
+            renderer.render_texture_with_matrix(texture, matrix);
+            renderer.render_scissor(None);
+        }
+    }
+
+    /// Get the width left for the title label, between the start of the title
+    /// bar and the leftmost button that fits in it.
+    fn label_width(&self) -> i32 {
+        let title_area = self.title_area();
+        let end = [self.minimize_area(), self.maximize_area(), self.close_area()]
+            .iter()
+            .find(|area| !area.is_empty())
+            .map(|area| area.origin.x)
+            .unwrap_or(title_area.origin.x + title_area.size.width);
+        end - title_area.origin.x - 2 * self.theme.button_padding
+    }
+
+    /// Get the area of the button at the given index, counting from the right
+    /// of the title bar.
+    ///
+    /// If there is not enough room for the button the area is empty.
+    fn button_area(&self, index: i32) -> Area {
+        let Theme {
+            button_size,
+            button_padding,
+            title_height,
+            ..
+        } = self.theme;
+        let title_area = self.title_area();
+        let size = button_size.min(title_height - 2 * button_padding).max(0);
+        let x = title_area.origin.x + title_area.size.width - (index + 1) * (size + button_padding);
+        if x < title_area.origin.x + button_padding {
+            return Area::new(Origin::new(x, title_area.origin.y), Size::default());
+        }
+        Area::new(
+            Origin::new(x, title_area.origin.y + (title_height - size) / 2),
+            Size::new(size, size)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decoration(width: i32) -> Decoration {
+        Decoration::new(
+            Theme::default(),
+            Area::new(Origin::new(100, 100), Size::new(width, 100)),
+            "title",
+            true
+        )
+    }
+
+    #[test]
+    fn bounds() {
+        let decoration = decoration(200);
+        assert_eq!(
+            decoration.bounds(),
+            Area::new(Origin::new(98, 74), Size::new(204, 128))
+        );
+        assert_eq!(
+            decoration.title_area(),
+            Area::new(Origin::new(100, 76), Size::new(200, 24))
+        );
+    }
+
+    #[test]
+    fn button_area() {
+        let decoration = decoration(200);
+        assert_eq!(
+            decoration.close_area(),
+            Area::new(Origin::new(280, 80), Size::new(16, 16))
+        );
+        assert_eq!(
+            decoration.maximize_area(),
+            Area::new(Origin::new(260, 80), Size::new(16, 16))
+        );
+        assert_eq!(
+            decoration.minimize_area(),
+            Area::new(Origin::new(240, 80), Size::new(16, 16))
+        );
+    }
+
+    #[test]
+    fn button_area_without_room() {
+        let decoration = decoration(30);
+        assert_eq!(
+            decoration.close_area(),
+            Area::new(Origin::new(110, 80), Size::new(16, 16))
+        );
+        assert!(decoration.maximize_area().is_empty());
+        assert!(decoration.minimize_area().is_empty());
+
+        let mut decoration = decoration;
+        decoration.set_theme(Theme {
+            title_height: 8,
+            ..Theme::default()
+        });
+        assert!(decoration.close_area().is_empty());
+    }
+
+    #[test]
+    fn button_area_shrinks_with_title_bar() {
+        let mut decoration = decoration(200);
+        decoration.set_theme(Theme {
+            title_height: 12,
+            ..Theme::default()
+        });
+        assert_eq!(
+            decoration.close_area(),
+            Area::new(Origin::new(292, 92), Size::new(4, 4))
+        );
+    }
+
+    #[test]
+    fn label_width() {
+        assert_eq!(decoration(200).label_width(), 132);
+        // Only the close button fits, so the label ends there.
+        assert_eq!(decoration(30).label_width(), 2);
+        let mut decoration = decoration(200);
+        decoration.set_theme(Theme {
+            title_height: 8,
+            ..Theme::default()
+        });
+        assert_eq!(decoration.label_width(), 192);
+    }
+
+    #[test]
+    fn hit_test() {
+        let decoration = decoration(200);
+        assert_eq!(decoration.hit_test(150.0, 150.0), None);
+        assert_eq!(decoration.hit_test(50.0, 50.0), None);
+        assert_eq!(decoration.hit_test(285.0, 85.0), Some(Hit::Close));
+        assert_eq!(decoration.hit_test(265.0, 85.0), Some(Hit::Maximize));
+        assert_eq!(decoration.hit_test(245.0, 85.0), Some(Hit::Minimize));
+        assert_eq!(decoration.hit_test(150.0, 80.0), Some(Hit::Title));
+        assert_eq!(decoration.hit_test(257.0, 85.0), Some(Hit::Title));
+        assert_eq!(
+            decoration.hit_test(150.0, 75.0),
+            Some(Hit::Border(Edges::WLR_EDGE_TOP))
+        );
+        assert_eq!(
+            decoration.hit_test(99.0, 150.0),
+            Some(Hit::Border(Edges::WLR_EDGE_LEFT))
+        );
+        assert_eq!(
+            decoration.hit_test(99.0, 75.0),
+            Some(Hit::Border(Edges::WLR_EDGE_TOP | Edges::WLR_EDGE_LEFT))
+        );
+        assert_eq!(
+            decoration.hit_test(301.0, 201.0),
+            Some(Hit::Border(Edges::WLR_EDGE_BOTTOM | Edges::WLR_EDGE_RIGHT))
+        );
+    }
+}
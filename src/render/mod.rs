@@ -1,4 +1,6 @@
 #[cfg(feature = "unstable")]
+pub mod decoration;
+#[cfg(feature = "unstable")]
 mod image;
 #[cfg(feature = "unstable")]
 pub mod matrix;
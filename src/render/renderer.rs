@@ -1,17 +1,19 @@
 //! TODO Documentation
 
-use std::{ptr, time::Duration};
+use std::{ptr, slice, time::Duration};
 
-use crate::libc::{c_float, c_int, c_void};
+use crate::libc::{self, c_float, c_int, c_void};
 use wlroots_sys::{
     wl_shm_format, wlr_backend, wlr_backend_get_renderer, wlr_render_ellipse_with_matrix,
     wlr_render_quad_with_matrix, wlr_render_rect, wlr_render_texture, wlr_render_texture_with_matrix,
     wlr_renderer, wlr_renderer_begin, wlr_renderer_clear, wlr_renderer_destroy, wlr_renderer_end,
-    wlr_renderer_scissor, wlr_texture_destroy, wlr_texture_from_pixels
+    wlr_renderer_get_dmabuf_formats, wlr_renderer_get_dmabuf_modifiers, wlr_renderer_scissor,
+    wlr_texture_destroy, wlr_texture_from_dmabuf, wlr_texture_from_pixels
 };
 
 use crate::{
     area::Area,
+    dmabuf,
     output::Output,
     render::{texture::Texture, PixmanRegion}
 };
@@ -40,11 +42,18 @@ pub struct Renderer<'output> {
 impl GenericRenderer {
     /// Make a gles2 renderer.
     pub(crate) unsafe fn gles2_renderer(backend: *mut wlr_backend) -> Self {
+        GenericRenderer::try_gles2_renderer(backend)
+            .unwrap_or_else(|| panic!("Could not construct GLES2 renderer"))
+    }
+
+    /// Make a gles2 renderer, returning `None` if the backend has none.
+    pub(crate) unsafe fn try_gles2_renderer(backend: *mut wlr_backend) -> Option<Self> {
         let renderer = wlr_backend_get_renderer(backend);
         if renderer.is_null() {
-            panic!("Could not construct GLES2 renderer");
+            None
+        } else {
+            Some(GenericRenderer { renderer })
         }
-        GenericRenderer { renderer }
     }
 
     /// Drops a texture that was created explicitly through the renderer.
@@ -89,6 +98,47 @@ impl GenericRenderer {
         }
     }
 
+    /// Get the DRM fourcc formats this renderer can import DMA-BUF buffers
+    /// with.
+    pub fn dmabuf_formats(&self) -> Vec<u32> {
+        unsafe {
+            let mut formats = ptr::null_mut();
+            let len = wlr_renderer_get_dmabuf_formats(self.renderer, &mut formats);
+            copy_and_free(formats, len)
+                .into_iter()
+                .map(|format| format as u32)
+                .collect()
+        }
+    }
+
+    /// Get the DRM format modifiers this renderer supports for the given
+    /// DRM fourcc format.
+    pub fn dmabuf_modifiers(&self, format: u32) -> Vec<u64> {
+        unsafe {
+            let mut modifiers = ptr::null_mut();
+            let len = wlr_renderer_get_dmabuf_modifiers(self.renderer, format as c_int, &mut modifiers);
+            copy_and_free(modifiers, len)
+        }
+    }
+
+    /// Import a DMA-BUF buffer provided by a client as a texture.
+    ///
+    /// The attributes can be retrieved from a client buffer using
+    /// `dmabuf::Dmabuf::buffer_attributes`.
+    pub fn create_texture_from_dmabuf(
+        &mut self,
+        attributes: &mut dmabuf::Attributes
+    ) -> Option<Texture<'static>> {
+        unsafe {
+            let texture = wlr_texture_from_dmabuf(self.renderer, &mut attributes.attributes);
+            if texture.is_null() {
+                None
+            } else {
+                Some(Texture::from_ptr(texture))
+            }
+        }
+    }
+
     pub(crate) unsafe fn as_ptr(&self) -> *mut wlr_renderer {
         self.renderer
     }
@@ -209,3 +259,17 @@ unsafe fn create_texture_from_pixels(
         Some(Texture::from_ptr(texture))
     }
 }
+
+/// Copies a list allocated by wlroots into a `Vec` and frees the original.
+unsafe fn copy_and_free<T: Copy>(list: *mut T, len: c_int) -> Vec<T> {
+    if list.is_null() {
+        return Vec::new();
+    }
+    let result = if len > 0 {
+        slice::from_raw_parts(list, len as usize).to_vec()
+    } else {
+        Vec::new()
+    };
+    libc::free(list as *mut c_void);
+    result
+}
@@ -0,0 +1,296 @@
+//! Maps a container tree to the areas of its windows.
+
+#[cfg(feature = "unstable")]
+use crate::area::{Area, Origin, Size};
+use crate::tiling::tree::{Container, Node, Split, Tree};
+
+/// A rectangle, in the coordinates of the output being tiled.
+///
+/// With the `unstable` feature this converts to and from `Area`.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32
+}
+
+/// The space left around tiled windows.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+pub struct Gaps {
+    /// The space between windows.
+    pub inner: i32,
+    /// The space between the windows and the edges of the output.
+    pub outer: i32
+}
+
+/// How windows are tiled.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+pub struct Style {
+    pub gaps: Gaps,
+    /// The height of the tabs of tabbed containers and of the title bars of
+    /// stacked containers.
+    pub title_height: i32
+}
+
+/// Where a window goes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Placement<W> {
+    pub window: W,
+    /// The area of the window itself.
+    pub area: Rect,
+    /// The area of the window's tab or title bar, if it's in a tabbed or
+    /// stacked container.
+    pub title: Option<Rect>,
+    /// Whether the window is shown, which is only the case for the focused
+    /// child of tabbed and stacked containers.
+    pub visible: bool
+}
+
+/// Places the windows of the tree on an output of the given area.
+///
+/// The placements are in the same coordinates as the output area, and in
+/// the order of the windows in the tree.
+pub fn arrange<W: Clone>(tree: &Tree<W>, output: Rect, style: Style) -> Vec<Placement<W>> {
+    let outer = style.gaps.outer;
+    let area = Rect::new(
+        output.x + outer,
+        output.y + outer,
+        output.width - 2 * outer,
+        output.height - 2 * outer
+    );
+    let mut placements = Vec::new();
+    arrange_container(tree.root(), area, true, style, &mut placements);
+    placements
+}
+
+fn arrange_container<W: Clone>(
+    container: &Container<W>,
+    area: Rect,
+    visible: bool,
+    style: Style,
+    placements: &mut Vec<Placement<W>>
+) {
+    let children = container.children();
+    let count = children.len() as i32;
+    if count == 0 {
+        return;
+    }
+    let Rect { x, y, width, height } = area;
+    let (inner, title_height) = (style.gaps.inner, style.title_height);
+    for (index, child) in children.iter().enumerate() {
+        let i = index as i32;
+        let focused = index == container.focused();
+        let (child_area, title, child_visible) = match container.split() {
+            Split::Horizontal => {
+                let (start, size) = share(width, inner, count, i);
+                (Rect::new(x + start, y, size, height), None, visible)
+            },
+            Split::Vertical => {
+                let (start, size) = share(height, inner, count, i);
+                (Rect::new(x, y + start, width, size), None, visible)
+            },
+            Split::Tabbed => {
+                let (start, size) = share(width, 0, count, i);
+                (
+                    Rect::new(x, y + title_height, width, height - title_height),
+                    Some(Rect::new(x + start, y, size, title_height)),
+                    visible && focused
+                )
+            },
+            Split::Stacked => {
+                let titles = title_height * count;
+                (
+                    Rect::new(x, y + titles, width, height - titles),
+                    Some(Rect::new(x, y + title_height * i, width, title_height)),
+                    visible && focused
+                )
+            }
+        };
+        match *child {
+            Node::Window(ref window) => placements.push(Placement {
+                window: window.clone(),
+                area: child_area,
+                title,
+                visible: child_visible
+            }),
+            Node::Container(ref container) => {
+                arrange_container(container, child_area, child_visible, style, placements)
+            },
+        }
+    }
+}
+
+/// Splits a length between `count` children with `gap` between each,
+/// returning the start and length of the child at `index`.
+///
+/// The last child gets what's left from rounding.
+fn share(length: i32, gap: i32, count: i32, index: i32) -> (i32, i32) {
+    let available = (length - gap * (count - 1)).max(0);
+    let size = available / count;
+    let start = index * (size + gap);
+    if index == count - 1 {
+        (start, (length - start).max(0))
+    } else {
+        (start, size)
+    }
+}
+
+impl Rect {
+    /// Makes a rectangle, with a negative size clamped to 0.
+    pub fn new(x: i32, y: i32, width: i32, height: i32) -> Self {
+        Rect {
+            x,
+            y,
+            width: width.max(0),
+            height: height.max(0)
+        }
+    }
+}
+
+#[cfg(feature = "unstable")]
+impl From<Area> for Rect {
+    fn from(area: Area) -> Self {
+        Rect::new(area.origin.x, area.origin.y, area.size.width, area.size.height)
+    }
+}
+
+#[cfg(feature = "unstable")]
+impl From<Rect> for Area {
+    fn from(rect: Rect) -> Self {
+        Area::new(Origin::new(rect.x, rect.y), Size::new(rect.width, rect.height))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn style(inner: i32, outer: i32, title_height: i32) -> Style {
+        Style {
+            gaps: Gaps { inner, outer },
+            title_height
+        }
+    }
+
+    fn tree(split: Split, windows: &[u32]) -> Tree<u32> {
+        let mut tree = Tree::new(split);
+        for &window in windows {
+            tree.insert(window);
+        }
+        tree
+    }
+
+    #[test]
+    fn share() {
+        // (length, gap, count, index, start and length)
+        let cases = [
+            (100, 0, 1, 0, (0, 100)),
+            (100, 0, 2, 1, (50, 50)),
+            (100, 0, 3, 0, (0, 33)),
+            (100, 0, 3, 1, (33, 33)),
+            // The last child gets what's left from rounding.
+            (100, 0, 3, 2, (66, 34)),
+            (100, 10, 3, 0, (0, 26)),
+            (100, 10, 3, 1, (36, 26)),
+            (100, 10, 3, 2, (72, 28)),
+            // Gaps wider than the length leave nothing.
+            (10, 20, 2, 0, (0, 0)),
+            (10, 20, 2, 1, (20, 0))
+        ];
+        for &(length, gap, count, index, expected) in &cases {
+            assert_eq!(
+                super::share(length, gap, count, index),
+                expected,
+                "share({}, {}, {}, {})",
+                length,
+                gap,
+                count,
+                index
+            );
+        }
+    }
+
+    #[test]
+    fn empty() {
+        let output = Rect::new(0, 0, 100, 100);
+        assert!(arrange(&Tree::<u32>::default(), output, style(0, 0, 0)).is_empty());
+    }
+
+    #[test]
+    fn gaps() {
+        let output = Rect::new(10, 20, 110, 60);
+        let cases = [
+            (
+                Split::Horizontal,
+                style(10, 5, 0),
+                [Rect::new(15, 25, 45, 50), Rect::new(70, 25, 45, 50)]
+            ),
+            (
+                Split::Vertical,
+                style(10, 5, 0),
+                [Rect::new(15, 25, 100, 20), Rect::new(15, 55, 100, 20)]
+            ),
+            (
+                Split::Horizontal,
+                style(0, 0, 0),
+                [Rect::new(10, 20, 55, 60), Rect::new(65, 20, 55, 60)]
+            )
+        ];
+        for &(split, style, areas) in &cases {
+            let placements = arrange(&tree(split, &[1, 2]), output, style);
+            let arranged = placements.iter().map(|placement| placement.area).collect::<Vec<_>>();
+            assert_eq!(arranged, areas.to_vec(), "{:?} {:?}", split, style);
+            assert!(placements.iter().all(|placement| placement.visible && placement.title.is_none()));
+        }
+    }
+
+    #[test]
+    fn tabbed_and_stacked() {
+        let output = Rect::new(0, 0, 100, 100);
+        let cases = [
+            (
+                Split::Tabbed,
+                [Rect::new(0, 10, 100, 90), Rect::new(0, 10, 100, 90)],
+                [Rect::new(0, 0, 50, 10), Rect::new(50, 0, 50, 10)]
+            ),
+            (
+                Split::Stacked,
+                [Rect::new(0, 20, 100, 80), Rect::new(0, 20, 100, 80)],
+                [Rect::new(0, 0, 100, 10), Rect::new(0, 10, 100, 10)]
+            )
+        ];
+        for &(split, areas, titles) in &cases {
+            let placements = arrange(&tree(split, &[1, 2]), output, style(4, 0, 10));
+            for (index, placement) in placements.iter().enumerate() {
+                assert_eq!(placement.area, areas[index], "{:?}", split);
+                assert_eq!(placement.title, Some(titles[index]), "{:?}", split);
+                // Only the focused window, the last inserted, is shown.
+                assert_eq!(placement.visible, index == 1, "{:?}", split);
+            }
+        }
+    }
+
+    #[test]
+    fn hidden_containers() {
+        // T[1, V[2, 3]] with 1 focused hides both windows of the container.
+        let mut tree = tree(Split::Tabbed, &[1, 2]);
+        tree.split_focused(Split::Vertical);
+        tree.insert(3);
+        tree.focus(&1);
+        let placements = arrange(&tree, Rect::new(0, 0, 100, 100), style(0, 0, 10));
+        let visible = placements
+            .iter()
+            .map(|placement| (placement.window, placement.visible))
+            .collect::<Vec<_>>();
+        assert_eq!(visible, vec![(1, true), (2, false), (3, false)]);
+        assert_eq!(placements[1].area, Rect::new(0, 10, 100, 45));
+        assert_eq!(placements[2].area, Rect::new(0, 55, 100, 45));
+    }
+
+    #[test]
+    fn negative_sizes() {
+        let placements = arrange(&tree(Split::Horizontal, &[1]), Rect::new(0, 0, 10, 10), style(0, 20, 0));
+        assert_eq!(placements[0].area, Rect::new(20, 20, 0, 0));
+    }
+}
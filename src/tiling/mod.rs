@@ -0,0 +1,44 @@
+//! A container tree for tiling windows, behind the `tiling` feature.
+//!
+//! Windows are kept in a `Tree` of containers that place their children
+//! side by side, on top of each other, or as tabs or a stack. `arrange`
+//! maps the tree to the area of each window on an output, without touching
+//! wlroots, so neither needs the `unstable` feature. With it, `configure`
+//! sends the windows their new sizes:
+//!
+//! ```rust,no_run,ignore
+//! let mut tree = Tree::new(Split::Horizontal);
+//! tree.insert(window::Handle::XdgShell(terminal));
+//! tree.split_focused(Split::Vertical);
+//! tree.insert(window::Handle::Xwayland(browser));
+//! tree.focus_direction(Direction::Up);
+//!
+//! let output_area = layout.run(|layout| output.run(|output| layout.get_box(output)).unwrap()).unwrap();
+//! let style = Style { gaps: Gaps { inner: 4, outer: 8 }, title_height: 20 };
+//! let placements = arrange(&tree, output_area.into(), style);
+//! tiling::configure(&placements);
+//! for placement in placements.iter().filter(|placement| placement.visible) {
+//!     // Draw the window at placement.area.origin
+//! }
+//! ```
+
+mod arrange;
+mod tree;
+
+pub use self::arrange::{arrange, Gaps, Placement, Rect, Style};
+pub use self::tree::{Container, Direction, Node, Split, Tree};
+
+#[cfg(feature = "unstable")]
+use crate::shell::window;
+
+/// Sends the windows shown by an arrangement their sizes, and Xwayland
+/// windows their positions too.
+///
+/// The arrangement must be in layout coordinates. Windows that were dropped
+/// are skipped.
+#[cfg(feature = "unstable")]
+pub fn configure(placements: &[Placement<window::Handle>]) {
+    for placement in placements.iter().filter(|placement| placement.visible) {
+        placement.window.configure(placement.area.into()).ok();
+    }
+}
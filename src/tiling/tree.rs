@@ -0,0 +1,512 @@
+//! The container tree windows are tiled in.
+
+/// How a container places its children.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Split {
+    /// Side by side, from left to right.
+    Horizontal,
+    /// On top of each other, from top to bottom.
+    Vertical,
+    /// On top of each other, all taking the whole container, with a row of
+    /// tabs at the top.
+    Tabbed,
+    /// On top of each other, all taking the whole container, with a title
+    /// bar per child at the top.
+    Stacked
+}
+
+/// A direction to move the focus in.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Direction {
+    Left,
+    Right,
+    Up,
+    Down
+}
+
+/// A window, or a container of more nodes.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Node<W> {
+    Window(W),
+    Container(Container<W>)
+}
+
+/// A container splitting its area between its children.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Container<W> {
+    split: Split,
+    children: Vec<Node<W>>,
+    /// The index of the child that was focused last.
+    focused: usize
+}
+
+/// A tree of containers holding windows.
+///
+/// The focused window is found by following the child each container
+/// focused last, starting from the root.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Tree<W> {
+    root: Container<W>
+}
+
+impl Split {
+    /// Determines if moving in the direction goes from one child of a
+    /// container with this split to another.
+    fn moves_along(self, direction: Direction) -> bool {
+        match (self, direction) {
+            (Split::Horizontal, Direction::Left) |
+            (Split::Horizontal, Direction::Right) |
+            (Split::Tabbed, Direction::Left) |
+            (Split::Tabbed, Direction::Right) |
+            (Split::Vertical, Direction::Up) |
+            (Split::Vertical, Direction::Down) |
+            (Split::Stacked, Direction::Up) |
+            (Split::Stacked, Direction::Down) => true,
+            _ => false
+        }
+    }
+}
+
+impl<W> Container<W> {
+    pub fn new(split: Split) -> Self {
+        Container {
+            split,
+            children: Vec::new(),
+            focused: 0
+        }
+    }
+
+    /// Gets how the container places its children.
+    pub fn split(&self) -> Split {
+        self.split
+    }
+
+    /// Gets the children of the container, in order.
+    pub fn children(&self) -> &[Node<W>] {
+        &self.children
+    }
+
+    /// Gets the index of the child that was focused last.
+    pub fn focused(&self) -> usize {
+        self.focused
+    }
+
+    /// Follows the focused children down to a window.
+    fn focused_window(&self) -> Option<&W> {
+        match self.children.get(self.focused)? {
+            Node::Window(ref window) => Some(window),
+            Node::Container(ref container) => container.focused_window()
+        }
+    }
+
+    /// Removes a child, keeping the focus on the same child or, if it was
+    /// the one removed, on the one that took its place.
+    fn remove_child(&mut self, index: usize) -> Node<W> {
+        let child = self.children.remove(index);
+        if self.focused > index || self.focused >= self.children.len() {
+            self.focused = self.focused.saturating_sub(1);
+        }
+        child
+    }
+
+    fn focused_path(&self, path: &mut Vec<usize>) {
+        if let Some(child) = self.children.get(self.focused) {
+            path.push(self.focused);
+            if let Node::Container(ref container) = *child {
+                container.focused_path(path)
+            }
+        }
+    }
+}
+
+impl<W: PartialEq> Container<W> {
+    /// Finds the indices leading from this container to the window.
+    fn path_to(&self, window: &W) -> Option<Vec<usize>> {
+        for (index, child) in self.children.iter().enumerate() {
+            match *child {
+                Node::Window(ref child) if child == window => return Some(vec![index]),
+                Node::Container(ref container) => {
+                    if let Some(mut path) = container.path_to(window) {
+                        path.insert(0, index);
+                        return Some(path);
+                    }
+                },
+                _ => {}
+            }
+        }
+        None
+    }
+}
+
+impl<W> Default for Tree<W> {
+    fn default() -> Self {
+        Tree::new(Split::Horizontal)
+    }
+}
+
+impl<W> Tree<W> {
+    /// Makes an empty tree whose root container has the given split.
+    pub fn new(split: Split) -> Self {
+        Tree {
+            root: Container::new(split)
+        }
+    }
+
+    /// Gets the root container.
+    pub fn root(&self) -> &Container<W> {
+        &self.root
+    }
+
+    /// Determines if the tree has no windows.
+    pub fn is_empty(&self) -> bool {
+        self.root.children.is_empty()
+    }
+
+    /// Gets the focused window, if there is one.
+    pub fn focused(&self) -> Option<&W> {
+        self.root.focused_window()
+    }
+
+    /// Gets every window in the tree, in order.
+    pub fn windows(&self) -> Vec<&W> {
+        fn collect<'tree, W>(container: &'tree Container<W>, windows: &mut Vec<&'tree W>) {
+            for child in &container.children {
+                match *child {
+                    Node::Window(ref window) => windows.push(window),
+                    Node::Container(ref container) => collect(container, windows)
+                }
+            }
+        }
+        let mut windows = Vec::new();
+        collect(&self.root, &mut windows);
+        windows
+    }
+
+    /// Changes how the container of the focused window places its children.
+    pub fn set_split(&mut self, split: Split) {
+        let path = self.focused_path();
+        let parent = &path[..path.len().saturating_sub(1)];
+        self.container_mut(parent).split = split
+    }
+
+    /// Splits the focused window, so the next window inserted goes next to
+    /// it in a new container with the given split.
+    ///
+    /// If the focused window is alone in its container, the container's
+    /// split is changed instead.
+    pub fn split_focused(&mut self, split: Split) {
+        let path = self.focused_path();
+        let (&index, parent) = match path.split_last() {
+            Some(last) => last,
+            None => {
+                self.root.split = split;
+                return;
+            }
+        };
+        let parent = self.container_mut(parent);
+        if parent.children.len() == 1 {
+            parent.split = split;
+            return;
+        }
+        let mut container = Container::new(split);
+        let window = parent.children.remove(index);
+        container.children.push(window);
+        parent.children.insert(index, Node::Container(container));
+    }
+
+    fn focused_path(&self) -> Vec<usize> {
+        let mut path = Vec::new();
+        self.root.focused_path(&mut path);
+        path
+    }
+
+    fn container_mut(&mut self, path: &[usize]) -> &mut Container<W> {
+        let mut container = &mut self.root;
+        for &index in path {
+            container = match container.children[index] {
+                Node::Container(ref mut child) => child,
+                Node::Window(_) => panic!("Path led to a window")
+            };
+        }
+        container
+    }
+
+    /// Sets the focused child of every container along the path.
+    fn focus_path(&mut self, path: &[usize]) {
+        for end in 0..path.len() {
+            self.container_mut(&path[..end]).focused = path[end];
+        }
+    }
+
+    /// Removes the container at the path if it has no children left, and
+    /// replaces it with its child if it has only one, up to the root.
+    fn collapse(&mut self, mut path: Vec<usize>) {
+        while let Some(index) = path.pop() {
+            let parent = self.container_mut(&path);
+            let len = match parent.children[index] {
+                Node::Container(ref container) => container.children.len(),
+                Node::Window(_) => return
+            };
+            match len {
+                0 => {
+                    parent.remove_child(index);
+                },
+                1 => {
+                    let child = match parent.children[index] {
+                        Node::Container(ref mut container) => container.children.remove(0),
+                        Node::Window(_) => return
+                    };
+                    parent.children[index] = child;
+                    return;
+                },
+                _ => return
+            }
+        }
+    }
+}
+
+impl<W: PartialEq> Tree<W> {
+    /// Determines if the window is in the tree.
+    pub fn contains(&self, window: &W) -> bool {
+        self.root.path_to(window).is_some()
+    }
+
+    /// Inserts a window right after the focused one, in the same container,
+    /// and focuses it.
+    pub fn insert(&mut self, window: W) {
+        let path = self.focused_path();
+        let (index, parent) = match path.split_last() {
+            Some((&index, parent)) => (index + 1, parent.to_vec()),
+            None => (0, Vec::new())
+        };
+        self.container_mut(&parent)
+            .children
+            .insert(index, Node::Window(window));
+        let mut path = parent;
+        path.push(index);
+        self.focus_path(&path)
+    }
+
+    /// Removes a window from the tree, collapsing the containers left empty
+    /// or holding a single child.
+    ///
+    /// If it was focused, the focus moves to the window that took its place.
+    /// Returns `false` if the window was not in the tree.
+    pub fn remove(&mut self, window: &W) -> bool {
+        let path = match self.root.path_to(window) {
+            Some(path) => path,
+            None => return false
+        };
+        let (&index, parent) = path.split_last().expect("Path was empty");
+        let parent = parent.to_vec();
+        self.container_mut(&parent).remove_child(index);
+        self.collapse(parent);
+        true
+    }
+
+    /// Focuses the window.
+    ///
+    /// Returns `false` if the window was not in the tree.
+    pub fn focus(&mut self, window: &W) -> bool {
+        match self.root.path_to(window) {
+            Some(path) => {
+                self.focus_path(&path);
+                true
+            },
+            None => false
+        }
+    }
+
+    /// Moves the focus to the window next to the focused one in the
+    /// direction, returning it.
+    ///
+    /// The nearest container placing its children along the direction is
+    /// moved in, and the child it lands on is descended into following the
+    /// children focused last. Returns `None` if there is nothing in that
+    /// direction.
+    pub fn focus_direction(&mut self, direction: Direction) -> Option<&W> {
+        let mut path = self.focused_path();
+        path.pop()?;
+        let mut moved = false;
+        for depth in (0..=path.len()).rev() {
+            let parent = self.container_mut(&path[..depth]);
+            if !parent.split.moves_along(direction) {
+                continue;
+            }
+            let next = match direction {
+                Direction::Left | Direction::Up => parent.focused.checked_sub(1),
+                Direction::Right | Direction::Down => Some(parent.focused + 1)
+            };
+            if let Some(next) = next.filter(|&next| next < parent.children.len()) {
+                parent.focused = next;
+                moved = true;
+                break;
+            }
+        }
+        if !moved {
+            return None;
+        }
+        self.focused()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds `H[1, V[2, 3]]` with 3 focused.
+    fn nested() -> Tree<u32> {
+        let mut tree = Tree::new(Split::Horizontal);
+        tree.insert(1);
+        tree.insert(2);
+        tree.split_focused(Split::Vertical);
+        tree.insert(3);
+        tree
+    }
+
+    fn container(split: Split, children: Vec<Node<u32>>, focused: usize) -> Node<u32> {
+        Node::Container(Container {
+            split,
+            children,
+            focused
+        })
+    }
+
+    #[test]
+    fn insert() {
+        let mut tree = Tree::new(Split::Horizontal);
+        assert!(tree.is_empty());
+        assert_eq!(tree.focused(), None);
+        for &(window, windows) in &[(1, &[1][..]), (2, &[1, 2][..]), (3, &[1, 2, 3][..])] {
+            tree.insert(window);
+            assert_eq!(tree.focused(), Some(&window));
+            assert_eq!(tree.windows(), windows.iter().collect::<Vec<_>>());
+        }
+        // Goes right after the focused window.
+        tree.focus(&1);
+        tree.insert(4);
+        assert_eq!(tree.windows(), vec![&1, &4, &2, &3]);
+        assert_eq!(tree.focused(), Some(&4));
+        assert!(tree.contains(&4));
+        assert!(!tree.contains(&5));
+    }
+
+    #[test]
+    fn split_focused() {
+        let tree = nested();
+        assert_eq!(
+            tree.root().children(),
+            &[
+                Node::Window(1),
+                container(Split::Vertical, vec![Node::Window(2), Node::Window(3)], 1)
+            ][..]
+        );
+
+        // A window alone in its container changes the container's split.
+        let mut tree = Tree::new(Split::Horizontal);
+        tree.split_focused(Split::Stacked);
+        assert_eq!(tree.root().split(), Split::Stacked);
+        tree.insert(1);
+        tree.split_focused(Split::Tabbed);
+        assert_eq!(tree.root().split(), Split::Tabbed);
+        assert_eq!(tree.root().children(), &[Node::Window(1)][..]);
+    }
+
+    #[test]
+    fn set_split() {
+        let mut tree = nested();
+        tree.set_split(Split::Stacked);
+        assert_eq!(tree.root().split(), Split::Horizontal);
+        match tree.root().children()[1] {
+            Node::Container(ref container) => assert_eq!(container.split(), Split::Stacked),
+            Node::Window(_) => panic!("Expected a container")
+        }
+    }
+
+    #[test]
+    fn remove() {
+        let mut tree = nested();
+        assert!(!tree.remove(&4));
+        // The vertical container is left with one child, which replaces it.
+        assert!(tree.remove(&2));
+        assert_eq!(tree.root().children(), &[Node::Window(1), Node::Window(3)][..]);
+        assert_eq!(tree.focused(), Some(&3));
+        assert!(tree.remove(&3));
+        assert_eq!(tree.focused(), Some(&1));
+        assert!(tree.remove(&1));
+        assert!(tree.is_empty());
+        assert_eq!(tree.focused(), None);
+    }
+
+    #[test]
+    fn remove_collapses_empty_containers() {
+        let mut tree = Tree::new(Split::Horizontal);
+        tree.insert(1);
+        tree.insert(2);
+        tree.split_focused(Split::Vertical);
+        assert!(tree.remove(&2));
+        assert_eq!(tree.root().children(), &[Node::Window(1)][..]);
+        assert_eq!(tree.focused(), Some(&1));
+    }
+
+    #[test]
+    fn remove_child_focus() {
+        // (focused, removed, focused after)
+        let cases = [(2, 0, 1), (0, 2, 0), (1, 1, 1), (2, 2, 1), (0, 0, 0)];
+        for &(focused, removed, expected) in &cases {
+            let mut container = Container {
+                split: Split::Horizontal,
+                children: vec![Node::Window(1), Node::Window(2), Node::Window(3)],
+                focused
+            };
+            container.remove_child(removed);
+            assert_eq!(
+                container.focused(),
+                expected,
+                "Removing {} while {} was focused",
+                removed,
+                focused
+            );
+        }
+    }
+
+    #[test]
+    fn focus_direction() {
+        // Each step starts from where the previous one left off.
+        let steps = [
+            (Direction::Up, Some(2)),
+            (Direction::Up, None),
+            (Direction::Down, Some(3)),
+            (Direction::Left, Some(1)),
+            (Direction::Left, None),
+            (Direction::Up, None),
+            // Descends into the child the container focused last.
+            (Direction::Right, Some(3)),
+            (Direction::Right, None)
+        ];
+        let mut tree = nested();
+        for &(direction, expected) in &steps {
+            assert_eq!(tree.focus_direction(direction).cloned(), expected, "{:?}", direction);
+            if expected.is_none() {
+                assert!(tree.focused().is_some());
+            }
+        }
+        assert_eq!(Tree::<u32>::default().focus_direction(Direction::Left), None);
+    }
+
+    #[test]
+    fn focus_direction_tabbed_and_stacked() {
+        let cases = [
+            (Split::Tabbed, Direction::Left, Some(1)),
+            (Split::Tabbed, Direction::Up, None),
+            (Split::Stacked, Direction::Up, Some(1)),
+            (Split::Stacked, Direction::Left, None)
+        ];
+        for &(split, direction, expected) in &cases {
+            let mut tree = Tree::new(split);
+            tree.insert(1);
+            tree.insert(2);
+            assert_eq!(tree.focus_direction(direction).cloned(), expected, "{:?} {:?}", split, direction);
+        }
+    }
+}
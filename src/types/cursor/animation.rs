@@ -0,0 +1,169 @@
+//! Plays back the frames of animated xcursors on a `wlr_cursor`.
+
+use std::{cell::RefCell, rc::Rc, time::Duration};
+
+use wlroots_sys::{wlr_cursor, wlr_cursor_set_image};
+
+use crate::{
+    cursor::xcursor,
+    event_loop::{EventLoop, Timer}
+};
+
+/// An owned copy of an `xcursor::Image`.
+#[derive(Debug)]
+struct Frame {
+    buffer: Vec<u8>,
+    width: u32,
+    height: u32,
+    hotspot_x: u32,
+    hotspot_y: u32,
+    delay: u32
+}
+
+/// The frames of an xcursor at each scale it was loaded at.
+#[derive(Debug)]
+struct Frames {
+    cursor: *mut wlr_cursor,
+    scales: Vec<(f32, Vec<Frame>)>,
+    current: usize,
+    /// The timer advancing the frames, if the xcursor is animated.
+    timer: Option<Timer>
+}
+
+/// An xcursor image set on a cursor, animated if it has several frames.
+#[derive(Debug)]
+pub(crate) struct Animation {
+    name: String,
+    event_loop: EventLoop,
+    frames: Rc<RefCell<Frames>>
+}
+
+impl Animation {
+    /// Sets the first frame of the named xcursor on the cursor, at every scale
+    /// the manager has loaded, and starts animating it.
+    ///
+    /// # Safety
+    /// The animation must be dropped before the cursor is destroyed.
+    pub(crate) unsafe fn start(
+        cursor: *mut wlr_cursor,
+        event_loop: &EventLoop,
+        manager: &xcursor::Manager,
+        name: &str
+    ) -> Option<Self> {
+        let scales = manager
+            .scaled_themes()
+            .into_iter()
+            .filter_map(|theme| {
+                let scale = theme.scale();
+                let xcursor = manager.get_xcursor(name.to_string(), scale)?;
+                let frames = xcursor.images().iter().map(Frame::new).collect::<Vec<_>>();
+                if frames.is_empty() {
+                    None
+                } else {
+                    Some((scale, frames))
+                }
+            })
+            .collect::<Vec<_>>();
+        if scales.is_empty() {
+            wlr_log!(WLR_ERROR, "Could not find xcursor {}", name);
+            return None;
+        }
+        let frames = Rc::new(RefCell::new(Frames {
+            cursor,
+            scales,
+            current: 0,
+            timer: None
+        }));
+        frames.borrow().show();
+        if frames.borrow().is_animated() {
+            let weak_frames = Rc::downgrade(&frames);
+            let timer = event_loop.add_timer(Duration::from_millis(0), move |_| {
+                if let Some(frames) = weak_frames.upgrade() {
+                    let mut frames = frames.borrow_mut();
+                    frames.current = (frames.current + 1) % frames.len();
+                    frames.show();
+                    frames.arm();
+                }
+            })?;
+            let mut frames = frames.borrow_mut();
+            frames.timer = Some(timer);
+            frames.arm();
+        }
+        Some(Animation {
+            name: name.to_string(),
+            event_loop: event_loop.clone(),
+            frames
+        })
+    }
+
+    /// Gets the name of the xcursor being shown.
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Gets the event loop driving the animation.
+    pub(crate) fn event_loop(&self) -> EventLoop {
+        self.event_loop.clone()
+    }
+}
+
+impl Frames {
+    /// Whether there are several frames and any of them has a delay, at the
+    /// first scale.
+    fn is_animated(&self) -> bool {
+        self.len() > 1 && self.scales[0].1.iter().any(|frame| frame.delay > 0)
+    }
+
+    /// The number of frames, at the first scale.
+    fn len(&self) -> usize {
+        self.scales[0].1.len()
+    }
+
+    /// Arms the timer to show the next frame once the current one's delay
+    /// elapsed.
+    fn arm(&mut self) {
+        // A zero delay would disarm the timer and stop the animation.
+        let delay = self.delay().max(Duration::from_millis(1));
+        if let Some(timer) = self.timer.as_mut() {
+            timer.update(delay)
+        }
+    }
+
+    /// The delay of the current frame, at the first scale.
+    fn delay(&self) -> Duration {
+        let frames = &self.scales[0].1;
+        Duration::from_millis(u64::from(frames[self.current % frames.len()].delay))
+    }
+
+    /// Sets the current frame on the cursor, at every scale.
+    fn show(&self) {
+        for (scale, frames) in &self.scales {
+            let frame = &frames[self.current % frames.len()];
+            unsafe {
+                wlr_cursor_set_image(
+                    self.cursor,
+                    frame.buffer.as_ptr(),
+                    (frame.width * 4) as i32,
+                    frame.width,
+                    frame.height,
+                    frame.hotspot_x as _,
+                    frame.hotspot_y as _,
+                    *scale
+                )
+            }
+        }
+    }
+}
+
+impl Frame {
+    fn new(image: &xcursor::Image) -> Self {
+        Frame {
+            buffer: image.buffer.to_vec(),
+            width: image.width,
+            height: image.height,
+            hotspot_x: image.hotspot_x,
+            hotspot_y: image.hotspot_y,
+            delay: image.delay
+        }
+    }
+}
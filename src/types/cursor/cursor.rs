@@ -20,7 +20,8 @@ use wlroots_sys::{
 use crate::{
     area::Area,
     compositor,
-    cursor::xcursor,
+    cursor::{animation::Animation, xcursor},
+    event_loop::EventLoop,
     input::{self, pointer, tablet_tool, touch},
     output::{self, layout::Layout, Output},
     surface::Surface,
@@ -36,7 +37,9 @@ pub(crate) struct CursorState {
     /// that they cannot be upgraded.
     counter: Rc<Cell<bool>>,
     /// A raw pointer to the Cursor on the heap
-    cursor: *mut Cursor
+    cursor: *mut Cursor,
+    /// The xcursor image being shown, if any.
+    animation: Option<Animation>
 }
 
 // NOTE We can't use `utils::Handle` because we own the cursor.
@@ -415,7 +418,8 @@ impl Cursor {
             let state = Box::new(CursorState {
                 counter,
                 cursor: Box::into_raw(cursor),
-                output_layout: None
+                output_layout: None,
+                animation: None
             });
             (*cursor_ptr).data = Box::into_raw(state) as *mut libc::c_void;
             Handle {
@@ -529,7 +533,10 @@ impl Cursor {
 
     // TODO USE IMAGE
     /// Sets the image of the cursor to the image.
+    ///
+    /// This stops any xcursor animation set with `set_xcursor`.
     pub fn set_cursor_image(&mut self, image: &xcursor::Image) {
+        self.stop_animation();
         unsafe {
             // NOTE Rationale for why lifetime isn't attached:
             //
@@ -553,10 +560,13 @@ impl Cursor {
     /// cursor image. The surface position is substracted from the hotspot.
     ///
     /// A `None` surface commit hides the cursor.
+    ///
+    /// This stops any xcursor animation set with `set_xcursor`.
     pub fn set_surface<'this, O>(&'this mut self, surface: O, hotspot_x: i32, hotspot_y: i32)
     where
         O: Into<Option<&'this Surface>>
     {
+        self.stop_animation();
         unsafe {
             let surface_ptr = surface
                 .into()
@@ -566,6 +576,65 @@ impl Cursor {
         }
     }
 
+    /// Sets the image of the cursor to the named xcursor, e.g. "left_ptr" or
+    /// "wait", at every scale the manager loaded a theme at.
+    ///
+    /// Animated xcursors are played back using timers on the event loop, until
+    /// another image or a surface is set on the cursor.
+    ///
+    /// Returns false if the manager has no such xcursor.
+    pub fn set_xcursor(&mut self, event_loop: &EventLoop, manager: &xcursor::Manager, name: &str) -> bool {
+        self.stop_animation();
+        unsafe {
+            let animation = match Animation::start(self.data.0, event_loop, manager, name) {
+                Some(animation) => animation,
+                None => return false
+            };
+            (*((*self.data.0).data as *mut CursorState)).animation = Some(animation);
+        }
+        true
+    }
+
+    /// Loads the theme of the manager at the scale (e.g. when an output with a
+    /// new scale factor was added) and updates the xcursor set with
+    /// `set_xcursor` to use it.
+    ///
+    /// Returns false if the theme could not be loaded.
+    pub fn load_xcursor_scale(&mut self, manager: &xcursor::Manager, scale: f32) -> bool {
+        // NOTE `Manager::load` returns true when it fails.
+        if manager.load(scale) {
+            return false;
+        }
+        let current = unsafe {
+            (*((*self.data.0).data as *mut CursorState))
+                .animation
+                .as_ref()
+                .map(|animation| (animation.event_loop(), animation.name().to_string()))
+        };
+        if let Some((event_loop, name)) = current {
+            self.set_xcursor(&event_loop, manager, &name);
+        }
+        true
+    }
+
+    /// Gets the name of the xcursor set with `set_xcursor`, if it's still
+    /// being shown.
+    pub fn xcursor_name(&self) -> Option<String> {
+        unsafe {
+            (*((*self.data.0).data as *mut CursorState))
+                .animation
+                .as_ref()
+                .map(|animation| animation.name().to_string())
+        }
+    }
+
+    /// Stops the xcursor animation set with `set_xcursor`, if any.
+    pub(crate) fn stop_animation(&self) {
+        unsafe {
+            (*((*self.data.0).data as *mut CursorState)).animation = None;
+        }
+    }
+
     /// Attaches this input device to this cursor. The input device must be one
     /// of:
     ///
@@ -1,4 +1,6 @@
 #[cfg(feature = "unstable")]
+mod animation;
+#[cfg(feature = "unstable")]
 #[allow(clippy::module_inception)]
 mod cursor;
 pub mod xcursor;
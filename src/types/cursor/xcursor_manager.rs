@@ -124,8 +124,11 @@ impl Manager {
     /// factors. The `Cursor` will take over from this point and ensure the
     /// correct cursor is used on each output, assuming an `OutputLayout` is
     /// attached to it.
+    ///
+    /// This stops any xcursor animation set with `Cursor::set_xcursor`.
     pub fn set_cursor_image(&mut self, name: String, cursor: &Cursor) {
         let name_str = safe_as_cstring(name);
+        cursor.stop_animation();
         unsafe {
             wlr_xcursor_manager_set_cursor_image(self.manager, name_str.as_ptr(), cursor.as_ptr());
         }
@@ -1,38 +1,174 @@
-//! Support for the DMABuf type
+//! Support for the linux-dmabuf-unstable-v1 protocol.
+//!
+//! Enable it with `compositor::Builder::linux_dmabuf`, or make the global
+//! yourself with `Dmabuf::new`. Both require the GLES2 renderer to also be
+//! enabled.
 
-use wlroots_sys::{wl_display, wlr_linux_dmabuf_v1, wlr_linux_dmabuf_v1_create, 
-                wlr_linux_dmabuf_v1_destroy};
-use {crate::compositor::Compositor,
-    crate::render::GenericRenderer};
+use std::{fmt, ptr};
 
+use crate::libc::{self, c_int};
+use crate::wayland_sys::server::{
+    signal::wl_signal_add, wl_display as wl_server_display, WAYLAND_SERVER_HANDLE
+};
+use wlroots_sys::{
+    wl_display, wl_resource, wlr_dmabuf_attributes, wlr_dmabuf_v1_buffer_from_buffer_resource,
+    wlr_dmabuf_v1_resource_is_buffer, wlr_linux_dmabuf_v1, wlr_linux_dmabuf_v1_create,
+    wlr_linux_dmabuf_v1_destroy
+};
 
-#[derive(Debug)]
+use crate::{compositor::Compositor, render::GenericRenderer};
+
+wayland_listener!(DestroyListener, *mut wlr_linux_dmabuf_v1, [
+    destroy_listener => destroy_notify: |this: &mut DestroyListener, _data: *mut libc::c_void,|
+    unsafe {
+        ffi_dispatch!(
+            WAYLAND_SERVER_HANDLE,
+            wl_list_remove,
+            &mut (*this.destroy_listener()).link as *mut _ as _
+        );
+        this.data = ptr::null_mut();
+    };
+]);
+
+/// The linux-dmabuf global, which lets clients share GPU buffers with
+/// the compositor.
+///
+/// The global is destroyed when this is dropped, or along with the
+/// compositor if that happens first.
 pub struct Dmabuf {
-    dmabuf: *mut wlr_linux_dmabuf_v1
+    /// Keeps track of the global, which is null once wlroots destroyed it.
+    listener: Box<DestroyListener>
+}
+
+/// A single plane of a DMA-BUF buffer.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Plane {
+    pub fd: c_int,
+    pub offset: u32,
+    pub stride: u32
+}
+
+/// The attributes describing a DMA-BUF buffer.
+///
+/// The file descriptors are owned by the buffer they came from,
+/// so they are only valid for as long as that buffer is alive.
+#[derive(Debug, Clone, Copy)]
+pub struct Attributes {
+    pub(crate) attributes: wlr_dmabuf_attributes
 }
 
 impl Dmabuf {
+    /// Makes a linux-dmabuf global using the renderer of the compositor.
+    ///
+    /// Returns `None` if the compositor was built without the GLES2
+    /// renderer, or if the global could not be created.
     pub fn new(compositor: &Compositor) -> Option<Self> {
-        unsafe {
-            // Get the renderer from compositor
-            let renderer: Option<&GenericRenderer> = compositor.renderer.as_ref();
-            match renderer {
-                Some(ref renderer) => {
-                    let dmabuf_raw = wlr_linux_dmabuf_v1_create(compositor.display as *mut wl_display, renderer.as_ptr());
-                    if !dmabuf_raw.is_null() {
-                        Some(Dmabuf { dmabuf: dmabuf_raw })
-                    } else {
-                        None
-                    }
-                }
-                None => None
-            }
+        let renderer = compositor.renderer.as_ref()?;
+        unsafe { Dmabuf::from_renderer(compositor.display, renderer) }
+    }
+
+    pub(crate) unsafe fn from_renderer(
+        display: *mut wl_server_display,
+        renderer: &GenericRenderer
+    ) -> Option<Self> {
+        let dmabuf_raw = wlr_linux_dmabuf_v1_create(display as *mut wl_display, renderer.as_ptr());
+        if dmabuf_raw.is_null() {
+            return None;
+        }
+        let mut listener = DestroyListener::new(dmabuf_raw);
+        wl_signal_add(
+            &mut (*dmabuf_raw).events.destroy as *mut _ as _,
+            listener.destroy_listener() as _
+        );
+        Some(Dmabuf { listener })
+    }
+
+    /// Determines if the buffer resource was created using linux-dmabuf.
+    ///
+    /// # Safety
+    /// The resource must be a valid `wl_buffer` resource, e.g. the one
+    /// returned by `surface::State::buffer`.
+    pub unsafe fn is_buffer(buffer_resource: *mut wl_resource) -> bool {
+        wlr_dmabuf_v1_resource_is_buffer(buffer_resource)
+    }
+
+    /// Gets the attributes of a buffer created using linux-dmabuf.
+    ///
+    /// Returns `None` if the buffer was not created using linux-dmabuf.
+    ///
+    /// # Safety
+    /// The resource must be a valid `wl_buffer` resource, e.g. the one
+    /// returned by `surface::State::buffer`.
+    pub unsafe fn buffer_attributes(buffer_resource: *mut wl_resource) -> Option<Attributes> {
+        if buffer_resource.is_null() || !wlr_dmabuf_v1_resource_is_buffer(buffer_resource) {
+            return None;
         }
+        let buffer = wlr_dmabuf_v1_buffer_from_buffer_resource(buffer_resource);
+        if buffer.is_null() {
+            None
+        } else {
+            Some(Attributes {
+                attributes: (*buffer).attributes
+            })
+        }
+    }
+}
+
+impl fmt::Debug for Dmabuf {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Dmabuf {:p}", self.listener.data)
     }
 }
 
 impl Drop for Dmabuf {
     fn drop(&mut self) {
-        unsafe { wlr_linux_dmabuf_v1_destroy(self.dmabuf) }
+        let dmabuf = self.listener.data;
+        if dmabuf.is_null() {
+            return;
+        }
+        unsafe {
+            ffi_dispatch!(
+                WAYLAND_SERVER_HANDLE,
+                wl_list_remove,
+                &mut (*self.listener.destroy_listener()).link as *mut _ as _
+            );
+            wlr_linux_dmabuf_v1_destroy(dmabuf)
+        }
+    }
+}
+
+impl Attributes {
+    /// Get the size of the buffer.
+    ///
+    /// Return value is in (width, height) format.
+    pub fn size(&self) -> (i32, i32) {
+        (self.attributes.width, self.attributes.height)
+    }
+
+    /// Get the DRM fourcc format of the buffer.
+    pub fn format(&self) -> u32 {
+        self.attributes.format
+    }
+
+    /// Get the DRM format modifier of the buffer.
+    pub fn modifier(&self) -> u64 {
+        self.attributes.modifier
+    }
+
+    /// Get the flags the buffer was created with.
+    pub fn flags(&self) -> u32 {
+        self.attributes.flags
+    }
+
+    /// Get the planes that make up the buffer.
+    pub fn planes(&self) -> Vec<Plane> {
+        let n_planes = self.attributes.n_planes.max(0) as usize;
+        (0..n_planes.min(self.attributes.fd.len()))
+            .map(|index| Plane {
+                fd: self.attributes.fd[index],
+                offset: self.attributes.offset[index],
+                stride: self.attributes.stride[index]
+            })
+            .collect()
     }
 }
@@ -0,0 +1,464 @@
+//! Recognizes multi-finger touch gestures, so compositor actions can be
+//! bound to them.
+//!
+//! Feed the touch events of a device to a `GestureRecognizer`. Once all the
+//! fingers are lifted the gesture they made (a swipe or a pinch) is given to
+//! the callback. A long press is reported while the fingers are still down.
+//!
+//! ```rust,no_run,ignore
+//! let gestures = GestureRecognizer::new(&event_loop, Config::default(), |compositor_handle, gesture| {
+//!     match *gesture {
+//!         Gesture::Swipe { fingers: 3, direction: SwipeDirection::Left, .. } => next_workspace(),
+//!         Gesture::Pinch { fingers: 4, scale, .. } if scale < 1.0 => show_overview(),
+//!         _ => {}
+//!     }
+//! })?;
+//! ```
+//!
+//! Locations are in the units of the touch events' `location`.
+
+use std::{cell::RefCell, collections::BTreeMap, rc::Rc, time::Duration};
+
+use crate::{
+    compositor,
+    event_loop::{EventLoop, Timer},
+    input::touch::event
+};
+
+type Callback = Box<FnMut(compositor::Handle, &Gesture)>;
+
+/// A recognized touch gesture.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Gesture {
+    /// The fingers moved together in one direction.
+    Swipe {
+        fingers: u32,
+        direction: SwipeDirection,
+        /// How far the fingers moved, on average.
+        delta: (f64, f64)
+    },
+    /// The fingers moved towards or away from each other.
+    Pinch {
+        fingers: u32,
+        /// The ratio between the final and initial spread of the fingers,
+        /// i.e. below 1 when pinching in and above 1 when pinching out.
+        scale: f64
+    },
+    /// The fingers were held still.
+    LongPress {
+        fingers: u32,
+        /// The center of the fingers.
+        location: (f64, f64)
+    }
+}
+
+/// The main direction of a swipe.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum SwipeDirection {
+    Up,
+    Down,
+    Left,
+    Right
+}
+
+/// The thresholds used to recognize gestures.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Config {
+    /// The least number of fingers a swipe or pinch is made with.
+    ///
+    /// Single finger gestures conflict with regular touch input, so this
+    /// defaults to 2.
+    pub min_fingers: u32,
+    /// How far the fingers have to move to make a swipe.
+    pub swipe_distance: f64,
+    /// How much the spread of the fingers has to change to make a pinch,
+    /// relative to the initial spread.
+    pub pinch_scale: f64,
+    /// How long the fingers have to be held down to make a long press.
+    pub long_press_delay: Duration,
+    /// How far the fingers can move during a long press.
+    pub long_press_tolerance: f64
+}
+
+/// Recognizes gestures from the touch events of a device.
+pub struct GestureRecognizer {
+    state: Rc<RefCell<State>>,
+    callback: Rc<RefCell<Callback>>
+}
+
+struct State {
+    config: Config,
+    /// The touch points that are down, with their initial and current
+    /// locations.
+    points: BTreeMap<i32, ((f64, f64), (f64, f64))>,
+    /// The locations of the points that were lifted during the gesture.
+    lifted: Vec<((f64, f64), (f64, f64))>,
+    /// The most fingers that were down at once during the gesture.
+    fingers: u32,
+    /// Set once the gesture was recognized as a long press or cancelled, so
+    /// lifting the fingers doesn't report anything.
+    done: bool,
+    timer: Option<Timer>
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            min_fingers: 2,
+            swipe_distance: 0.1,
+            pinch_scale: 0.2,
+            long_press_delay: Duration::from_millis(500),
+            long_press_tolerance: 0.02
+        }
+    }
+}
+
+impl GestureRecognizer {
+    /// Makes a gesture recognizer, driven by the event loop for long
+    /// presses.
+    pub fn new<F>(event_loop: &EventLoop, config: Config, callback: F) -> Option<Self>
+    where
+        F: FnMut(compositor::Handle, &Gesture) + 'static
+    {
+        let state = Rc::new(RefCell::new(State {
+            config,
+            points: BTreeMap::new(),
+            lifted: Vec::new(),
+            fingers: 0,
+            done: false,
+            timer: None
+        }));
+        let callback: Rc<RefCell<Callback>> = Rc::new(RefCell::new(Box::new(callback)));
+        let weak_state = Rc::downgrade(&state);
+        let weak_callback = Rc::downgrade(&callback);
+        let timer = event_loop.add_timer(Duration::from_millis(0), move |compositor_handle| {
+            let (state, callback) = match (weak_state.upgrade(), weak_callback.upgrade()) {
+                (Some(state), Some(callback)) => (state, callback),
+                _ => return
+            };
+            let gesture = state.borrow_mut().long_press();
+            if let Some(gesture) = gesture {
+                call(&callback, compositor_handle, &gesture)
+            }
+        })?;
+        state.borrow_mut().timer = Some(timer);
+        Some(GestureRecognizer { state, callback })
+    }
+
+    /// Gets the thresholds used to recognize gestures.
+    pub fn config(&self) -> Config {
+        self.state.borrow().config
+    }
+
+    /// Changes the thresholds used to recognize gestures.
+    pub fn set_config(&mut self, config: Config) {
+        self.state.borrow_mut().config = config
+    }
+
+    /// Determines if there are enough fingers down to make a gesture.
+    ///
+    /// Compositors may want to stop sending touch events to clients while
+    /// this is true.
+    pub fn is_active(&self) -> bool {
+        let state = self.state.borrow();
+        !state.done && state.fingers >= state.config.min_fingers
+    }
+
+    /// Feeds a touch down event to the recognizer.
+    pub fn down(&mut self, event: &event::Down) {
+        let mut state = self.state.borrow_mut();
+        if state.points.is_empty() {
+            state.lifted.clear();
+            state.fingers = 0;
+            state.done = false;
+        }
+        let location = event.location();
+        state.points.insert(event.touch_id(), (location, location));
+        state.fingers = state.fingers.max(state.points.len() as u32);
+        // Long presses start over when a finger is added.
+        if !state.done && state.lifted.is_empty() {
+            let delay = state.config.long_press_delay;
+            if let Some(timer) = state.timer.as_mut() {
+                timer.update(delay)
+            }
+        }
+    }
+
+    /// Feeds a touch motion event to the recognizer.
+    pub fn motion(&mut self, event: &event::Motion) {
+        let mut state = self.state.borrow_mut();
+        let tolerance = state.config.long_press_tolerance;
+        let moved = match state.points.get_mut(&event.touch_id()) {
+            Some(point) => {
+                point.1 = event.location();
+                distance(point.0, point.1) > tolerance
+            },
+            None => return
+        };
+        if moved {
+            state.disarm()
+        }
+    }
+
+    /// Feeds a touch up event to the recognizer.
+    ///
+    /// The gesture is reported once the last finger is lifted.
+    pub fn up(&mut self, event: &event::Up) {
+        let gesture = {
+            let mut state = self.state.borrow_mut();
+            let point = match state.points.remove(&event.touch_id()) {
+                Some(point) => point,
+                None => return
+            };
+            state.lifted.push(point);
+            // A long press can't be made once a finger was lifted.
+            state.disarm();
+            if !state.points.is_empty() {
+                return;
+            }
+            state.finish()
+        };
+        if let Some(gesture) = gesture {
+            if let Some(compositor_handle) = compositor::handle() {
+                call(&self.callback, compositor_handle, &gesture)
+            }
+        }
+    }
+
+    /// Feeds a touch cancel event to the recognizer, which cancels the
+    /// current gesture.
+    pub fn cancel(&mut self, event: &event::Cancel) {
+        let mut state = self.state.borrow_mut();
+        state.points.remove(&event.touch_id());
+        state.done = true;
+        state.disarm();
+    }
+
+    /// Cancels the current gesture, the fingers that are down won't make
+    /// any gesture.
+    pub fn reset(&mut self) {
+        let mut state = self.state.borrow_mut();
+        state.done = true;
+        state.disarm();
+    }
+}
+
+impl State {
+    fn disarm(&mut self) {
+        if let Some(timer) = self.timer.as_mut() {
+            timer.disarm()
+        }
+    }
+
+    /// Reports a long press if the fingers are still down.
+    fn long_press(&mut self) -> Option<Gesture> {
+        let fingers = self.points.len() as u32;
+        if self.done || fingers == 0 || fingers < self.config.min_fingers || !self.lifted.is_empty() {
+            return None;
+        }
+        self.done = true;
+        let current = self.points.values().map(|point| point.1).collect::<Vec<_>>();
+        Some(Gesture::LongPress {
+            fingers,
+            location: center(&current)
+        })
+    }
+
+    /// Recognizes the gesture made by the lifted fingers.
+    fn finish(&mut self) -> Option<Gesture> {
+        if self.done || self.fingers < self.config.min_fingers {
+            return None;
+        }
+        self.done = true;
+        let start = self.lifted.iter().map(|point| point.0).collect::<Vec<_>>();
+        let end = self.lifted.iter().map(|point| point.1).collect::<Vec<_>>();
+        let (start_center, end_center) = (center(&start), center(&end));
+        let start_spread = spread(&start, start_center);
+        if self.lifted.len() > 1 && start_spread > 0.0 {
+            let scale = spread(&end, end_center) / start_spread;
+            if (scale - 1.0).abs() >= self.config.pinch_scale {
+                return Some(Gesture::Pinch {
+                    fingers: self.fingers,
+                    scale
+                });
+            }
+        }
+        let delta = (end_center.0 - start_center.0, end_center.1 - start_center.1);
+        if distance((0.0, 0.0), delta) < self.config.swipe_distance {
+            return None;
+        }
+        let direction = if delta.0.abs() >= delta.1.abs() {
+            if delta.0 < 0.0 {
+                SwipeDirection::Left
+            } else {
+                SwipeDirection::Right
+            }
+        } else if delta.1 < 0.0 {
+            SwipeDirection::Up
+        } else {
+            SwipeDirection::Down
+        };
+        Some(Gesture::Swipe {
+            fingers: self.fingers,
+            direction,
+            delta
+        })
+    }
+}
+
+fn call(callback: &RefCell<Callback>, compositor_handle: compositor::Handle, gesture: &Gesture) {
+    match callback.try_borrow_mut() {
+        Ok(mut callback) => callback(compositor_handle, gesture),
+        Err(_) => wlr_log!(WLR_ERROR, "Gesture callback was called recursively")
+    }
+}
+
+fn distance(a: (f64, f64), b: (f64, f64)) -> f64 {
+    (b.0 - a.0).hypot(b.1 - a.1)
+}
+
+fn center(points: &[(f64, f64)]) -> (f64, f64) {
+    let count = points.len().max(1) as f64;
+    let (x, y) = points
+        .iter()
+        .fold((0.0, 0.0), |(x, y), point| (x + point.0, y + point.1));
+    (x / count, y / count)
+}
+
+/// The average distance of the points to their center.
+fn spread(points: &[(f64, f64)], center: (f64, f64)) -> f64 {
+    let count = points.len().max(1) as f64;
+    points.iter().map(|&point| distance(center, point)).sum::<f64>() / count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Makes the state of fingers that were all lifted, given where each of
+    /// them started and ended.
+    fn lifted(points: &[((f64, f64), (f64, f64))]) -> State {
+        State {
+            config: Config::default(),
+            points: BTreeMap::new(),
+            lifted: points.to_vec(),
+            fingers: points.len() as u32,
+            done: false,
+            timer: None
+        }
+    }
+
+    #[test]
+    fn swipe() {
+        let mut state = lifted(&[((0.1, 0.5), (0.4, 0.5)), ((0.2, 0.5), (0.5, 0.5))]);
+        match state.finish() {
+            Some(Gesture::Swipe {
+                fingers: 2,
+                direction: SwipeDirection::Right,
+                delta
+            }) => {
+                assert!((delta.0 - 0.3).abs() < 1e-9);
+                assert!(delta.1.abs() < 1e-9);
+            },
+            gesture => panic!("Expected a swipe, got {:?}", gesture)
+        }
+        assert!(state.done);
+        assert_eq!(state.finish(), None);
+    }
+
+    #[test]
+    fn swipe_direction() {
+        let swipe = |dx, dy| {
+            let points = [
+                ((0.5, 0.5), (0.5 + dx, 0.5 + dy)),
+                ((0.6, 0.5), (0.6 + dx, 0.5 + dy))
+            ];
+            match lifted(&points).finish() {
+                Some(Gesture::Swipe { direction, .. }) => Some(direction),
+                _ => None
+            }
+        };
+        assert_eq!(swipe(-0.3, 0.1), Some(SwipeDirection::Left));
+        assert_eq!(swipe(0.3, -0.1), Some(SwipeDirection::Right));
+        assert_eq!(swipe(0.1, -0.3), Some(SwipeDirection::Up));
+        assert_eq!(swipe(-0.1, 0.3), Some(SwipeDirection::Down));
+        assert_eq!(swipe(0.05, 0.05), None);
+    }
+
+    #[test]
+    fn pinch() {
+        let mut state = lifted(&[((0.2, 0.5), (0.4, 0.5)), ((0.8, 0.5), (0.6, 0.5))]);
+        match state.finish() {
+            Some(Gesture::Pinch { fingers: 2, scale }) => assert!((scale - 1.0 / 3.0).abs() < 1e-9),
+            gesture => panic!("Expected a pinch, got {:?}", gesture)
+        }
+        let mut state = lifted(&[((0.4, 0.5), (0.2, 0.5)), ((0.6, 0.5), (0.8, 0.5))]);
+        match state.finish() {
+            Some(Gesture::Pinch { fingers: 2, scale }) => assert!((scale - 3.0).abs() < 1e-9),
+            gesture => panic!("Expected a pinch, got {:?}", gesture)
+        }
+        // A small change of spread is not a pinch, nor a swipe.
+        let mut state = lifted(&[((0.4, 0.5), (0.39, 0.5)), ((0.6, 0.5), (0.61, 0.5))]);
+        assert_eq!(state.finish(), None);
+    }
+
+    #[test]
+    fn too_few_fingers() {
+        let mut state = lifted(&[((0.1, 0.5), (0.9, 0.5))]);
+        assert_eq!(state.finish(), None);
+        assert!(!state.done);
+        let mut state = lifted(&[((0.1, 0.5), (0.9, 0.5)), ((0.2, 0.5), (0.9, 0.5))]);
+        state.config.min_fingers = 3;
+        assert_eq!(state.finish(), None);
+    }
+
+    #[test]
+    fn long_press() {
+        let mut state = lifted(&[]);
+        state.points.insert(0, ((0.25, 0.25), (0.25, 0.25)));
+        state.points.insert(1, ((0.75, 0.5), (0.75, 0.5)));
+        state.fingers = 2;
+        assert_eq!(
+            state.long_press(),
+            Some(Gesture::LongPress {
+                fingers: 2,
+                location: (0.5, 0.375)
+            })
+        );
+        assert_eq!(state.long_press(), None);
+        // Lifting the fingers doesn't report anything after a long press.
+        state.lifted = state.points.values().cloned().collect();
+        state.points.clear();
+        assert_eq!(state.finish(), None);
+
+        let mut state = lifted(&[((0.2, 0.2), (0.2, 0.2))]);
+        state.points.insert(1, ((0.4, 0.6), (0.4, 0.6)));
+        assert_eq!(state.long_press(), None);
+    }
+
+    #[test]
+    fn long_press_too_few_fingers() {
+        let mut state = lifted(&[]);
+        state.points.insert(0, ((0.5, 0.5), (0.5, 0.5)));
+        state.fingers = 1;
+        assert_eq!(state.long_press(), None);
+        assert!(!state.done);
+        state.config.min_fingers = 1;
+        assert_eq!(
+            state.long_press(),
+            Some(Gesture::LongPress {
+                fingers: 1,
+                location: (0.5, 0.5)
+            })
+        );
+    }
+
+    #[test]
+    fn center_and_spread() {
+        assert_eq!(center(&[]), (0.0, 0.0));
+        assert_eq!(center(&[(1.0, 2.0), (3.0, 4.0)]), (2.0, 3.0));
+        assert_eq!(spread(&[(1.0, 0.0), (3.0, 0.0)], (2.0, 0.0)), 1.0);
+        assert_eq!(spread(&[], (0.0, 0.0)), 0.0);
+        assert_eq!(distance((0.0, 0.0), (3.0, 4.0)), 5.0);
+    }
+}
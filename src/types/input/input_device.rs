@@ -7,8 +7,9 @@ use wlroots_sys::{
 
 pub(crate) use crate::manager::input_manager::Manager;
 use crate::{
+    backend::Libinput,
     input::{keyboard, pointer, switch, tablet_pad, tablet_tool, touch},
-    utils::c_to_rust_string
+    utils::{c_to_rust_string, UserData}
 };
 
 /// A handle to an input device.
@@ -21,9 +22,35 @@ pub enum Handle {
     Switch(switch::Handle)
 }
 
+/// The kind of an input device.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum DeviceKind {
+    Keyboard,
+    /// A pointer that is not a touchpad, e.g. a mouse or a trackball.
+    Pointer,
+    /// A touchpad, i.e. a libinput pointer that supports tapping.
+    Touchpad,
+    Touch,
+    TabletTool,
+    TabletPad,
+    Switch
+}
+
+/// Identifies input devices by their name, vendor and product ids and kind.
+///
+/// Fields that are `None` match every device.
+#[derive(Debug, Default, Clone, Eq, PartialEq, Hash)]
+pub struct DeviceMatch {
+    pub name: Option<String>,
+    pub vendor: Option<u32>,
+    pub product: Option<u32>,
+    pub kind: Option<DeviceKind>
+}
+
 pub(crate) struct InputState {
     pub(crate) handle: Weak<Cell<bool>>,
-    pub(crate) device: Device
+    pub(crate) device: Device,
+    pub(crate) user_data: UserData
 }
 
 /// Wrapper for wlr_input_device
@@ -74,6 +101,64 @@ impl Device {
         unsafe { self.device.as_ref().type_ }
     }
 
+    /// Get the kind of the device.
+    ///
+    /// Unlike `dev_type` this tells touchpads apart from other pointers.
+    pub fn kind(&self) -> DeviceKind {
+        match self.dev_type() {
+            WLR_INPUT_DEVICE_KEYBOARD => DeviceKind::Keyboard,
+            WLR_INPUT_DEVICE_POINTER => {
+                let is_touchpad = Libinput::device_config(self)
+                    .map(|config| config.tap_finger_count() > 0)
+                    .unwrap_or(false);
+                if is_touchpad {
+                    DeviceKind::Touchpad
+                } else {
+                    DeviceKind::Pointer
+                }
+            },
+            WLR_INPUT_DEVICE_TOUCH => DeviceKind::Touch,
+            WLR_INPUT_DEVICE_TABLET_TOOL => DeviceKind::TabletTool,
+            WLR_INPUT_DEVICE_TABLET_PAD => DeviceKind::TabletPad,
+            WLR_INPUT_DEVICE_SWITCH => DeviceKind::Switch
+        }
+    }
+
+    /// Gets the user data attached to the device.
+    ///
+    /// It is dropped when the device is destroyed.
+    ///
+    /// Returns `None` if the device is not managed by the compositor, e.g.
+    /// when its `*Added` callback has not been called yet or returned `None`.
+    pub fn user_data(&self) -> Option<&UserData> {
+        unsafe { self.state().as_ref().map(|state| &state.user_data) }
+    }
+
+    /// Gets the user data attached to the device mutably.
+    ///
+    /// It is dropped when the device is destroyed.
+    ///
+    /// Returns `None` if the device is not managed by the compositor, e.g.
+    /// when its `*Added` callback has not been called yet or returned `None`.
+    pub fn user_data_mut(&mut self) -> Option<&mut UserData> {
+        unsafe { self.state().as_mut().map(|state| &mut state.user_data) }
+    }
+
+    /// Gets the state stored in the user data of the backing input device,
+    /// which is null if it is not managed.
+    unsafe fn state(&self) -> *mut InputState {
+        let dev_union = self.dev_union();
+        let data = match self.dev_type() {
+            WLR_INPUT_DEVICE_KEYBOARD => (*dev_union.keyboard).data,
+            WLR_INPUT_DEVICE_POINTER => (*dev_union.pointer).data,
+            WLR_INPUT_DEVICE_TOUCH => (*dev_union.touch).data,
+            WLR_INPUT_DEVICE_TABLET_TOOL => (*dev_union.tablet).data,
+            WLR_INPUT_DEVICE_TABLET_PAD => (*dev_union.tablet_pad).data,
+            WLR_INPUT_DEVICE_SWITCH => (*dev_union.lid_switch).data
+        };
+        data as *mut InputState
+    }
+
     /// Get a handle to the backing input device.
     pub fn device(&self) -> Handle {
         unsafe {
@@ -123,3 +208,21 @@ impl Device {
         self.device.as_ptr()
     }
 }
+
+impl DeviceMatch {
+    /// Determines if the input device matches.
+    pub fn matches(&self, device: &Device) -> bool {
+        if let Some(ref name) = self.name {
+            if device.name().as_ref() != Some(name) {
+                return false;
+            }
+        }
+        self.vendor
+            .map(|vendor| vendor == device.vendor())
+            .unwrap_or(true) &&
+            self.product
+                .map(|product| product == device.product())
+                .unwrap_or(true) &&
+            self.kind.map(|kind| kind == device.kind()).unwrap_or(true)
+    }
+}
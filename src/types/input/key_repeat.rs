@@ -0,0 +1,235 @@
+//! Repeats held keys for key bindings handled by the compositor.
+//!
+//! Clients repeat keys on their own using the keyboard's repeat info, so
+//! this is only needed for keys the compositor consumes (e.g. holding a
+//! volume key bound to "raise volume").
+//!
+//! Create one `KeyRepeat` per keyboard and forward it the keyboard events:
+//!
+//! ```rust,no_run,ignore
+//! fn on_key(&mut self, compositor_handle: compositor::Handle,
+//!           keyboard_handle: keyboard::Handle, event: &keyboard::event::Key) {
+//!     keyboard_handle.run(|keyboard| self.repeat.key(keyboard, event)).unwrap();
+//!     // Handle the event, repeated events are given to the `KeyRepeat` callback.
+//! }
+//! ```
+
+use std::{cell::RefCell, rc::Rc, time::Duration};
+
+use wlroots_sys::{wlr_event_keyboard_key, wlr_key_state::*};
+use xkbcommon::xkb::ffi::{xkb_keymap, xkb_keymap_key_repeats};
+
+use crate::{
+    compositor,
+    event_loop::{EventLoop, Timer},
+    input::keyboard::{self, Keyboard, Modifier, RepeatInfo},
+    utils::Handleable
+};
+
+type Callback = Box<FnMut(compositor::Handle, keyboard::Handle, &keyboard::event::Key)>;
+
+/// Repeats the last pressed key of a keyboard while it's held down.
+///
+/// Repeating stops when the key is released, when the modifiers change or
+/// when `KeyRepeat::cancel` is called (e.g. because the focus changed).
+pub struct KeyRepeat {
+    state: Rc<RefCell<State>>
+}
+
+struct State {
+    /// Overrides the repeat info of the keyboard.
+    repeat_info: Option<RepeatInfo>,
+    enabled: bool,
+    held: Option<HeldKey>,
+    timer: Option<Timer>
+}
+
+#[derive(Debug, Clone)]
+struct HeldKey {
+    keyboard: keyboard::Handle,
+    keycode: u32,
+    modifiers: Modifier,
+    time_msec: u32,
+    interval: Duration
+}
+
+impl KeyRepeat {
+    /// Makes a key repeater driven by the given event loop.
+    ///
+    /// The callback is called with a pressed key event each time the held
+    /// key repeats, like `keyboard::Handler::on_key` would be.
+    pub fn new<F>(event_loop: &EventLoop, callback: F) -> Option<Self>
+    where
+        F: FnMut(compositor::Handle, keyboard::Handle, &keyboard::event::Key) + 'static
+    {
+        let state = Rc::new(RefCell::new(State {
+            repeat_info: None,
+            enabled: true,
+            held: None,
+            timer: None
+        }));
+        let weak_state = Rc::downgrade(&state);
+        let callback: Rc<RefCell<Callback>> = Rc::new(RefCell::new(Box::new(callback)));
+        let timer = event_loop.add_timer(Duration::from_millis(0), move |compositor_handle| {
+            let state = match weak_state.upgrade() {
+                Some(state) => state,
+                None => return
+            };
+            let held = match state.borrow_mut().repeat() {
+                Some(held) => held,
+                None => return
+            };
+            let mut raw_event = wlr_event_keyboard_key {
+                time_msec: held.time_msec,
+                keycode: held.keycode,
+                update_state: false,
+                state: WLR_KEY_PRESSED
+            };
+            // NOTE The keyboard isn't borrowed so the callback can use its handle.
+            let event = unsafe {
+                let xkb_state = (*held.keyboard.as_ptr()).xkb_state;
+                keyboard::event::Key::new(&mut raw_event, xkb_state)
+            };
+            match callback.try_borrow_mut() {
+                Ok(mut callback) => callback(compositor_handle, held.keyboard.clone(), &event),
+                Err(_) => wlr_log!(WLR_ERROR, "Key repeat callback was called recursively")
+            }
+        })?;
+        state.borrow_mut().timer = Some(timer);
+        Some(KeyRepeat { state })
+    }
+
+    /// Feeds a key event of the keyboard to the repeater.
+    ///
+    /// Pressing a key that repeats in the keymap starts repeating it,
+    /// releasing it stops.
+    pub fn key(&mut self, keyboard: &mut Keyboard, event: &keyboard::event::Key) {
+        let mut state = self.state.borrow_mut();
+        let keyboard_handle = keyboard.weak_reference();
+        match event.key_state() {
+            WLR_KEY_PRESSED => {
+                let repeat_info = state.repeat_info.unwrap_or_else(|| keyboard.repeat_info());
+                if !state.enabled || repeat_info.rate <= 0 || !key_repeats(keyboard, event.keycode()) {
+                    state.cancel();
+                    return;
+                }
+                let delay = Duration::from_millis(repeat_info.delay.max(0) as u64);
+                state.held = Some(HeldKey {
+                    keyboard: keyboard_handle,
+                    keycode: event.keycode(),
+                    modifiers: keyboard.get_modifiers(),
+                    time_msec: (event.time_msec() + delay).as_millis() as u32,
+                    interval: Duration::from_millis(1000 / repeat_info.rate as u64)
+                });
+                state.arm(delay);
+            },
+            WLR_KEY_RELEASED => {
+                let is_held = state
+                    .held
+                    .as_ref()
+                    .map(|held| held.keyboard == keyboard_handle && held.keycode == event.keycode())
+                    .unwrap_or(false);
+                if is_held {
+                    state.cancel();
+                }
+            }
+        }
+    }
+
+    /// Feeds a modifiers event of the keyboard to the repeater.
+    ///
+    /// Repeating stops if the modifiers changed since the key was pressed.
+    pub fn modifiers(&mut self, keyboard: &mut Keyboard) {
+        let mut state = self.state.borrow_mut();
+        let changed = state
+            .held
+            .as_ref()
+            .map(|held| {
+                held.keyboard == keyboard.weak_reference() && held.modifiers != keyboard.get_modifiers()
+            })
+            .unwrap_or(false);
+        if changed {
+            state.cancel();
+        }
+    }
+
+    /// Stops repeating the held key, if any.
+    ///
+    /// Call this when the keyboard focus changes.
+    pub fn cancel(&mut self) {
+        self.state.borrow_mut().cancel()
+    }
+
+    /// Determines if a key is currently being repeated.
+    pub fn is_repeating(&self) -> bool {
+        self.state.borrow().held.is_some()
+    }
+
+    /// Enables or disables repeating keys. Disabling stops any current
+    /// repetition.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        let mut state = self.state.borrow_mut();
+        state.enabled = enabled;
+        if !enabled {
+            state.cancel();
+        }
+    }
+
+    /// Overrides the repeat rate and delay of the keyboard.
+    ///
+    /// `None` uses the keyboard's own repeat info, which is the default.
+    /// The new repeat info is used the next time a key is pressed.
+    pub fn set_repeat_info(&mut self, repeat_info: Option<RepeatInfo>) {
+        self.state.borrow_mut().repeat_info = repeat_info;
+    }
+}
+
+impl State {
+    /// Rearms the timer for the next repetition, returning the key to repeat
+    /// now.
+    fn repeat(&mut self) -> Option<HeldKey> {
+        let held = {
+            let held = self.held.as_mut()?;
+            if !held.keyboard.is_alive() {
+                None
+            } else {
+                let repeated = held.clone();
+                held.time_msec = held.time_msec.wrapping_add(held.interval.as_millis() as u32);
+                Some(repeated)
+            }
+        };
+        match held {
+            Some(held) => {
+                self.arm(held.interval);
+                Some(held)
+            },
+            None => {
+                self.cancel();
+                None
+            }
+        }
+    }
+
+    fn arm(&mut self, delay: Duration) {
+        // A zero delay would disarm the timer.
+        let delay = delay.max(Duration::from_millis(1));
+        if let Some(timer) = self.timer.as_mut() {
+            timer.update(delay)
+        }
+    }
+
+    fn cancel(&mut self) {
+        self.held = None;
+        if let Some(timer) = self.timer.as_mut() {
+            timer.disarm()
+        }
+    }
+}
+
+/// Determines if the key repeats according to the keyboard's keymap.
+fn key_repeats(keyboard: &mut Keyboard, keycode: u32) -> bool {
+    unsafe {
+        let keymap = (*keyboard.as_ptr()).keymap as *mut xkb_keymap;
+        !keymap.is_null() && xkb_keymap_key_repeats(keymap, keycode + 8) != 0
+    }
+}
@@ -0,0 +1,577 @@
+//! A registry of key bindings handled by the compositor.
+//!
+//! Bindings are written as strings such as `"Logo+Shift+Return"`: any number
+//! of modifiers followed by an XKB keysym name, separated by `+`. Chords,
+//! i.e. combinations that have to be pressed one after another, are
+//! separated by spaces: `"Logo+w h"`.
+//!
+//! A modifier can be bound on its own, e.g. `"Super_L"` with
+//! `Trigger::Release` fires when Super is tapped. Releases of a modifier only
+//! fire if no other key was pressed while it was held, so `"Logo+Return"`
+//! does not also fire the tap binding.
+//!
+//! Releases are matched against the modifiers that were held when the key
+//! was pressed, so letting go of the modifiers first does not change which
+//! binding fires.
+//!
+//! Bindings are grouped in modes. Only the bindings of the current mode are
+//! active, which is `"default"` until `Keybindings::set_mode` is called.
+//!
+//! Feed every key event to `Keybindings::handle_key` and only forward it to
+//! the focused client (e.g. through `Seat::keyboard_notify_key`) if it was
+//! not consumed:
+//!
+//! ```rust,no_run,ignore
+//! let result = keyboard_handle.run(|keyboard| state.bindings.handle_key(keyboard, event)).unwrap();
+//! match result {
+//!     Match::Action(action) => state.run_action(action),
+//!     Match::Unbound => seat.keyboard_notify_key(event.time_msec(), event.keycode(), event.key_state()),
+//!     Match::Pending | Match::Consumed => {}
+//! }
+//! ```
+
+use std::{collections::HashMap, error::Error, fmt, str::FromStr};
+
+use wlroots_sys::{wlr_key_state::*, xkb_keysym_t};
+use xkbcommon::xkb::{self, ffi::XKB_MOD_INVALID};
+
+use crate::input::keyboard::{self, Keyboard, Modifier};
+
+/// The name of the mode that's active by default.
+pub const DEFAULT_MODE: &str = "default";
+
+/// A set of modifiers and a key that are pressed together.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct Combo {
+    pub modifiers: Modifier,
+    pub keysym: keyboard::Key
+}
+
+/// A sequence of combos that have to be pressed one after another.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Chord(pub Vec<Combo>);
+
+/// When a binding fires.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Trigger {
+    /// When the key is pressed.
+    Press,
+    /// When the key is pressed and each time it repeats.
+    Repeat,
+    /// When the key is released.
+    Release
+}
+
+/// The result of handling a key event.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Match<A> {
+    /// The key is not bound, the event should be forwarded to the client.
+    Unbound,
+    /// The key continues a chord that is not complete yet.
+    Pending,
+    /// A binding fired.
+    Action(A),
+    /// The event belongs to a binding but did not fire anything, e.g. the
+    /// release of a key whose press fired a binding.
+    Consumed
+}
+
+/// Errors that can occur while parsing a binding.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ParseError {
+    /// The binding was empty, or had an empty component (e.g. `"Logo++"`).
+    Empty,
+    /// The modifier is not known.
+    UnknownModifier(String),
+    /// The name is not a known XKB keysym.
+    UnknownKeysym(String)
+}
+
+/// Key bindings, grouped by mode, and the state of the chord being typed.
+#[derive(Debug)]
+pub struct Keybindings<A> {
+    modes: HashMap<String, Vec<Binding<A>>>,
+    mode: String,
+    /// The combos of the chord being typed.
+    pending: Vec<Combo>,
+    /// Keycodes whose release should be consumed.
+    consumed: Vec<u32>,
+    /// The combos of the keys being held, as they were when pressed.
+    pressed: Vec<(u32, Vec<Combo>)>,
+    /// The modifier key pressed last, if no other key was pressed since.
+    tap: Option<u32>
+}
+
+#[derive(Debug)]
+struct Binding<A> {
+    chord: Chord,
+    trigger: Trigger,
+    action: A
+}
+
+impl<A: Clone> Keybindings<A> {
+    /// Makes an empty registry in the default mode.
+    pub fn new() -> Self {
+        Keybindings {
+            modes: HashMap::new(),
+            mode: DEFAULT_MODE.into(),
+            pending: Vec::new(),
+            consumed: Vec::new(),
+            pressed: Vec::new(),
+            tap: None
+        }
+    }
+
+    /// Parses and binds a chord in the given mode.
+    ///
+    /// Binding the same chord and trigger again replaces the action.
+    pub fn bind(&mut self, mode: &str, chord: &str, trigger: Trigger, action: A) -> Result<(), ParseError> {
+        let chord = chord.parse()?;
+        self.bind_chord(mode, chord, trigger, action);
+        Ok(())
+    }
+
+    /// Binds an already parsed chord in the given mode.
+    pub fn bind_chord(&mut self, mode: &str, chord: Chord, trigger: Trigger, action: A) {
+        let bindings = self.modes.entry(mode.into()).or_insert_with(Vec::new);
+        bindings.retain(|binding| binding.chord != chord || binding.trigger != trigger);
+        bindings.push(Binding {
+            chord,
+            trigger,
+            action
+        });
+    }
+
+    /// Removes a binding, returning its action.
+    pub fn unbind(&mut self, mode: &str, chord: &Chord, trigger: Trigger) -> Option<A> {
+        let bindings = self.modes.get_mut(mode)?;
+        let index = bindings
+            .iter()
+            .position(|binding| binding.chord == *chord && binding.trigger == trigger)?;
+        Some(bindings.remove(index).action)
+    }
+
+    /// Gets the name of the current mode.
+    pub fn mode(&self) -> &str {
+        &self.mode
+    }
+
+    /// Switches to another mode, abandoning the chord being typed.
+    pub fn set_mode(&mut self, mode: &str) {
+        self.mode = mode.into();
+        self.pending.clear();
+    }
+
+    /// Abandons the chord being typed, if any.
+    pub fn reset(&mut self) {
+        self.pending.clear();
+    }
+
+    /// Determines if part of a chord has been typed.
+    pub fn is_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Matches a key event of the keyboard against the bindings of the
+    /// current mode.
+    pub fn handle_key(&mut self, keyboard: &mut Keyboard, event: &keyboard::event::Key) -> Match<A> {
+        let combos = combos(keyboard, event.keycode());
+        match event.key_state() {
+            WLR_KEY_PRESSED => self.press(event.keycode(), &combos, false),
+            WLR_KEY_RELEASED => self.release(event.keycode(), &combos)
+        }
+    }
+
+    /// Matches a repeated key event (e.g. from `input::key_repeat::KeyRepeat`)
+    /// against the bindings of the current mode.
+    ///
+    /// Only bindings using `Trigger::Repeat` fire, the chord being typed is
+    /// left alone.
+    pub fn handle_repeat(&mut self, keyboard: &mut Keyboard, event: &keyboard::event::Key) -> Match<A> {
+        let combos = combos(keyboard, event.keycode());
+        self.press(event.keycode(), &combos, true)
+    }
+
+    fn press(&mut self, keycode: u32, combos: &[Combo], repeat: bool) -> Match<A> {
+        let modifier_only = is_modifier_only(combos);
+        if !repeat {
+            self.pressed.retain(|&(pressed, _)| pressed != keycode);
+            self.pressed.push((keycode, combos.to_vec()));
+            self.tap = if modifier_only { Some(keycode) } else { None };
+        }
+        let bindings = match self.modes.get(&self.mode) {
+            Some(bindings) => bindings,
+            None => return Match::Unbound
+        };
+        // Pressing a modifier that is not bound never breaks a chord.
+        if modifier_only {
+            let pending = &self.pending;
+            let is_bound = combos.iter().any(|combo| {
+                bindings.iter().any(|binding| {
+                    binding.chord.0.len() > pending.len() &&
+                        binding.chord.0.starts_with(pending) &&
+                        binding.chord.0[pending.len()] == *combo
+                })
+            });
+            if !is_bound {
+                return Match::Unbound;
+            }
+        }
+        let mut result = Match::Unbound;
+        for combo in combos {
+            let mut chord = self.pending.clone();
+            chord.push(*combo);
+            let action = bindings
+                .iter()
+                .find(|binding| {
+                    binding.chord.0 == chord &&
+                        match binding.trigger {
+                            Trigger::Press => !repeat,
+                            Trigger::Repeat => true,
+                            Trigger::Release => false
+                        }
+                })
+                .map(|binding| binding.action.clone());
+            if let Some(action) = action {
+                result = Match::Action(action);
+                break;
+            }
+            if repeat {
+                continue;
+            }
+            let has_release = bindings
+                .iter()
+                .any(|binding| binding.trigger == Trigger::Release && binding.chord.0 == chord);
+            let is_prefix = bindings
+                .iter()
+                .any(|binding| binding.chord.0.len() > chord.len() && binding.chord.0.starts_with(&chord));
+            if is_prefix {
+                self.pending = chord;
+                result = Match::Pending;
+                break;
+            }
+            if has_release {
+                result = Match::Consumed;
+                break;
+            }
+        }
+        if repeat {
+            return result;
+        }
+        match result {
+            Match::Unbound | Match::Action(_) => self.pending.clear(),
+            Match::Pending | Match::Consumed => {}
+        }
+        if result.is_consumed() && !self.consumed.contains(&keycode) {
+            self.consumed.push(keycode);
+        }
+        result
+    }
+
+    fn release(&mut self, keycode: u32, combos: &[Combo]) -> Match<A> {
+        let was_consumed = self.consumed.contains(&keycode);
+        self.consumed.retain(|&consumed| consumed != keycode);
+        // Match the key as it was pressed, the modifiers may have changed
+        // since. Keys pressed before the first event fall back to the
+        // current state.
+        let combos = match self.pressed.iter().position(|&(pressed, _)| pressed == keycode) {
+            Some(index) => self.pressed.remove(index).1,
+            None => combos.to_vec()
+        };
+        let tapped = self.tap == Some(keycode);
+        if tapped {
+            self.tap = None;
+        }
+        let bindings = match self.modes.get(&self.mode) {
+            Some(bindings) if tapped || !is_modifier_only(&combos) => Some(bindings),
+            _ => None
+        };
+        if let Some(bindings) = bindings {
+            for combo in &combos {
+                let mut chord = self.pending.clone();
+                chord.push(*combo);
+                let action = bindings
+                    .iter()
+                    .find(|binding| binding.trigger == Trigger::Release && binding.chord.0 == chord)
+                    .map(|binding| binding.action.clone());
+                if let Some(action) = action {
+                    self.pending.clear();
+                    return Match::Action(action);
+                }
+            }
+        }
+        if was_consumed {
+            Match::Consumed
+        } else {
+            Match::Unbound
+        }
+    }
+}
+
+impl<A: Clone> Default for Keybindings<A> {
+    fn default() -> Self {
+        Keybindings::new()
+    }
+}
+
+impl<A> Match<A> {
+    /// Determines if the event was consumed by the bindings, in which case
+    /// it should not be forwarded to the client.
+    pub fn is_consumed(&self) -> bool {
+        match *self {
+            Match::Unbound => false,
+            Match::Pending | Match::Action(_) | Match::Consumed => true
+        }
+    }
+}
+
+impl FromStr for Combo {
+    type Err = ParseError;
+
+    fn from_str(combo: &str) -> Result<Self, Self::Err> {
+        let mut parts = combo.split('+').collect::<Vec<_>>();
+        let key = parts.pop().ok_or(ParseError::Empty)?;
+        let mut modifiers = Modifier::empty();
+        for part in parts {
+            modifiers |= parse_modifier(part)?;
+        }
+        if key.is_empty() {
+            return Err(ParseError::Empty);
+        }
+        let mut keysym = xkb::keysym_from_name(key, 0);
+        if keysym == xkb::KEY_NoSymbol {
+            keysym = xkb::keysym_from_name(key, xkb::KEYSYM_CASE_INSENSITIVE);
+        }
+        if keysym == xkb::KEY_NoSymbol {
+            return Err(ParseError::UnknownKeysym(key.into()));
+        }
+        Ok(Combo {
+            modifiers,
+            keysym: keysym as xkb_keysym_t
+        })
+    }
+}
+
+impl FromStr for Chord {
+    type Err = ParseError;
+
+    fn from_str(chord: &str) -> Result<Self, Self::Err> {
+        let combos = chord
+            .split_whitespace()
+            .map(str::parse)
+            .collect::<Result<Vec<Combo>, _>>()?;
+        if combos.is_empty() {
+            return Err(ParseError::Empty);
+        }
+        Ok(Chord(combos))
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::ParseError::*;
+        match *self {
+            Empty => write!(f, "Binding is empty"),
+            UnknownModifier(ref modifier) => write!(f, "Unknown modifier \"{}\"", modifier),
+            UnknownKeysym(ref keysym) => write!(f, "Unknown keysym \"{}\"", keysym)
+        }
+    }
+}
+
+impl Error for ParseError {
+    fn description(&self) -> &str {
+        use self::ParseError::*;
+        match *self {
+            Empty => "Binding is empty",
+            UnknownModifier(_) => "Unknown modifier",
+            UnknownKeysym(_) => "Unknown keysym"
+        }
+    }
+}
+
+fn parse_modifier(modifier: &str) -> Result<Modifier, ParseError> {
+    Ok(match modifier.to_lowercase().as_str() {
+        "" => return Err(ParseError::Empty),
+        "shift" => Modifier::WLR_MODIFIER_SHIFT,
+        "caps" | "lock" => Modifier::WLR_MODIFIER_CAPS,
+        "ctrl" | "control" => Modifier::WLR_MODIFIER_CTRL,
+        "alt" | "mod1" => Modifier::WLR_MODIFIER_ALT,
+        "mod2" => Modifier::WLR_MODIFIER_MOD2,
+        "mod3" => Modifier::WLR_MODIFIER_MOD3,
+        "logo" | "super" | "mod4" => Modifier::WLR_MODIFIER_LOGO,
+        "mod5" => Modifier::WLR_MODIFIER_MOD5,
+        _ => return Err(ParseError::UnknownModifier(modifier.into()))
+    })
+}
+
+fn is_modifier_keysym(keysym: keyboard::Key) -> bool {
+    let keysym = keysym as u32;
+    (xkb::KEY_Shift_L..=xkb::KEY_Hyper_R).contains(&keysym) ||
+        (xkb::KEY_ISO_Lock..=xkb::KEY_ISO_Level5_Lock).contains(&keysym)
+}
+
+/// Determines if a key only produces modifiers.
+fn is_modifier_only(combos: &[Combo]) -> bool {
+    combos.iter().all(|combo| is_modifier_keysym(combo.keysym))
+}
+
+/// Gets the combos a key press on the keyboard can match, using both the
+/// keysyms translated by the current keyboard state and the keysyms of the
+/// key without any modifiers applied.
+///
+/// e.g. pressing `2` with Shift held on a US layout gives `Shift+2` as well
+/// as `at`, since Shift was used to produce `@`.
+fn combos(keyboard: &mut Keyboard, keycode: u32) -> Vec<Combo> {
+    // Lock modifiers are ignored so bindings work regardless of them.
+    let ignored = Modifier::WLR_MODIFIER_CAPS | Modifier::WLR_MODIFIER_MOD2;
+    let modifiers = keyboard.get_modifiers() - ignored;
+    let mut combos = Vec::new();
+    let (xkb_state, keymap) = match (keyboard.get_xkb_state(), keyboard.get_keymap()) {
+        (Some(xkb_state), Some(keymap)) => (xkb_state, keymap),
+        _ => return combos
+    };
+    let xkb_keycode = keycode + 8;
+    let consumed = to_modifier(keyboard, xkb_state.key_get_consumed_mods(xkb_keycode));
+    for &keysym in xkb_state.key_get_syms(xkb_keycode) {
+        combos.push(Combo {
+            modifiers: modifiers - consumed,
+            keysym: keysym as xkb_keysym_t
+        });
+    }
+    let layout = xkb_state.key_get_layout(xkb_keycode);
+    for &keysym in keymap.key_get_syms_by_level(xkb_keycode, layout, 0) {
+        let combo = Combo {
+            modifiers,
+            keysym: keysym as xkb_keysym_t
+        };
+        if !combos.contains(&combo) {
+            combos.push(combo);
+        }
+    }
+    combos
+}
+
+/// Converts an XKB modifier mask to the wlroots modifiers of the keyboard.
+fn to_modifier(keyboard: &Keyboard, mask: u32) -> Modifier {
+    let mut modifiers = Modifier::empty();
+    for (bit, &mod_index) in keyboard.modifier_list().iter().enumerate() {
+        if mod_index != XKB_MOD_INVALID && mod_index < 32 && mask & (1 << mod_index) != 0 {
+            modifiers |= Modifier::from_bits_truncate(1 << bit);
+        }
+    }
+    modifiers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn combo(modifiers: Modifier, keysym: u32) -> Combo {
+        Combo {
+            modifiers,
+            keysym: keysym as keyboard::Key
+        }
+    }
+
+    #[test]
+    fn parse_combo() {
+        assert_eq!(
+            "Logo+Shift+Return".parse(),
+            Ok(combo(
+                Modifier::WLR_MODIFIER_LOGO | Modifier::WLR_MODIFIER_SHIFT,
+                xkb::KEY_Return
+            ))
+        );
+        assert_eq!(
+            "ctrl+alt+a".parse(),
+            Ok(combo(
+                Modifier::WLR_MODIFIER_CTRL | Modifier::WLR_MODIFIER_ALT,
+                xkb::KEY_a
+            ))
+        );
+        assert_eq!("Super_L".parse(), Ok(combo(Modifier::empty(), xkb::KEY_Super_L)));
+        // Keysyms fall back to a case insensitive lookup.
+        assert_eq!("return".parse(), Ok(combo(Modifier::empty(), xkb::KEY_Return)));
+    }
+
+    #[test]
+    fn parse_combo_errors() {
+        assert_eq!("".parse::<Combo>(), Err(ParseError::Empty));
+        assert_eq!("Logo+".parse::<Combo>(), Err(ParseError::Empty));
+        assert_eq!("Logo++a".parse::<Combo>(), Err(ParseError::Empty));
+        assert_eq!(
+            "Hyper+a".parse::<Combo>(),
+            Err(ParseError::UnknownModifier("Hyper".into()))
+        );
+        assert_eq!(
+            "Logo+NotAKeysym".parse::<Combo>(),
+            Err(ParseError::UnknownKeysym("NotAKeysym".into()))
+        );
+    }
+
+    #[test]
+    fn parse_chord() {
+        assert_eq!(
+            "Logo+w  h".parse(),
+            Ok(Chord(vec![
+                combo(Modifier::WLR_MODIFIER_LOGO, xkb::KEY_w),
+                combo(Modifier::empty(), xkb::KEY_h),
+            ]))
+        );
+        assert_eq!("".parse::<Chord>(), Err(ParseError::Empty));
+        assert_eq!(" ".parse::<Chord>(), Err(ParseError::Empty));
+        assert_eq!(
+            "Logo+w Foo+h".parse::<Chord>(),
+            Err(ParseError::UnknownModifier("Foo".into()))
+        );
+    }
+
+    #[test]
+    fn modifier_tap() {
+        let mut bindings = Keybindings::new();
+        bindings
+            .bind(DEFAULT_MODE, "Super_L", Trigger::Release, 1)
+            .unwrap();
+        let super_l = [combo(Modifier::empty(), xkb::KEY_Super_L)];
+        let held = [combo(Modifier::WLR_MODIFIER_LOGO, xkb::KEY_Super_L)];
+        assert_eq!(bindings.press(125, &super_l, false), Match::Consumed);
+        assert_eq!(bindings.release(125, &held), Match::Action(1));
+
+        // Pressing another key in between is not a tap.
+        let logo_return = [combo(Modifier::WLR_MODIFIER_LOGO, xkb::KEY_Return)];
+        assert_eq!(bindings.press(125, &super_l, false), Match::Consumed);
+        assert_eq!(bindings.press(28, &logo_return, false), Match::Unbound);
+        assert_eq!(bindings.release(28, &logo_return), Match::Unbound);
+        assert_eq!(bindings.release(125, &held), Match::Consumed);
+    }
+
+    #[test]
+    fn release_uses_press_modifiers() {
+        let mut bindings = Keybindings::new();
+        bindings
+            .bind(DEFAULT_MODE, "Logo+q", Trigger::Release, 1)
+            .unwrap();
+        let logo_q = [combo(Modifier::WLR_MODIFIER_LOGO, xkb::KEY_q)];
+        let q = [combo(Modifier::empty(), xkb::KEY_q)];
+        assert_eq!(bindings.press(16, &logo_q, false), Match::Consumed);
+        assert_eq!(bindings.release(16, &q), Match::Action(1));
+        assert_eq!(bindings.press(16, &q, false), Match::Unbound);
+        assert_eq!(bindings.release(16, &logo_q), Match::Unbound);
+    }
+
+    #[test]
+    fn unbound_modifier_keeps_chord() {
+        let mut bindings = Keybindings::new();
+        bindings
+            .bind(DEFAULT_MODE, "Logo+w h", Trigger::Press, 1)
+            .unwrap();
+        let logo_w = [combo(Modifier::WLR_MODIFIER_LOGO, xkb::KEY_w)];
+        let shift = [combo(Modifier::empty(), xkb::KEY_Shift_L)];
+        let h = [combo(Modifier::empty(), xkb::KEY_h)];
+        assert_eq!(bindings.press(17, &logo_w, false), Match::Pending);
+        assert_eq!(bindings.press(42, &shift, false), Match::Unbound);
+        assert!(bindings.is_pending());
+        assert_eq!(bindings.press(35, &h, false), Match::Action(1));
+        assert!(!bindings.is_pending());
+    }
+}
@@ -1,19 +1,27 @@
 //! TODO Documentation
-use std::{cell::Cell, fmt, ptr::NonNull, rc::Rc};
+use std::{
+    cell::Cell,
+    fmt,
+    ptr::{self, NonNull},
+    rc::Rc
+};
 
 pub use wlroots_sys::wlr_key_state;
 use wlroots_sys::{
     wlr_input_device, wlr_keyboard, wlr_keyboard_get_modifiers, wlr_keyboard_led, wlr_keyboard_led_update,
-    wlr_keyboard_modifier, wlr_keyboard_modifiers, wlr_keyboard_set_keymap, xkb_keysym_t
+    wlr_keyboard_modifier, wlr_keyboard_modifiers, wlr_keyboard_notify_modifiers, wlr_keyboard_set_keymap,
+    xkb_keysym_t
+};
+use xkbcommon::xkb::ffi::{
+    xkb_keymap, xkb_keymap_layout_get_name, xkb_keymap_num_layouts, xkb_keymap_ref, xkb_state, xkb_state_ref
 };
-use xkbcommon::xkb::ffi::{xkb_keymap, xkb_state};
 use xkbcommon::xkb::{self, Keycode, Keymap, LedIndex, ModIndex};
 
 pub use crate::events::key_events as event;
 pub use crate::manager::keyboard_handler::*;
 use crate::{
     input::{self, InputState},
-    utils::{self, HandleErr, HandleResult, Handleable}
+    utils::{self, c_to_rust_string, HandleErr, HandleResult, Handleable, UserData}
 };
 
 pub type Key = xkb_keysym_t;
@@ -107,7 +115,8 @@ impl Keyboard {
                 let handle = Rc::downgrade(&liveliness);
                 let state = Box::new(InputState {
                     handle,
-                    device: input::Device::from_ptr(device)
+                    device: input::Device::from_ptr(device),
+                    user_data: UserData::new()
                 });
                 (*keyboard.as_ptr()).data = Box::into_raw(state) as *mut _;
                 Some(Keyboard {
@@ -130,6 +139,11 @@ impl Keyboard {
         &self.device
     }
 
+    /// Gets the wlr_input_device associated with this keyboard mutably.
+    pub fn input_device_mut(&mut self) -> &mut input::Device {
+        &mut self.device
+    }
+
     /// Set the keymap for this Keyboard.
     pub fn set_keymap(&mut self, keymap: &Keymap) {
         unsafe {
@@ -141,13 +155,16 @@ impl Keyboard {
     }
 
     /// Get the XKB keymap associated with this Keyboard.
+    ///
+    /// The returned keymap holds its own reference, so it stays valid after
+    /// the keymap of the keyboard is changed.
     pub fn get_keymap(&mut self) -> Option<Keymap> {
         unsafe {
             let keymap_ptr = (*self.keyboard.as_ptr()).keymap as *mut xkb_keymap;
             if keymap_ptr.is_null() {
                 None
             } else {
-                Some(Keymap::from_raw_ptr(keymap_ptr))
+                Some(Keymap::from_raw_ptr(xkb_keymap_ref(keymap_ptr)))
             }
         }
     }
@@ -181,17 +198,72 @@ impl Keyboard {
     }
 
     /// Get the XKB state associated with this `Keyboard`.
+    ///
+    /// The returned state holds its own reference and keeps following the
+    /// keyboard, until its keymap is changed.
     pub fn get_xkb_state(&mut self) -> Option<xkb::State> {
         unsafe {
             let xkb_state_ptr = (*self.keyboard.as_ptr()).xkb_state as *mut xkb_state;
             if xkb_state_ptr.is_null() {
                 None
             } else {
-                Some(xkb::State::from_raw_ptr(xkb_state_ptr))
+                Some(xkb::State::from_raw_ptr(xkb_state_ref(xkb_state_ptr)))
+            }
+        }
+    }
+
+    /// Get the number of layouts in the keymap of this keyboard.
+    pub fn num_layouts(&self) -> u32 {
+        unsafe {
+            let keymap_ptr = (*self.keyboard.as_ptr()).keymap as *mut xkb_keymap;
+            if keymap_ptr.is_null() {
+                0
+            } else {
+                xkb_keymap_num_layouts(keymap_ptr)
+            }
+        }
+    }
+
+    /// Get the name of a layout in the keymap of this keyboard.
+    pub fn layout_name(&self, layout: u32) -> Option<String> {
+        unsafe {
+            let keymap_ptr = (*self.keyboard.as_ptr()).keymap as *mut xkb_keymap;
+            if keymap_ptr.is_null() || layout >= xkb_keymap_num_layouts(keymap_ptr) {
+                None
+            } else {
+                c_to_rust_string(xkb_keymap_layout_get_name(keymap_ptr, layout))
             }
         }
     }
 
+    /// Get the index of the active layout of this keyboard.
+    pub fn active_layout(&self) -> u32 {
+        unsafe { (*self.keyboard.as_ptr()).modifiers.group }
+    }
+
+    /// Switch the active layout of this keyboard.
+    ///
+    /// If the layout changed `keyboard::Handler::modifiers` is called, which
+    /// is where the new layout should be sent to the focused client.
+    ///
+    /// Returns `false` if there is no layout with that index.
+    pub fn set_active_layout(&mut self, layout: u32) -> bool {
+        if layout >= self.num_layouts() {
+            return false;
+        }
+        unsafe {
+            let modifiers = (*self.keyboard.as_ptr()).modifiers;
+            wlr_keyboard_notify_modifiers(
+                self.keyboard.as_ptr(),
+                modifiers.depressed,
+                modifiers.latched,
+                modifiers.locked,
+                layout
+            );
+        }
+        true
+    }
+
     /// Get the repeat info for this keyboard.
     pub fn repeat_info(&self) -> RepeatInfo {
         unsafe {
@@ -228,6 +300,7 @@ impl Drop for Keyboard {
             wlr_log!(WLR_DEBUG, "Dropped Keyboard {:p}", self.keyboard.as_ptr());
             unsafe {
                 let _ = Box::from_raw((*self.keyboard.as_ptr()).data as *mut InputState);
+                (*self.keyboard.as_ptr()).data = ptr::null_mut();
             }
             let weak_count = Rc::weak_count(&self.liveliness);
             if weak_count > 0 {
@@ -0,0 +1,263 @@
+//! Keymap configuration using XKB RMLVO names (rules, model, layout,
+//! variant and options).
+//!
+//! A `Config` describes a keymap and can compile it and apply it to a
+//! keyboard. `Keymaps` picks a configuration for each keyboard, so e.g. an
+//! external keyboard can use a different layout than the laptop's:
+//!
+//! ```rust,no_run,ignore
+//! let mut keymaps = Keymaps::new(Config::from_env());
+//! keymaps.add_rule(
+//!     DeviceMatch { name: Some("Kinesis Advantage2".into()), ..DeviceMatch::default() },
+//!     Config { layouts: vec!["us".into()], variants: vec!["dvorak".into()], ..Config::default() }
+//! );
+//! // In the keyboard added callback:
+//! keyboard_handle.run(|keyboard| keymaps.apply(keyboard)).unwrap()?;
+//! ```
+
+use std::{env, error::Error, fmt};
+
+use xkbcommon::xkb::{self, Keymap};
+
+pub use crate::input::DeviceMatch;
+use crate::input::{self, keyboard::Keyboard};
+
+/// The RMLVO names describing a keymap.
+///
+/// Empty names use the system defaults.
+#[derive(Debug, Default, Clone, Eq, PartialEq, Hash)]
+pub struct Config {
+    pub rules: String,
+    pub model: String,
+    /// The layouts of the keymap, which can be switched between using
+    /// `Keyboard::set_active_layout`.
+    pub layouts: Vec<String>,
+    /// The variant of each layout, in the same order as `layouts`.
+    ///
+    /// Layouts without a variant, or with an empty one, use their default
+    /// variant.
+    pub variants: Vec<String>,
+    pub options: String
+}
+
+/// Keymap configurations for keyboards, picked per device.
+#[derive(Debug, Default, Clone)]
+pub struct Keymaps {
+    default: Config,
+    rules: Vec<(DeviceMatch, Config)>
+}
+
+/// Errors that can occur while compiling a keymap.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum KeymapError {
+    /// One of the names contained a nul byte.
+    InvalidName(String),
+    /// XKB could not compile a keymap from the names.
+    CompileFailed(Config)
+}
+
+impl Config {
+    /// Makes a configuration from the `XKB_DEFAULT_RULES`, `XKB_DEFAULT_MODEL`,
+    /// `XKB_DEFAULT_LAYOUT`, `XKB_DEFAULT_VARIANT` and `XKB_DEFAULT_OPTIONS`
+    /// environment variables.
+    pub fn from_env() -> Self {
+        Config::from_vars(|name| env::var(name).ok())
+    }
+
+    /// Makes a configuration from the `XKB_DEFAULT_*` variables, as looked
+    /// up by `lookup`.
+    fn from_vars<F>(lookup: F) -> Self
+    where
+        F: Fn(&str) -> Option<String>
+    {
+        let var = |name| lookup(name).unwrap_or_default();
+        Config {
+            rules: var("XKB_DEFAULT_RULES"),
+            model: var("XKB_DEFAULT_MODEL"),
+            layouts: var("XKB_DEFAULT_LAYOUT")
+                .split(',')
+                .filter(|layout| !layout.is_empty())
+                .map(String::from)
+                .collect(),
+            // NOTE Empty variants are kept, they pair the next variants
+            // with the right layouts.
+            variants: match var("XKB_DEFAULT_VARIANT") {
+                ref variants if variants.is_empty() => Vec::new(),
+                variants => variants.split(',').map(String::from).collect()
+            },
+            options: var("XKB_DEFAULT_OPTIONS")
+        }
+    }
+
+    /// Compiles the keymap described by the configuration.
+    pub fn compile(&self) -> Result<Keymap, KeymapError> {
+        let layout = self.layouts.join(",");
+        let variant = self.variants.join(",");
+        for name in &[&self.rules, &self.model, &layout, &variant, &self.options] {
+            if name.contains('\0') {
+                return Err(KeymapError::InvalidName(name.to_string()));
+            }
+        }
+        let context = xkb::Context::new(xkb::CONTEXT_NO_FLAGS);
+        Keymap::new_from_names(
+            &context,
+            self.rules.as_str(),
+            self.model.as_str(),
+            layout.as_str(),
+            variant.as_str(),
+            if self.options.is_empty() {
+                None
+            } else {
+                Some(self.options.clone())
+            },
+            xkb::KEYMAP_COMPILE_NO_FLAGS
+        )
+        .ok_or_else(|| KeymapError::CompileFailed(self.clone()))
+    }
+
+    /// Compiles the keymap and makes the keyboard use it.
+    pub fn apply(&self, keyboard: &mut Keyboard) -> Result<(), KeymapError> {
+        let keymap = self.compile()?;
+        wlr_log!(WLR_DEBUG, "Applying keymap {:?}", self);
+        keyboard.set_keymap(&keymap);
+        Ok(())
+    }
+}
+
+impl Keymaps {
+    /// Makes a set of configurations using `default` for every keyboard.
+    pub fn new(default: Config) -> Self {
+        Keymaps {
+            default,
+            rules: Vec::new()
+        }
+    }
+
+    /// Uses `config` for the keyboards matching `device_match`.
+    ///
+    /// Rules are checked in the order they were added, the first one that
+    /// matches is used.
+    pub fn add_rule(&mut self, device_match: DeviceMatch, config: Config) {
+        self.rules.push((device_match, config))
+    }
+
+    /// Removes every rule, so every keyboard uses the default configuration.
+    pub fn clear_rules(&mut self) {
+        self.rules.clear()
+    }
+
+    /// Gets the default configuration.
+    pub fn default_config(&self) -> &Config {
+        &self.default
+    }
+
+    /// Changes the default configuration.
+    pub fn set_default_config(&mut self, config: Config) {
+        self.default = config
+    }
+
+    /// Gets the configuration to use for the input device.
+    pub fn config_for(&self, device: &input::Device) -> &Config {
+        self.rules
+            .iter()
+            .find(|(device_match, _)| device_match.matches(device))
+            .map(|(_, config)| config)
+            .unwrap_or(&self.default)
+    }
+
+    /// Applies the configuration matching the keyboard to it.
+    pub fn apply(&self, keyboard: &mut Keyboard) -> Result<(), KeymapError> {
+        let config = self.config_for(keyboard.input_device());
+        config.apply(keyboard)
+    }
+}
+
+impl fmt::Display for KeymapError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::KeymapError::*;
+        match *self {
+            InvalidName(ref name) => write!(f, "Invalid XKB name {:?}", name),
+            CompileFailed(ref config) => write!(f, "Could not compile keymap {:?}", config)
+        }
+    }
+}
+
+impl Error for KeymapError {
+    fn description(&self) -> &str {
+        use self::KeymapError::*;
+        match *self {
+            InvalidName(_) => "Invalid XKB name",
+            CompileFailed(_) => "Could not compile keymap"
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn from_vars(vars: &[(&str, &str)]) -> Config {
+        Config::from_vars(|name| {
+            vars.iter()
+                .find(|&&(var, _)| var == name)
+                .map(|&(_, value)| value.to_string())
+        })
+    }
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn from_vars_defaults() {
+        assert_eq!(from_vars(&[]), Config::default());
+        assert_eq!(
+            from_vars(&[("XKB_DEFAULT_LAYOUT", ""), ("XKB_DEFAULT_VARIANT", "")]),
+            Config::default()
+        );
+    }
+
+    #[test]
+    fn from_vars_names() {
+        let config = from_vars(&[
+            ("XKB_DEFAULT_RULES", "evdev"),
+            ("XKB_DEFAULT_MODEL", "pc105"),
+            ("XKB_DEFAULT_LAYOUT", "us"),
+            ("XKB_DEFAULT_VARIANT", "dvorak"),
+            ("XKB_DEFAULT_OPTIONS", "caps:escape,compose:ralt")
+        ]);
+        assert_eq!(
+            config,
+            Config {
+                rules: "evdev".into(),
+                model: "pc105".into(),
+                layouts: strings(&["us"]),
+                variants: strings(&["dvorak"]),
+                options: "caps:escape,compose:ralt".into()
+            }
+        );
+    }
+
+    #[test]
+    fn from_vars_pairs_layouts_and_variants() {
+        // The first layout uses its default variant.
+        let config = from_vars(&[
+            ("XKB_DEFAULT_LAYOUT", "us,de,fr"),
+            ("XKB_DEFAULT_VARIANT", ",nodeadkeys,")
+        ]);
+        assert_eq!(config.layouts, strings(&["us", "de", "fr"]));
+        assert_eq!(config.variants, strings(&["", "nodeadkeys", ""]));
+        assert_eq!(config.layouts.join(","), "us,de,fr");
+        assert_eq!(config.variants.join(","), ",nodeadkeys,");
+
+        // Fewer variants than layouts.
+        let config = from_vars(&[("XKB_DEFAULT_LAYOUT", "us,de"), ("XKB_DEFAULT_VARIANT", "dvorak")]);
+        assert_eq!(config.layouts, strings(&["us", "de"]));
+        assert_eq!(config.variants, strings(&["dvorak"]));
+
+        // Empty layouts are dropped.
+        let config = from_vars(&[("XKB_DEFAULT_LAYOUT", "us,,de,")]);
+        assert_eq!(config.layouts, strings(&["us", "de"]));
+        assert!(config.variants.is_empty());
+    }
+}
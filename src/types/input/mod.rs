@@ -1,10 +1,17 @@
+pub mod gesture;
 mod input_device;
+pub mod key_repeat;
+pub mod keybinding;
 pub mod keyboard;
+pub mod keymap;
 pub mod pointer;
+pub mod rules;
 pub mod switch;
+pub mod switch_state;
 pub mod tablet_pad;
 pub mod tablet_tool;
 pub mod touch;
+pub mod touch_emulation;
 
 pub use self::input_device::*;
 
@@ -13,9 +20,11 @@ pub mod manager {
     //!
     //! To manage a particular type of input resource implement a function
     //! with the signature of its corresponding name. For example, to manage
-    //! keyboards implement [`KeyboardAdded`](./type.KeyboardAdded.html).
+    //! keyboards implement [`KeyboardAdded`](./trait.KeyboardAdded.html).
+    //! Closures work too, so the callbacks can capture configuration or
+    //! channels.
     //!
-    //! Pass those functions to an [`input::Builder`](./struct.Builder.html)
+    //! Pass those callbacks to an [`input::Builder`](./struct.Builder.html)
     //! which is then given to a `compositor::Builder`.
     pub use crate::manager::input_manager::*;
 }
@@ -1,6 +1,10 @@
 //! TODO Documentation
 
-use std::{cell::Cell, ptr::NonNull, rc::Rc};
+use std::{
+    cell::Cell,
+    ptr::{self, NonNull},
+    rc::Rc
+};
 
 use wlroots_sys::{wlr_input_device, wlr_pointer};
 
@@ -8,7 +12,7 @@ pub use crate::events::pointer_events as event;
 pub use crate::manager::pointer_handler::*;
 use crate::{
     input::{self, InputState},
-    utils::{self, HandleErr, HandleResult, Handleable}
+    utils::{self, HandleErr, HandleResult, Handleable, UserData}
 };
 
 pub type Handle = utils::Handle<NonNull<wlr_input_device>, wlr_pointer, Pointer>;
@@ -52,7 +56,8 @@ impl Pointer {
                 let handle = Rc::downgrade(&liveliness);
                 let state = Box::new(InputState {
                     handle,
-                    device: input::Device::from_ptr(device)
+                    device: input::Device::from_ptr(device),
+                    user_data: UserData::new()
                 });
                 (*pointer.as_ptr()).data = Box::into_raw(state) as *mut _;
                 Some(Pointer {
@@ -70,6 +75,11 @@ impl Pointer {
         &self.device
     }
 
+    /// Gets the wlr_input_device associated with this pointer mutably.
+    pub fn input_device_mut(&mut self) -> &mut input::Device {
+        &mut self.device
+    }
+
     /// Gets the wlr_pointer associated with this Pointer.
     #[allow(dead_code)]
     pub(crate) unsafe fn as_ptr(&self) -> *mut wlr_pointer {
@@ -83,6 +93,7 @@ impl Drop for Pointer {
             wlr_log!(WLR_DEBUG, "Dropped Pointer {:p}", self.pointer);
             unsafe {
                 let _ = Box::from_raw((*self.pointer.as_ptr()).data as *mut InputState);
+                (*self.pointer.as_ptr()).data = ptr::null_mut();
             }
             let weak_count = Rc::weak_count(&self.liveliness);
             if weak_count > 0 {
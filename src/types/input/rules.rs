@@ -0,0 +1,488 @@
+//! Declarative configuration of input devices, applied when they are
+//! plugged in.
+//!
+//! Every rule whose `DeviceMatch` matches a device is applied to it, in the
+//! order the rules were added, so later rules override the settings of
+//! earlier ones:
+//!
+//! ```rust,no_run,ignore
+//! let mut rules = DeviceRules::new();
+//! rules.add_rule(
+//!     DeviceMatch { kind: Some(DeviceKind::Touchpad), ..DeviceMatch::default() },
+//!     Settings { tap: Some(true), natural_scroll: Some(true), ..Settings::default() }
+//! );
+//! rules.add_rule(
+//!     DeviceMatch { name: Some("Wacom Intuos S Pen".into()), ..DeviceMatch::default() },
+//!     Settings { output: Some("DP-1".into()), ..Settings::default() }
+//! );
+//!
+//! // Either give the rules to the compositor, which applies the libinput
+//! // settings and keymaps before the input manager callbacks are called, and
+//! // maps the devices to their output after them:
+//! let compositor = compositor::Builder::new()
+//!     .input_manager(input_builder)
+//!     .device_rules(rules.clone(), cursor_handle, layout_handle)
+//!     .build_auto(state);
+//!
+//! // Or apply them by hand, in the `InputAdded` callback:
+//! rules.apply(device);
+//! // In the `KeyboardAdded` callback:
+//! keyboard_handle.run(|keyboard| rules.apply_keymap(keyboard)).unwrap()?;
+//! // In the `PointerAdded`, `TouchAdded` and `TabletToolAdded` callbacks,
+//! // after attaching the device to the cursor:
+//! rules.map_to_output(cursor, layout, device);
+//! ```
+//!
+//! Either way, call `map_to_output` again when outputs are added to the
+//! layout, so devices plugged in before their output are mapped too.
+
+use std::rc::Rc;
+
+use wlroots_sys::wlr_input_device_type::{
+    WLR_INPUT_DEVICE_POINTER, WLR_INPUT_DEVICE_TABLET_TOOL, WLR_INPUT_DEVICE_TOUCH
+};
+
+use crate::{
+    backend::{AccelProfile, ClickMethod, DeviceConfigError, Libinput, ScrollMethod},
+    cursor::{self, Cursor},
+    input::{
+        self,
+        keyboard::{self, Keyboard},
+        keymap::{self, KeymapError},
+        manager, pointer, tablet_tool, touch, DeviceMatch
+    },
+    output::layout::{self, Layout}
+};
+
+/// The settings a rule applies to the devices it matches.
+///
+/// Fields that are `None` are left as they are.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Settings {
+    /// The keymap of keyboards.
+    pub keymap: Option<keymap::Config>,
+    pub tap: Option<bool>,
+    pub natural_scroll: Option<bool>,
+    pub accel_profile: Option<AccelProfile>,
+    pub accel_speed: Option<f64>,
+    pub left_handed: Option<bool>,
+    pub scroll_method: Option<ScrollMethod>,
+    pub click_method: Option<ClickMethod>,
+    pub disable_while_typing: Option<bool>,
+    pub middle_emulation: Option<bool>,
+    /// The name of the output absolute input (e.g. from tablets and
+    /// touchscreens) is mapped to.
+    pub output: Option<String>
+}
+
+/// A list of rules configuring input devices.
+#[derive(Debug, Default, Clone)]
+pub struct DeviceRules {
+    rules: Vec<(DeviceMatch, Settings)>
+}
+
+impl Settings {
+    /// Overrides these settings with the ones set in `other`.
+    pub fn merge(&mut self, other: &Settings) {
+        fn set<T: Clone>(field: &mut Option<T>, other: &Option<T>) {
+            if other.is_some() {
+                *field = other.clone()
+            }
+        }
+        set(&mut self.keymap, &other.keymap);
+        set(&mut self.tap, &other.tap);
+        set(&mut self.natural_scroll, &other.natural_scroll);
+        set(&mut self.accel_profile, &other.accel_profile);
+        set(&mut self.accel_speed, &other.accel_speed);
+        set(&mut self.left_handed, &other.left_handed);
+        set(&mut self.scroll_method, &other.scroll_method);
+        set(&mut self.click_method, &other.click_method);
+        set(&mut self.disable_while_typing, &other.disable_while_typing);
+        set(&mut self.middle_emulation, &other.middle_emulation);
+        set(&mut self.output, &other.output);
+    }
+
+    /// Applies the libinput settings to the device.
+    ///
+    /// Settings the device doesn't support are skipped. Every other failure
+    /// is logged and the remaining settings are still applied.
+    pub fn apply_libinput(&self, device: &input::Device) {
+        let mut config = match Libinput::device_config(device) {
+            Ok(config) => config,
+            Err(_) => return
+        };
+        let results = [
+            ("tap", self.tap.map(|tap| config.set_tap_enabled(tap))),
+            (
+                "natural_scroll",
+                self.natural_scroll
+                    .map(|enabled| config.set_natural_scroll(enabled))
+            ),
+            (
+                "accel_profile",
+                self.accel_profile
+                    .map(|profile| config.set_accel_profile(profile))
+            ),
+            (
+                "accel_speed",
+                self.accel_speed.map(|speed| config.set_accel_speed(speed))
+            ),
+            (
+                "left_handed",
+                self.left_handed.map(|enabled| config.set_left_handed(enabled))
+            ),
+            (
+                "scroll_method",
+                self.scroll_method.map(|method| config.set_scroll_method(method))
+            ),
+            (
+                "click_method",
+                self.click_method.map(|method| config.set_click_method(method))
+            ),
+            (
+                "disable_while_typing",
+                self.disable_while_typing
+                    .map(|enabled| config.set_disable_while_typing(enabled))
+            ),
+            (
+                "middle_emulation",
+                self.middle_emulation
+                    .map(|enabled| config.set_middle_emulation(enabled))
+            )
+        ];
+        for (setting, result) in results.iter() {
+            match result {
+                Some(Err(DeviceConfigError::Unsupported)) => {
+                    wlr_log!(WLR_DEBUG, "{:?} does not support {}", device.name(), setting)
+                },
+                Some(Err(err)) => wlr_log!(
+                    WLR_ERROR,
+                    "Could not set {} on {:?}: {}",
+                    setting,
+                    device.name(),
+                    err
+                ),
+                _ => {}
+            }
+        }
+    }
+}
+
+impl DeviceRules {
+    pub fn new() -> Self {
+        DeviceRules::default()
+    }
+
+    /// Applies `settings` to the devices matching `device_match`.
+    pub fn add_rule(&mut self, device_match: DeviceMatch, settings: Settings) {
+        self.rules.push((device_match, settings))
+    }
+
+    /// Removes every rule.
+    pub fn clear_rules(&mut self) {
+        self.rules.clear()
+    }
+
+    /// Gets the settings of every rule matching the device, merged in order.
+    pub fn settings_for(&self, device: &input::Device) -> Settings {
+        self.merged_settings(|device_match| device_match.matches(device))
+    }
+
+    /// Gets the settings of every rule accepted by `matches`, merged in
+    /// order.
+    fn merged_settings<F: Fn(&DeviceMatch) -> bool>(&self, matches: F) -> Settings {
+        let mut settings = Settings::default();
+        for (device_match, rule) in &self.rules {
+            if matches(device_match) {
+                settings.merge(rule)
+            }
+        }
+        settings
+    }
+
+    /// Applies the libinput settings matching the device.
+    ///
+    /// Call this from the `InputAdded` callback.
+    pub fn apply(&self, device: &input::Device) {
+        self.settings_for(device).apply_libinput(device)
+    }
+
+    /// Applies the keymap matching the keyboard, if any.
+    ///
+    /// Call this from the `KeyboardAdded` callback.
+    pub fn apply_keymap(&self, keyboard: &mut Keyboard) -> Result<(), KeymapError> {
+        match self.settings_for(keyboard.input_device()).keymap {
+            Some(config) => config.apply(keyboard),
+            None => Ok(())
+        }
+    }
+
+    /// Maps the device to the output its settings name, if that output is
+    /// in the layout.
+    ///
+    /// The device must already be attached to the cursor. Call this again
+    /// when outputs are added to the layout, so devices plugged in before
+    /// their output are mapped too.
+    ///
+    /// Returns whether the device was mapped.
+    pub fn map_to_output(&self, cursor: &mut Cursor, layout: &mut Layout, device: &input::Device) -> bool {
+        let name = match self.settings_for(device).output {
+            Some(name) => name,
+            None => return false
+        };
+        for (output_handle, _) in layout.outputs() {
+            let mapped = output_handle
+                .run(|output| {
+                    if output.name() != name {
+                        return false;
+                    }
+                    cursor.map_input_to_output(device, &*output);
+                    true
+                })
+                .unwrap_or(false);
+            if mapped {
+                return true;
+            }
+        }
+        wlr_log!(
+            WLR_DEBUG,
+            "Output {} of {:?} is not in the layout",
+            name,
+            device.name()
+        );
+        false
+    }
+
+    /// Maps the device to its output using the cursor and layout behind
+    /// the handles, logging why it could not be done.
+    fn map_to_output_handles(
+        &self,
+        cursor_handle: &cursor::Handle,
+        layout_handle: &layout::Handle,
+        device: &input::Device
+    ) {
+        let res = cursor_handle
+            .run(|cursor| layout_handle.run(|layout| self.map_to_output(cursor, layout, device)));
+        match res {
+            Ok(Ok(_)) => {},
+            Ok(Err(err)) | Err(err) => wlr_log!(
+                WLR_ERROR,
+                "Could not map {:?} to its output: {:?}",
+                device.name(),
+                err
+            )
+        }
+    }
+
+    /// Wraps the callbacks of the input manager so the rules are applied to
+    /// each device when it is added.
+    ///
+    /// Pointers, touch devices and tablet tools get their libinput settings
+    /// before the `PointerAdded`, `TouchAdded` and `TabletToolAdded`
+    /// callbacks, and are mapped to their output after them, once the
+    /// callback attached them to the cursor. Keyboards get their keymap
+    /// before `KeyboardAdded`, and every other device gets its libinput
+    /// settings before `InputAdded`.
+    pub(crate) fn wrap_callbacks(
+        self,
+        mut builder: manager::Builder,
+        cursor_handle: cursor::Handle,
+        layout_handle: layout::Handle
+    ) -> manager::Builder {
+        let rules = Rc::new(self);
+
+        let input_rules = rules.clone();
+        let mut input_added = builder.input_added.take();
+        builder.input_added = Some(Box::new(move |compositor_handle, device: &mut input::Device| {
+            match device.dev_type() {
+                // Applied before their own callbacks.
+                WLR_INPUT_DEVICE_POINTER | WLR_INPUT_DEVICE_TOUCH | WLR_INPUT_DEVICE_TABLET_TOOL => {},
+                _ => input_rules.apply(device)
+            }
+            if let Some(ref mut input_added) = input_added {
+                input_added(compositor_handle, device)
+            }
+        }));
+
+        let keyboard_rules = rules.clone();
+        let mut keyboard_added = builder.keyboard_added.take();
+        builder.keyboard_added = Some(Box::new(
+            move |compositor_handle, keyboard_handle: keyboard::Handle| {
+                let res = keyboard_handle.run(|keyboard| keyboard_rules.apply_keymap(keyboard));
+                if let Ok(Err(err)) = res {
+                    wlr_log!(WLR_ERROR, "Could not apply the keymap rule: {}", err)
+                }
+                keyboard_added
+                    .as_mut()
+                    .and_then(|keyboard_added| keyboard_added(compositor_handle, keyboard_handle))
+            }
+        ));
+
+        let (pointer_rules, pointer_cursor, pointer_layout) =
+            (rules.clone(), cursor_handle.clone(), layout_handle.clone());
+        let mut pointer_added = builder.pointer_added.take();
+        builder.pointer_added = Some(Box::new(
+            move |compositor_handle, pointer_handle: pointer::Handle| {
+                pointer_handle
+                    .run(|pointer| pointer_rules.apply(pointer.input_device()))
+                    .ok();
+                let handler = pointer_added
+                    .as_mut()
+                    .and_then(|pointer_added| pointer_added(compositor_handle, pointer_handle.clone()));
+                pointer_handle
+                    .run(|pointer| {
+                        pointer_rules.map_to_output_handles(
+                            &pointer_cursor,
+                            &pointer_layout,
+                            pointer.input_device()
+                        )
+                    })
+                    .ok();
+                handler
+            }
+        ));
+
+        let (touch_rules, touch_cursor, touch_layout) =
+            (rules.clone(), cursor_handle.clone(), layout_handle.clone());
+        let mut touch_added = builder.touch_added.take();
+        builder.touch_added = Some(Box::new(move |compositor_handle, touch_handle: touch::Handle| {
+            touch_handle
+                .run(|touch| touch_rules.apply(touch.input_device()))
+                .ok();
+            let handler = touch_added
+                .as_mut()
+                .and_then(|touch_added| touch_added(compositor_handle, touch_handle.clone()));
+            touch_handle
+                .run(|touch| {
+                    touch_rules.map_to_output_handles(&touch_cursor, &touch_layout, touch.input_device())
+                })
+                .ok();
+            handler
+        }));
+
+        let tablet_tool_rules = rules;
+        let mut tablet_tool_added = builder.tablet_tool_added.take();
+        builder.tablet_tool_added = Some(Box::new(
+            move |compositor_handle, tablet_tool_handle: tablet_tool::Handle| {
+                tablet_tool_handle
+                    .run(|tablet_tool| tablet_tool_rules.apply(tablet_tool.input_device()))
+                    .ok();
+                let handler = tablet_tool_added.as_mut().and_then(|tablet_tool_added| {
+                    tablet_tool_added(compositor_handle, tablet_tool_handle.clone())
+                });
+                tablet_tool_handle
+                    .run(|tablet_tool| {
+                        tablet_tool_rules.map_to_output_handles(
+                            &cursor_handle,
+                            &layout_handle,
+                            tablet_tool.input_device()
+                        )
+                    })
+                    .ok();
+                handler
+            }
+        ));
+
+        builder
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merge() {
+        let mut settings = Settings {
+            tap: Some(true),
+            accel_speed: Some(0.5),
+            output: Some("eDP-1".into()),
+            ..Settings::default()
+        };
+        settings.merge(&Settings {
+            tap: Some(false),
+            natural_scroll: Some(true),
+            ..Settings::default()
+        });
+        assert_eq!(
+            settings,
+            Settings {
+                tap: Some(false),
+                natural_scroll: Some(true),
+                accel_speed: Some(0.5),
+                output: Some("eDP-1".into()),
+                ..Settings::default()
+            }
+        );
+        // Merging nothing changes nothing.
+        let before = settings.clone();
+        settings.merge(&Settings::default());
+        assert_eq!(settings, before);
+    }
+
+    #[test]
+    fn rule_precedence() {
+        let named = |name: &str| DeviceMatch {
+            name: Some(name.into()),
+            ..DeviceMatch::default()
+        };
+        let mut rules = DeviceRules::new();
+        rules.add_rule(
+            DeviceMatch::default(),
+            Settings {
+                tap: Some(true),
+                left_handed: Some(false),
+                ..Settings::default()
+            }
+        );
+        rules.add_rule(
+            named("touchpad"),
+            Settings {
+                tap: Some(false),
+                ..Settings::default()
+            }
+        );
+        rules.add_rule(
+            named("mouse"),
+            Settings {
+                left_handed: Some(true),
+                ..Settings::default()
+            }
+        );
+        let settings_for = |name: &str| {
+            rules.merged_settings(|device_match| {
+                device_match
+                    .name
+                    .as_ref()
+                    .map(|match_name| match_name == name)
+                    .unwrap_or(true)
+            })
+        };
+        assert_eq!(
+            settings_for("touchpad"),
+            Settings {
+                tap: Some(false),
+                left_handed: Some(false),
+                ..Settings::default()
+            }
+        );
+        assert_eq!(
+            settings_for("mouse"),
+            Settings {
+                tap: Some(true),
+                left_handed: Some(true),
+                ..Settings::default()
+            }
+        );
+        assert_eq!(
+            settings_for("keyboard"),
+            Settings {
+                tap: Some(true),
+                left_handed: Some(false),
+                ..Settings::default()
+            }
+        );
+        rules.clear_rules();
+        assert_eq!(rules.merged_settings(|_| true), Settings::default());
+    }
+}
@@ -1,13 +1,17 @@
 //! TODO Documentation
 
-use std::{cell::Cell, ptr::NonNull, rc::Rc};
+use std::{
+    cell::Cell,
+    ptr::{self, NonNull},
+    rc::Rc
+};
 
 pub use crate::events::switch_events as event;
 pub use crate::manager::switch_handler::*;
 use wlroots_sys::{wlr_input_device, wlr_switch};
 use crate::{
     input::{self, InputState},
-    utils::{self, HandleErr, HandleResult, Handleable}
+    utils::{self, HandleErr, HandleResult, Handleable, UserData}
 };
 
 pub type Handle = utils::Handle<NonNull<wlr_input_device>, wlr_switch, Switch>;
@@ -51,7 +55,8 @@ impl Switch {
                 let handle = Rc::downgrade(&liveliness);
                 let state = Box::new(InputState {
                     handle,
-                    device: input::Device::from_ptr(device)
+                    device: input::Device::from_ptr(device),
+                    user_data: UserData::new()
                 });
                 (*switch.as_ptr()).data = Box::into_raw(state) as *mut _;
                 Some(Switch {
@@ -68,6 +73,11 @@ impl Switch {
     pub fn input_device(&self) -> &input::Device {
         &self.device
     }
+
+    /// Gets the wlr_input_device associated with this switch mutably.
+    pub fn input_device_mut(&mut self) -> &mut input::Device {
+        &mut self.device
+    }
 }
 
 impl Drop for Switch {
@@ -76,6 +86,7 @@ impl Drop for Switch {
             wlr_log!(WLR_DEBUG, "Dropped Switch {:p}", self.switch.as_ptr());
             unsafe {
                 let _ = Box::from_raw((*self.switch.as_ptr()).data as *mut InputState);
+                (*self.switch.as_ptr()).data = ptr::null_mut();
             }
             let weak_count = Rc::weak_count(&self.liveliness);
             if weak_count > 0 {
@@ -0,0 +1,329 @@
+//! Tracks the state of the lid and tablet mode switches.
+//!
+//! Forward the toggle and destroy events of every switch to a
+//! `SwitchState`, which calls its `Handler` when the lid is opened or
+//! closed and when tablet mode is entered or left:
+//!
+//! ```rust,no_run,ignore
+//! impl switch::Handler for SwitchHandler {
+//!     fn on_toggle(&mut self, compositor_handle: compositor::Handle,
+//!                  switch_handle: switch::Handle, event: &switch::event::Toggle) {
+//!         self.switch_state.toggle(compositor_handle, event);
+//!     }
+//!
+//!     fn destroyed(&mut self, compositor_handle: compositor::Handle, switch_handle: switch::Handle) {
+//!         switch_handle.run(|switch| self.switch_state.remove(compositor_handle, switch)).unwrap();
+//!     }
+//! }
+//! ```
+//!
+//! It can also disable the internal output (e.g. the laptop's screen)
+//! while the lid is closed and an external output is connected.
+
+use std::{collections::HashMap, hash::Hash, ptr::NonNull};
+
+use wlroots_sys::{
+    wlr_input_device,
+    wlr_switch_state::{self, *},
+    wlr_switch_type::*
+};
+
+use crate::{
+    compositor,
+    input::switch::{event, Switch},
+    output::{self, layout}
+};
+
+/// Handles changes of the switch state.
+#[allow(unused_variables)]
+pub trait Handler {
+    /// Callback that is triggered when the lid is closed.
+    fn lid_closed(&mut self, compositor_handle: compositor::Handle) {}
+
+    /// Callback that is triggered when the lid is opened.
+    fn lid_opened(&mut self, compositor_handle: compositor::Handle) {}
+
+    /// Callback that is triggered when the device enters tablet mode.
+    fn tablet_mode_entered(&mut self, compositor_handle: compositor::Handle) {}
+
+    /// Callback that is triggered when the device leaves tablet mode.
+    fn tablet_mode_left(&mut self, compositor_handle: compositor::Handle) {}
+}
+
+/// The kind of a switch.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+enum Kind {
+    Lid,
+    TabletMode
+}
+
+/// Whether each switch is on, along with its kind.
+///
+/// With several switches of the same kind, the kind is on if any of them is.
+#[derive(Debug)]
+struct Switches<K> {
+    states: HashMap<K, (Kind, bool)>
+}
+
+/// The current state of the lid and tablet mode switches.
+///
+/// With several switches of the same kind, the lid is closed if any lid
+/// switch is on, and likewise for tablet mode.
+pub struct SwitchState {
+    handler: Box<Handler>,
+    switches: Switches<NonNull<wlr_input_device>>,
+    layout: Option<layout::Handle>,
+    /// Overrides the detection of the internal output by name.
+    internal_output: Option<output::Handle>,
+    disable_internal_output: bool,
+    /// The internal output disabled because the lid is closed.
+    disabled_output: Option<output::Handle>
+}
+
+impl SwitchState {
+    /// Makes a switch state tracker calling the handler when the state
+    /// changes.
+    ///
+    /// The lid is assumed to be open and tablet mode to be off until a switch
+    /// reports otherwise.
+    pub fn new(handler: Box<Handler>) -> Self {
+        SwitchState {
+            handler,
+            switches: Switches::new(),
+            layout: None,
+            internal_output: None,
+            disable_internal_output: false,
+            disabled_output: None
+        }
+    }
+
+    /// Determines if the lid is closed.
+    pub fn lid_closed(&self) -> bool {
+        self.switches.is_on(Kind::Lid)
+    }
+
+    /// Determines if the device is in tablet mode.
+    pub fn tablet_mode(&self) -> bool {
+        self.switches.is_on(Kind::TabletMode)
+    }
+
+    /// Makes closing the lid disable the internal output, as long as another
+    /// enabled output is in the layout. Opening the lid enables it again.
+    ///
+    /// Passing `None` stops managing the internal output, re-enabling it if
+    /// it was disabled.
+    pub fn disable_internal_output_on_lid(&mut self, layout: Option<layout::Handle>) {
+        self.disable_internal_output = layout.is_some();
+        self.layout = layout;
+        self.update_outputs()
+    }
+
+    /// Sets which output is the internal one.
+    ///
+    /// By default outputs are considered internal based on their name, see
+    /// `is_internal_output_name`.
+    pub fn set_internal_output(&mut self, output: Option<output::Handle>) {
+        self.internal_output = output;
+        self.update_outputs()
+    }
+
+    /// Feeds a toggle event of a switch to the tracker.
+    pub fn toggle(&mut self, compositor_handle: compositor::Handle, event: &event::Toggle) {
+        let kind = match event.switch_type() {
+            WLR_SWITCH_TYPE_LID => Kind::Lid,
+            WLR_SWITCH_TYPE_TABLET_MODE => Kind::TabletMode
+        };
+        let device = unsafe { event.device().as_non_null() };
+        if let Some(is_on) = self.switches.toggle(device, kind, event.switch_state()) {
+            self.notify(compositor_handle, kind, is_on)
+        }
+    }
+
+    /// Forgets about a switch that is being destroyed.
+    pub fn remove(&mut self, compositor_handle: compositor::Handle, switch: &Switch) {
+        let device = unsafe { switch.input_device().as_non_null() };
+        if let Some((kind, is_on)) = self.switches.remove(&device) {
+            self.notify(compositor_handle, kind, is_on)
+        }
+    }
+
+    /// Enables or disables the internal output according to the lid state.
+    ///
+    /// This is done whenever the switch state changes, but it should also be
+    /// called when outputs are added to or removed from the layout.
+    pub fn update_outputs(&mut self) {
+        let internal = if self.disable_internal_output && self.lid_closed() {
+            self.find_internal_output()
+        } else {
+            None
+        };
+        if internal != self.disabled_output {
+            if let Some(output) = self.disabled_output.take() {
+                wlr_log!(WLR_DEBUG, "Enabling internal output");
+                output.run(|output| output.enable(true)).ok();
+            }
+        }
+        if let Some(output) = internal {
+            if self.disabled_output.is_none() {
+                wlr_log!(WLR_DEBUG, "Disabling internal output, the lid is closed");
+                output.run(|output| output.enable(false)).ok();
+                self.disabled_output = Some(output);
+            }
+        }
+    }
+
+    /// Calls the handler for a kind of switch that was turned on or off.
+    fn notify(&mut self, compositor_handle: compositor::Handle, kind: Kind, is_on: bool) {
+        match (kind, is_on) {
+            (Kind::Lid, true) => self.handler.lid_closed(compositor_handle),
+            (Kind::Lid, false) => self.handler.lid_opened(compositor_handle),
+            (Kind::TabletMode, true) => self.handler.tablet_mode_entered(compositor_handle),
+            (Kind::TabletMode, false) => self.handler.tablet_mode_left(compositor_handle)
+        }
+        if kind == Kind::Lid {
+            self.update_outputs()
+        }
+    }
+
+    /// Finds the internal output, if there is another enabled output in the
+    /// layout to show things on instead.
+    fn find_internal_output(&self) -> Option<output::Handle> {
+        let outputs = self
+            .layout
+            .as_ref()?
+            .run(|layout| layout.outputs())
+            .ok()?
+            .into_iter()
+            .map(|(output, _)| output)
+            .collect::<Vec<_>>();
+        let is_internal = |handle: &output::Handle| match self.internal_output {
+            Some(ref internal) => handle == internal,
+            None => handle
+                .run(|output| is_internal_output_name(&output.name()))
+                .unwrap_or(false)
+        };
+        let internal = outputs
+            .iter()
+            .find(|&handle| is_internal(handle) || Some(handle) == self.disabled_output.as_ref())?;
+        let has_external = outputs
+            .iter()
+            .any(|handle| handle != internal && handle.run(|output| output.enabled()).unwrap_or(false));
+        if has_external {
+            Some(internal.clone())
+        } else {
+            None
+        }
+    }
+}
+
+impl<K: Eq + Hash> Switches<K> {
+    fn new() -> Self {
+        Switches {
+            states: HashMap::new()
+        }
+    }
+
+    /// Determines if any switch of the kind is on.
+    fn is_on(&self, kind: Kind) -> bool {
+        self.states
+            .values()
+            .any(|&(switch_kind, on)| switch_kind == kind && on)
+    }
+
+    /// Updates the state of a switch, tracking it if it's new.
+    ///
+    /// Returns whether the kind is now on, if that changed.
+    fn toggle(&mut self, key: K, kind: Kind, switch_state: wlr_switch_state) -> Option<bool> {
+        let was_on = self.is_on(kind);
+        {
+            let state = &mut self.states.entry(key).or_insert((kind, false)).1;
+            *state = match switch_state {
+                WLR_SWITCH_STATE_OFF => false,
+                WLR_SWITCH_STATE_ON => true,
+                WLR_SWITCH_STATE_TOGGLE => !*state
+            };
+        }
+        changed(was_on, self.is_on(kind))
+    }
+
+    /// Stops tracking a switch.
+    ///
+    /// Returns the kind of the switch and whether the kind is now on, if that
+    /// changed.
+    fn remove(&mut self, key: &K) -> Option<(Kind, bool)> {
+        let kind = self.states.get(key)?.0;
+        let was_on = self.is_on(kind);
+        self.states.remove(key);
+        changed(was_on, self.is_on(kind)).map(|is_on| (kind, is_on))
+    }
+}
+
+fn changed(was_on: bool, is_on: bool) -> Option<bool> {
+    if was_on == is_on {
+        None
+    } else {
+        Some(is_on)
+    }
+}
+
+/// Determines if an output is built into the device from its name, e.g.
+/// `eDP-1` or `LVDS-1`.
+pub fn is_internal_output_name(name: &str) -> bool {
+    ["eDP", "LVDS", "DSI"]
+        .iter()
+        .any(|prefix| name.starts_with(prefix))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn internal_output_names() {
+        for name in &["eDP-1", "eDP-2", "LVDS-1", "DSI-1"] {
+            assert!(is_internal_output_name(name), "{}", name);
+        }
+        for name in &[
+            "DP-1", "HDMI-A-1", "DVI-D-1", "VGA-1", "WL-1", "X11-1", "", "edp-1"
+        ] {
+            assert!(!is_internal_output_name(name), "{}", name);
+        }
+    }
+
+    #[test]
+    fn aggregate_switches() {
+        let mut switches = Switches::new();
+        assert!(!switches.is_on(Kind::Lid));
+        assert_eq!(switches.toggle(0, Kind::Lid, WLR_SWITCH_STATE_OFF), None);
+        assert_eq!(switches.toggle(0, Kind::Lid, WLR_SWITCH_STATE_ON), Some(true));
+        // Another lid switch turning on doesn't change anything.
+        assert_eq!(switches.toggle(1, Kind::Lid, WLR_SWITCH_STATE_ON), None);
+        assert_eq!(switches.toggle(0, Kind::Lid, WLR_SWITCH_STATE_OFF), None);
+        assert!(switches.is_on(Kind::Lid));
+        assert!(!switches.is_on(Kind::TabletMode));
+        assert_eq!(
+            switches.toggle(1, Kind::Lid, WLR_SWITCH_STATE_TOGGLE),
+            Some(false)
+        );
+        assert_eq!(switches.toggle(1, Kind::Lid, WLR_SWITCH_STATE_TOGGLE), Some(true));
+        assert_eq!(
+            switches.toggle(2, Kind::TabletMode, WLR_SWITCH_STATE_ON),
+            Some(true)
+        );
+        assert!(switches.is_on(Kind::Lid));
+        assert!(switches.is_on(Kind::TabletMode));
+    }
+
+    #[test]
+    fn remove_switches() {
+        let mut switches = Switches::new();
+        switches.toggle(0, Kind::Lid, WLR_SWITCH_STATE_ON);
+        switches.toggle(1, Kind::Lid, WLR_SWITCH_STATE_ON);
+        switches.toggle(2, Kind::TabletMode, WLR_SWITCH_STATE_OFF);
+        assert_eq!(switches.remove(&0), None);
+        assert_eq!(switches.remove(&0), None);
+        assert_eq!(switches.remove(&2), None);
+        assert_eq!(switches.remove(&1), Some((Kind::Lid, false)));
+        assert!(!switches.is_on(Kind::Lid));
+    }
+}
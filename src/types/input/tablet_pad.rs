@@ -1,5 +1,9 @@
 //! TODO Documentation
-use std::{cell::Cell, ptr::NonNull, rc::Rc};
+use std::{
+    cell::Cell,
+    ptr::{self, NonNull},
+    rc::Rc
+};
 
 use wlroots_sys::{wlr_input_device, wlr_tablet_pad};
 
@@ -7,7 +11,7 @@ pub use crate::events::tablet_pad_events as event;
 pub use crate::manager::tablet_pad_handler::*;
 use crate::{
     input::{self, InputState},
-    utils::{self, HandleErr, HandleResult, Handleable}
+    utils::{self, HandleErr, HandleResult, Handleable, UserData}
 };
 
 pub type Handle = utils::Handle<NonNull<wlr_input_device>, wlr_tablet_pad, TabletPad>;
@@ -51,7 +55,8 @@ impl TabletPad {
                 let handle = Rc::downgrade(&liveliness);
                 let state = Box::new(InputState {
                     handle,
-                    device: input::Device::from_ptr(device)
+                    device: input::Device::from_ptr(device),
+                    user_data: UserData::new()
                 });
                 (*pad.as_ptr()).data = Box::into_raw(state) as *mut _;
                 Some(TabletPad {
@@ -68,6 +73,11 @@ impl TabletPad {
     pub fn input_device(&self) -> &input::Device {
         &self.device
     }
+
+    /// Gets the wlr_input_device associated with this TabletPad mutably.
+    pub fn input_device_mut(&mut self) -> &mut input::Device {
+        &mut self.device
+    }
 }
 
 impl Drop for TabletPad {
@@ -78,6 +88,7 @@ impl Drop for TabletPad {
         wlr_log!(WLR_DEBUG, "Dropped TabletPad {:p}", self.pad.as_ptr());
         unsafe {
             let _ = Box::from_raw((*self.pad.as_ptr()).data as *mut InputState);
+            (*self.pad.as_ptr()).data = ptr::null_mut();
         }
         let weak_count = Rc::weak_count(&self.liveliness);
         if weak_count > 0 {
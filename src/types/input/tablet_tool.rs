@@ -1,5 +1,9 @@
 //! TODO Documentation
-use std::{cell::Cell, ptr::NonNull, rc::Rc};
+use std::{
+    cell::Cell,
+    ptr::{self, NonNull},
+    rc::Rc
+};
 
 use wlroots_sys::{wlr_input_device, wlr_tablet, wlr_tablet_tool_axes};
 
@@ -7,7 +11,7 @@ pub use crate::events::tablet_tool_events as event;
 pub use crate::manager::tablet_tool_handler::*;
 use crate::{
     input::{self, InputState},
-    utils::{self, HandleErr, HandleResult, Handleable}
+    utils::{self, HandleErr, HandleResult, Handleable, UserData}
 };
 
 pub type Handle = utils::Handle<NonNull<wlr_input_device>, wlr_tablet, TabletTool>;
@@ -69,7 +73,8 @@ impl TabletTool {
                 let handle = Rc::downgrade(&liveliness);
                 let state = Box::new(InputState {
                     handle,
-                    device: input::Device::from_ptr(device)
+                    device: input::Device::from_ptr(device),
+                    user_data: UserData::new()
                 });
                 (*tool.as_ptr()).data = Box::into_raw(state) as *mut _;
                 Some(TabletTool {
@@ -86,6 +91,11 @@ impl TabletTool {
     pub fn input_device(&self) -> &input::Device {
         &self.device
     }
+
+    /// Gets the wlr_input_device associated with this TabletTool mutably.
+    pub fn input_device_mut(&mut self) -> &mut input::Device {
+        &mut self.device
+    }
 }
 
 impl Drop for TabletTool {
@@ -96,6 +106,7 @@ impl Drop for TabletTool {
         wlr_log!(WLR_DEBUG, "Dropped TabletTool {:p}", self.tool.as_ptr());
         unsafe {
             let _ = Box::from_raw((*self.tool.as_ptr()).data as *mut InputState);
+            (*self.tool.as_ptr()).data = ptr::null_mut();
         }
         let weak_count = Rc::weak_count(&self.liveliness);
         if weak_count > 0 {
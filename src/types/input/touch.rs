@@ -1,6 +1,10 @@
 //! TODO Documentation
 
-use std::{cell::Cell, ptr::NonNull, rc::Rc};
+use std::{
+    cell::Cell,
+    ptr::{self, NonNull},
+    rc::Rc
+};
 
 use wlroots_sys::{wlr_input_device, wlr_touch};
 
@@ -8,7 +12,7 @@ pub use crate::events::touch_events as event;
 pub use crate::manager::touch_handler::*;
 use crate::{
     input::{self, InputState},
-    utils::{self, HandleErr, HandleResult, Handleable}
+    utils::{self, HandleErr, HandleResult, Handleable, UserData}
 };
 
 pub type Handle = utils::Handle<NonNull<wlr_input_device>, wlr_touch, Touch>;
@@ -49,7 +53,8 @@ impl Touch {
                 let handle = Rc::downgrade(&liveliness);
                 let state = Box::new(InputState {
                     handle,
-                    device: input::Device::from_ptr(device)
+                    device: input::Device::from_ptr(device),
+                    user_data: UserData::new()
                 });
                 (*touch.as_ptr()).data = Box::into_raw(state) as *mut _;
                 Some(Touch {
@@ -66,13 +71,19 @@ impl Touch {
     pub fn input_device(&self) -> &input::Device {
         &self.device
     }
+
+    /// Gets the wlr_input_device associated with this `Touch` mutably.
+    pub fn input_device_mut(&mut self) -> &mut input::Device {
+        &mut self.device
+    }
 }
 impl Drop for Touch {
     fn drop(&mut self) {
         if Rc::strong_count(&self.liveliness) == 1 {
             wlr_log!(WLR_DEBUG, "Dropped Touch {:p}", self.touch.as_ptr());
             unsafe {
-                let _ = Box::from_raw((*self.touch.as_ptr()).data as *mut input::Device);
+                let _ = Box::from_raw((*self.touch.as_ptr()).data as *mut InputState);
+                (*self.touch.as_ptr()).data = ptr::null_mut();
             }
             let weak_count = Rc::weak_count(&self.liveliness);
             if weak_count > 0 {
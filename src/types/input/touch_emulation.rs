@@ -0,0 +1,143 @@
+//! Emulates a pointer with touch input, for clients that don't support
+//! touch.
+//!
+//! The first finger put down on a surface whose client did not bind
+//! `wl_touch` drives the pointer: touching presses the left button, moving
+//! moves the pointer and lifting releases the button and makes the pointer
+//! leave the surface. Touch points on surfaces of clients supporting touch
+//! are passed on to the seat as is.
+//!
+//! Forward the touch events to a `TouchEmulation` instead of the seat:
+//!
+//! ```rust,no_run,ignore
+//! fn on_down(&mut self, compositor_handle: compositor::Handle,
+//!            touch_handle: touch::Handle, event: &touch::event::Down) {
+//!     // Find the surface under the touch point, then
+//!     seat_handle.run(|seat| {
+//!         self.emulation.down(seat, surface, event, sx, sy);
+//!     }).unwrap();
+//! }
+//! ```
+
+use std::time::Duration;
+
+use wlroots_sys::wlr_button_state::*;
+
+use crate::{
+    input::touch::event,
+    seat::{Seat, TouchId},
+    surface::Surface
+};
+
+/// The button emulated touches press.
+const BTN_LEFT: u32 = 0x110;
+
+/// Converts single finger touch input into pointer input.
+#[derive(Debug)]
+pub struct TouchEmulation {
+    enabled: bool,
+    /// The id of the touch point driving the pointer.
+    emulated: Option<i32>
+}
+
+impl Default for TouchEmulation {
+    fn default() -> Self {
+        TouchEmulation::new()
+    }
+}
+
+impl TouchEmulation {
+    pub fn new() -> Self {
+        TouchEmulation {
+            enabled: true,
+            emulated: None
+        }
+    }
+
+    /// Determines if a touch point is currently driving the pointer.
+    pub fn is_emulating(&self) -> bool {
+        self.emulated.is_some()
+    }
+
+    /// Enables or disables the emulation.
+    ///
+    /// While disabled every touch point is passed on to the seat. Touch
+    /// points already driving the pointer keep doing so until they are
+    /// lifted.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled
+    }
+
+    /// Handles a touch point being put down on the surface.
+    ///
+    /// `sx` and `sy` are the surface-local coordinates of the touch point.
+    ///
+    /// Returns `true` if the touch point is emulated as a pointer.
+    pub fn down(
+        &mut self,
+        seat: &Seat,
+        surface: &mut Surface,
+        event: &event::Down,
+        sx: f64,
+        sy: f64
+    ) -> bool {
+        let time = Duration::from_millis(u64::from(event.time_msec()));
+        let emulate = self.enabled && self.emulated.is_none() && !seat.client_accepts_touch(surface);
+        if !emulate {
+            seat.touch_notify_down(surface, time, TouchId::new(event.touch_id()), sx, sy);
+            return false;
+        }
+        self.emulated = Some(event.touch_id());
+        seat.pointer_notify_enter(surface, sx, sy);
+        seat.pointer_notify_motion(time, sx, sy);
+        seat.pointer_notify_button(time, BTN_LEFT, WLR_BUTTON_PRESSED as u32);
+        true
+    }
+
+    /// Handles a touch point moving.
+    ///
+    /// `sx` and `sy` are the coordinates of the touch point, local to the
+    /// surface it was put down on.
+    pub fn motion(&mut self, seat: &Seat, event: &event::Motion, sx: f64, sy: f64) {
+        let time = Duration::from_millis(u64::from(event.time_msec()));
+        if self.emulated == Some(event.touch_id()) {
+            seat.pointer_notify_motion(time, sx, sy);
+        } else {
+            seat.touch_notify_motion(time, TouchId::new(event.touch_id()), sx, sy);
+        }
+    }
+
+    /// Handles a touch point being lifted.
+    pub fn up(&mut self, seat: &Seat, event: &event::Up) {
+        let time = Duration::from_millis(u64::from(event.time_msec()));
+        if self.emulated == Some(event.touch_id()) {
+            self.emulated = None;
+            seat.pointer_notify_button(time, BTN_LEFT, WLR_BUTTON_RELEASED as u32);
+            seat.pointer_clear_focus();
+        } else {
+            seat.touch_notify_up(time, TouchId::new(event.touch_id()));
+        }
+    }
+
+    /// Handles a touch point being cancelled.
+    ///
+    /// The client is told to forget about the touch point, instead of it
+    /// being lifted: an emulated pointer leaves the surface without
+    /// releasing the button, and a touch point is cancelled.
+    pub fn cancel(&mut self, seat: &Seat, event: &event::Cancel) {
+        let time = Duration::from_millis(u64::from(event.time_msec()));
+        let touch_id = TouchId::new(event.touch_id());
+        if self.emulated == Some(event.touch_id()) {
+            self.emulated = None;
+            seat.pointer_clear_focus();
+            // NOTE Nothing has pointer focus anymore, so this only releases
+            // the button in the seat.
+            seat.pointer_notify_button(time, BTN_LEFT, WLR_BUTTON_RELEASED as u32);
+        } else {
+            seat.touch_send_cancel(touch_id);
+            // NOTE wlroots has no other way to remove the touch point, the
+            // client ignores the up event of a cancelled touch point.
+            seat.touch_notify_up(time, touch_id);
+        }
+    }
+}
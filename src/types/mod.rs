@@ -11,6 +11,8 @@ pub mod input;
 #[cfg(feature = "unstable")]
 pub mod output;
 #[cfg(feature = "unstable")]
+pub mod scene;
+#[cfg(feature = "unstable")]
 pub mod seat;
 #[cfg(feature = "unstable")]
 pub mod shell;
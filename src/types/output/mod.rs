@@ -15,7 +15,7 @@ pub mod manager {
     //!
     //! Using the [`OutputBuilder`](./struct.OutputBuilder.html) a
     //! [`BuilderResult`](./struct.BuilderResult.html) is constructed in a
-    //! function conforming to the [`OutputAdded`](./type.OutputAdded.html)
+    //! function conforming to the [`OutputAdded`](./trait.OutputAdded.html)
     //! type signature. That function is passed to the [`output::Builder`](.
     //! /struct.Builder.html) which is then given to the `compositor::Builder`.
     pub use crate::manager::output_manager::*;
@@ -25,20 +25,38 @@ pub use crate::manager::output_handler::*;
 pub(crate) use crate::manager::output_manager::Manager;
 pub use crate::manager::output_manager::{BuilderResult, OutputBuilder as Builder};
 use crate::{
-    area::{Origin, Size},
+    area::{Area, Origin, Size},
     output::{self, layout},
     render::PixmanRegion,
-    utils::{self, c_to_rust_string, HandleErr, HandleResult, Handleable}
+    utils::{self, c_to_rust_string, HandleErr, HandleResult, Handleable, UserData}
 };
 
 pub type Subpixel = wl_output_subpixel;
 pub type Transform = wl_output_transform;
 
+/// The power state of an output.
+///
+/// This is separate from whether the output is enabled: an output that is
+/// powered off is still considered enabled by the compositor, it just
+/// doesn't display anything (e.g. DPMS).
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum PowerMode {
+    /// The output is powered off and does not display anything.
+    Off,
+    /// The output is powered on.
+    On
+}
+
 pub(crate) struct OutputState {
     pub(crate) output: Option<NonNull<UserOutput>>,
     handle: Weak<Cell<bool>>,
     damage: NonNull<wlr_output_damage>,
-    layout_handle: Option<layout::Handle>
+    layout_handle: Option<layout::Handle>,
+    /// Whether the compositor has enabled the output using `Output::enable`.
+    enabled: bool,
+    /// The current power mode, set using `Output::set_power_mode`.
+    power_mode: PowerMode,
+    user_data: UserData
 }
 
 #[derive(Debug)]
@@ -95,7 +113,10 @@ impl Output {
             output: None,
             handle,
             damage: damage_ptr,
-            layout_handle: None
+            layout_handle: None,
+            enabled: (*output.as_ptr()).enabled,
+            power_mode: PowerMode::On,
+            user_data: UserData::new()
         });
         (*output.as_ptr()).data = Box::into_raw(state) as *mut _;
         Output {
@@ -110,7 +131,7 @@ impl Output {
         T: Into<Option<layout::Handle>>
     {
         self.remove_from_output_layout();
-        let user_data = self.user_data();
+        let user_data = self.state();
         if user_data.is_null() {
             return;
         }
@@ -119,14 +140,14 @@ impl Output {
         (*self.output.as_ptr()).data = Box::into_raw(data) as *mut _;
     }
 
-    unsafe fn user_data(&mut self) -> *mut OutputState {
+    unsafe fn state(&mut self) -> *mut OutputState {
         (*self.output.as_ptr()).data as *mut _
     }
 
     /// Used to clear the pointer to an OutputLayout when the OutputLayout
     /// removes this Output from its listing.
     pub(crate) unsafe fn clear_output_layout_data(&mut self) {
-        let user_data = self.user_data();
+        let user_data = self.state();
         if user_data.is_null() {
             return;
         }
@@ -138,7 +159,7 @@ impl Output {
     /// Remove this Output from an OutputLayout, if it is part of an
     /// OutputLayout.
     pub(crate) unsafe fn remove_from_output_layout(&mut self) {
-        let output_data = self.user_data();
+        let output_data = self.state();
         if output_data.is_null() {
             return;
         }
@@ -159,7 +180,7 @@ impl Output {
     /// create two mutable pointers to the same structure. We keep it internally
     /// though because we use it during the cleanup process.
     pub(crate) unsafe fn layout(&mut self) -> Option<layout::Handle> {
-        let data = self.user_data();
+        let data = self.state();
         if data.is_null() {
             None
         } else {
@@ -167,6 +188,26 @@ impl Output {
         }
     }
 
+    /// Gets the user data attached to the output.
+    ///
+    /// It is dropped when the output is destroyed.
+    pub fn user_data(&self) -> &UserData {
+        unsafe {
+            let state = (*self.output.as_ptr()).data as *mut OutputState;
+            &state.as_ref().expect("Output had no state").user_data
+        }
+    }
+
+    /// Gets the user data attached to the output mutably.
+    ///
+    /// It is dropped when the output is destroyed.
+    pub fn user_data_mut(&mut self) -> &mut UserData {
+        unsafe {
+            let state = self.state();
+            &mut state.as_mut().expect("Output had no state").user_data
+        }
+    }
+
     /// Sets the best modesetting for an output.
     ///
     /// NOTE You _cannot_ call this when the output will be removed.
@@ -240,6 +281,9 @@ impl Output {
     }
 
     /// Determines if the output is enabled or not.
+    ///
+    /// Note that this will be `false` while the output is powered off
+    /// using `set_power_mode`.
     pub fn enabled(&self) -> bool {
         unsafe { (*self.output.as_ptr()).enabled }
     }
@@ -402,6 +446,23 @@ impl Output {
         }
     }
 
+    /// Converts an area in output layout coordinates to output buffer
+    /// coordinates, scaling it by the output scale.
+    pub fn layout_area_to_buffer(&self, area: Area) -> Area {
+        let (output_x, output_y) = self.layout_space_pos();
+        let scale = self.scale();
+        Area::new(
+            Origin::new(
+                ((area.origin.x - output_x) as f32 * scale) as i32,
+                ((area.origin.y - output_y) as f32 * scale) as i32
+            ),
+            Size::new(
+                (area.size.width as f32 * scale) as i32,
+                (area.size.height as f32 * scale) as i32
+            )
+        )
+    }
+
     pub fn transform_matrix(&self) -> [c_float; 9] {
         unsafe { (*self.output.as_ptr()).transform_matrix }
     }
@@ -426,8 +487,53 @@ impl Output {
     }
 
     /// Enables or disables an output.
+    ///
+    /// If the output is currently powered off using `set_power_mode` it
+    /// will stay off until it is powered back on.
     pub fn enable(&mut self, enable: bool) -> bool {
-        unsafe { wlr_output_enable(self.output.as_ptr(), enable) }
+        unsafe {
+            let user_data = self.state();
+            if user_data.is_null() {
+                return wlr_output_enable(self.output.as_ptr(), enable);
+            }
+            (*user_data).enabled = enable;
+            wlr_output_enable(
+                self.output.as_ptr(),
+                enable && (*user_data).power_mode == PowerMode::On
+            )
+        }
+    }
+
+    /// Get the current power mode of the output.
+    pub fn power_mode(&self) -> PowerMode {
+        unsafe {
+            let user_data = (*self.output.as_ptr()).data as *mut OutputState;
+            if user_data.is_null() {
+                PowerMode::On
+            } else {
+                (*user_data).power_mode
+            }
+        }
+    }
+
+    /// Powers the output on or off.
+    ///
+    /// Unlike `enable` this is meant to temporarily turn off the display
+    /// (e.g. when the user is idle) without the compositor having to forget
+    /// about the output. Disabling the output using `enable` takes precedence
+    /// over powering it on.
+    pub fn set_power_mode(&mut self, power_mode: PowerMode) -> bool {
+        unsafe {
+            let user_data = self.state();
+            if user_data.is_null() {
+                return false;
+            }
+            (*user_data).power_mode = power_mode;
+            wlr_output_enable(
+                self.output.as_ptr(),
+                (*user_data).enabled && power_mode == PowerMode::On
+            )
+        }
     }
 
     /// Sets the gamma based on the size.
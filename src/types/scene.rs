@@ -0,0 +1,917 @@
+//! A retained scene graph for compositors that don't want to walk their
+//! surfaces by hand every frame.
+//!
+//! A `Scene` is a tree of nodes. Each node has a position relative to its
+//! parent and can be disabled to hide it and everything below it. Tree nodes
+//! only group other nodes, the other kinds draw something:
+//!
+//! * surface nodes draw a surface along with its subsurfaces,
+//! * XDG surface nodes draw an XDG surface along with its subsurfaces and
+//!   popups,
+//! * rectangle nodes draw a solid color,
+//! * buffer nodes draw a texture created by the compositor.
+//!
+//! Children are drawn above their parent, and later children above earlier
+//! ones. The positions are in output layout coordinates, so render the scene
+//! once per output with `Scene::render_output`.
+//!
+//! The scene damages the outputs of the layout it was made with whenever
+//! something in it changes, including when a client commits new contents for
+//! one of its surfaces.
+
+use std::{cell::RefCell, rc::Rc};
+
+use crate::libc;
+use crate::wayland_sys::server::{signal::wl_signal_add, WAYLAND_SERVER_HANDLE};
+use wlroots_sys::{wl_output_transform::WL_OUTPUT_TRANSFORM_NORMAL, wlr_surface, wlr_texture_destroy};
+
+use crate::{
+    area::{Area, IntersectionResult, Origin, Size},
+    output::{self, layout, Output},
+    render::{matrix, Renderer, Texture},
+    shell::{xdg_shell, xdg_shell_v6},
+    surface,
+    utils::{HandleErr, HandleResult, Handleable}
+};
+
+/// The index of the root node, which can never be removed.
+const ROOT: usize = 0;
+
+/// Identifies a node in a `Scene`.
+///
+/// Ids of removed nodes are never reused, using one after its node has been
+/// removed returns `HandleErr::AlreadyDropped`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct NodeId {
+    index: usize,
+    generation: u32
+}
+
+/// What a node draws.
+#[derive(Debug)]
+enum Content {
+    Tree,
+    Surface(surface::Handle),
+    XdgSurface(xdg_shell::Handle),
+    XdgV6Surface(xdg_shell_v6::Handle),
+    Rect { size: Size, color: [f32; 4] },
+    Buffer { texture: Texture<'static>, size: Size }
+}
+
+struct Node {
+    parent: Option<usize>,
+    children: Vec<usize>,
+    position: Origin,
+    enabled: bool,
+    content: Content,
+    /// Listeners on the surfaces drawn by this node, used to damage the
+    /// outputs when they change.
+    listeners: Vec<Box<SurfaceListener>>
+}
+
+struct Slot {
+    generation: u32,
+    node: Option<Node>
+}
+
+/// Damages the outputs of a layout, given areas in layout coordinates.
+struct DamageTracker {
+    layout: layout::Handle,
+    /// The outputs as of the last time the layout could be borrowed.
+    outputs: RefCell<Vec<output::Handle>>,
+    /// Damage for outputs that were borrowed when it was added.
+    deferred: RefCell<Vec<(output::Handle, Area)>>
+}
+
+struct SurfaceDamage {
+    tracker: Rc<DamageTracker>,
+    surface: *mut wlr_surface,
+    /// Where the surface was last drawn, in layout coordinates.
+    area: Area,
+    alive: bool
+}
+
+wayland_listener!(SurfaceListener, SurfaceDamage, [
+    commit_listener => commit_notify: |this: &mut SurfaceListener, _data: *mut libc::c_void,| unsafe {
+        let surface = this.data.surface;
+        let old_area = this.data.area;
+        let size = Size::new((*surface).current.width, (*surface).current.height);
+        this.data.area = old_area.with_size(size);
+        this.data.tracker.add(old_area);
+        this.data.tracker.add(this.data.area);
+    };
+    destroy_listener => destroy_notify: |this: &mut SurfaceListener, _data: *mut libc::c_void,| unsafe {
+        this.data.tracker.add(this.data.area);
+        this.data.alive = false;
+        ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                      wl_list_remove,
+                      &mut (*this.commit_listener()).link as *mut _ as _);
+        ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                      wl_list_remove,
+                      &mut (*this.destroy_listener()).link as *mut _ as _);
+    };
+]);
+
+impl Drop for SurfaceListener {
+    fn drop(&mut self) {
+        if !self.data.alive {
+            return;
+        }
+        unsafe {
+            ffi_dispatch!(
+                WAYLAND_SERVER_HANDLE,
+                wl_list_remove,
+                &mut (*self.commit_listener()).link as *mut _ as _
+            );
+            ffi_dispatch!(
+                WAYLAND_SERVER_HANDLE,
+                wl_list_remove,
+                &mut (*self.destroy_listener()).link as *mut _ as _
+            );
+        }
+    }
+}
+
+/// A tree of things to draw on the outputs of an output layout.
+///
+/// The textures of buffer nodes are destroyed when the scene is dropped, so
+/// it must be dropped before the renderer is.
+pub struct Scene {
+    nodes: Vec<Slot>,
+    free: Vec<usize>,
+    damage: Rc<DamageTracker>
+}
+
+impl Scene {
+    /// Makes an empty scene that damages the outputs of the given layout.
+    pub fn new(layout: layout::Handle) -> Self {
+        let root = Node {
+            parent: None,
+            children: Vec::new(),
+            position: Origin::default(),
+            enabled: true,
+            content: Content::Tree,
+            listeners: Vec::new()
+        };
+        Scene {
+            nodes: vec![Slot {
+                generation: 0,
+                node: Some(root)
+            }],
+            free: Vec::new(),
+            damage: Rc::new(DamageTracker {
+                layout,
+                outputs: RefCell::new(Vec::new()),
+                deferred: RefCell::new(Vec::new())
+            })
+        }
+    }
+
+    /// Gets the root of the scene, which every other node descends from.
+    pub fn root(&self) -> NodeId {
+        NodeId {
+            index: ROOT,
+            generation: self.nodes[ROOT].generation
+        }
+    }
+
+    /// Adds a node that only groups other nodes.
+    pub fn add_tree(&mut self, parent: NodeId) -> HandleResult<NodeId> {
+        self.add(parent, Content::Tree)
+    }
+
+    /// Adds a node drawing a surface and its subsurfaces.
+    pub fn add_surface(&mut self, parent: NodeId, surface: surface::Handle) -> HandleResult<NodeId> {
+        self.add(parent, Content::Surface(surface))
+    }
+
+    /// Adds a node drawing an XDG surface, its subsurfaces and its popups.
+    ///
+    /// Nothing is drawn while the XDG surface is unmapped.
+    pub fn add_xdg_surface(&mut self, parent: NodeId, shell: xdg_shell::Handle) -> HandleResult<NodeId> {
+        self.add(parent, Content::XdgSurface(shell))
+    }
+
+    /// Adds a node drawing an XDG v6 surface, its subsurfaces and its popups.
+    ///
+    /// Nothing is drawn while the XDG surface is unmapped.
+    pub fn add_xdg_v6_surface(
+        &mut self,
+        parent: NodeId,
+        shell: xdg_shell_v6::Handle
+    ) -> HandleResult<NodeId> {
+        self.add(parent, Content::XdgV6Surface(shell))
+    }
+
+    /// Adds a node drawing a solid rectangle.
+    pub fn add_rect(&mut self, parent: NodeId, size: Size, color: [f32; 4]) -> HandleResult<NodeId> {
+        self.add(parent, Content::Rect { size, color })
+    }
+
+    /// Adds a node drawing a texture at its natural size.
+    ///
+    /// The scene takes ownership of the texture, it is handed back when the
+    /// node is removed so it can be given to `GenericRenderer::drop_texture`.
+    pub fn add_buffer(&mut self, parent: NodeId, texture: Texture<'static>) -> HandleResult<NodeId> {
+        let (width, height) = texture.size();
+        self.add(
+            parent,
+            Content::Buffer {
+                texture,
+                size: Size::new(width, height)
+            }
+        )
+    }
+
+    /// Removes a node and everything below it.
+    ///
+    /// Returns the textures of the removed buffer nodes. They should be
+    /// dropped using `GenericRenderer::drop_texture`.
+    ///
+    /// Removing the root only removes its children.
+    pub fn remove(&mut self, node: NodeId) -> HandleResult<Vec<Texture<'static>>> {
+        let index = self.index(node)?;
+        self.damage_subtree(index);
+        let mut textures = Vec::new();
+        if index == ROOT {
+            let children = self.node_mut(ROOT).children.drain(..).collect::<Vec<_>>();
+            for child in children {
+                self.free_subtree(child, &mut textures);
+            }
+        } else {
+            if let Some(parent) = self.node(index).parent {
+                self.node_mut(parent).children.retain(|&child| child != index);
+            }
+            self.free_subtree(index, &mut textures);
+        }
+        Ok(textures)
+    }
+
+    /// Gets the position of the node relative to its parent.
+    pub fn position(&self, node: NodeId) -> HandleResult<Origin> {
+        let index = self.index(node)?;
+        Ok(self.node(index).position)
+    }
+
+    /// Gets the position of the node in output layout coordinates.
+    pub fn layout_position(&self, node: NodeId) -> HandleResult<Origin> {
+        let index = self.index(node)?;
+        Ok(self.absolute_origin(index))
+    }
+
+    /// Moves the node, relative to its parent.
+    pub fn set_position(&mut self, node: NodeId, position: Origin) -> HandleResult<()> {
+        let index = self.index(node)?;
+        if self.node(index).position == position {
+            return Ok(());
+        }
+        self.damage_subtree(index);
+        self.node_mut(index).position = position;
+        self.damage_subtree(index);
+        Ok(())
+    }
+
+    /// Determines if the node is enabled.
+    ///
+    /// A node is only drawn if it and all of its ancestors are enabled.
+    pub fn enabled(&self, node: NodeId) -> HandleResult<bool> {
+        let index = self.index(node)?;
+        Ok(self.node(index).enabled)
+    }
+
+    /// Enables or disables the node, which shows or hides it and everything
+    /// below it.
+    pub fn set_enabled(&mut self, node: NodeId, enabled: bool) -> HandleResult<()> {
+        let index = self.index(node)?;
+        if self.node(index).enabled == enabled {
+            return Ok(());
+        }
+        if enabled {
+            self.node_mut(index).enabled = true;
+            self.damage_subtree(index);
+        } else {
+            self.damage_subtree(index);
+            self.node_mut(index).enabled = false;
+        }
+        Ok(())
+    }
+
+    /// Changes the size and color of a rectangle node.
+    ///
+    /// Does nothing if the node is not a rectangle.
+    pub fn set_rect(&mut self, node: NodeId, size: Size, color: [f32; 4]) -> HandleResult<()> {
+        let index = self.index(node)?;
+        match self.node(index).content {
+            Content::Rect { .. } => {},
+            _ => return Ok(())
+        }
+        self.damage_subtree(index);
+        self.node_mut(index).content = Content::Rect { size, color };
+        self.damage_subtree(index);
+        Ok(())
+    }
+
+    /// Draws the node above all of its siblings.
+    pub fn raise_to_top(&mut self, node: NodeId) -> HandleResult<()> {
+        let index = self.index(node)?;
+        self.restack(index, |siblings| siblings.len())
+    }
+
+    /// Draws the node below all of its siblings.
+    pub fn lower_to_bottom(&mut self, node: NodeId) -> HandleResult<()> {
+        let index = self.index(node)?;
+        self.restack(index, |_| 0)
+    }
+
+    /// Draws the node right above one of its siblings.
+    ///
+    /// Does nothing if the nodes don't have the same parent.
+    pub fn place_above(&mut self, node: NodeId, sibling: NodeId) -> HandleResult<()> {
+        let (index, sibling) = (self.index(node)?, self.index(sibling)?);
+        if index == sibling || self.node(index).parent != self.node(sibling).parent {
+            return Ok(());
+        }
+        self.restack(index, |siblings| {
+            siblings
+                .iter()
+                .position(|&child| child == sibling)
+                .map(|pos| pos + 1)
+                .unwrap_or(0)
+        })
+    }
+
+    /// Draws the node right below one of its siblings.
+    ///
+    /// Does nothing if the nodes don't have the same parent.
+    pub fn place_below(&mut self, node: NodeId, sibling: NodeId) -> HandleResult<()> {
+        let (index, sibling) = (self.index(node)?, self.index(sibling)?);
+        if index == sibling || self.node(index).parent != self.node(sibling).parent {
+            return Ok(());
+        }
+        self.restack(index, |siblings| {
+            siblings.iter().position(|&child| child == sibling).unwrap_or(0)
+        })
+    }
+
+    /// Moves the node, and everything below it, to a new parent.
+    ///
+    /// The node is drawn above its new siblings. Its position stays
+    /// relative to its parent, so it moves along with the new parent.
+    ///
+    /// Does nothing if the node is the root or if the new parent is the
+    /// node itself or one of its descendants.
+    pub fn reparent(&mut self, node: NodeId, parent: NodeId) -> HandleResult<()> {
+        let (index, new_parent) = (self.index(node)?, self.index(parent)?);
+        if index == ROOT || self.is_ancestor(index, new_parent) {
+            wlr_log!(WLR_ERROR, "Can't move a scene node below itself");
+            return Ok(());
+        }
+        if self.node(index).parent == Some(new_parent) {
+            return Ok(());
+        }
+        self.damage_subtree(index);
+        if let Some(old_parent) = self.node(index).parent {
+            self.node_mut(old_parent).children.retain(|&child| child != index);
+        }
+        self.node_mut(new_parent).children.push(index);
+        self.node_mut(index).parent = Some(new_parent);
+        self.damage_subtree(index);
+        Ok(())
+    }
+
+    /// Damages everything drawn by the scene on every output.
+    pub fn damage_whole(&mut self) {
+        self.damage_subtree(ROOT);
+    }
+
+    /// Draws the scene on the output being rendered to.
+    ///
+    /// This does not clear the output or send frame done events, the
+    /// compositor is expected to do both itself.
+    pub fn render_output(&mut self, renderer: &mut Renderer) {
+        self.damage.flush_output(renderer.output);
+        self.damage.flush();
+        let output_box = output_box(renderer.output);
+        let transform_matrix = renderer.output.transform_matrix();
+        for (index, origin) in self.visible_nodes(ROOT) {
+            match self.node(index).content {
+                Content::Tree => {},
+                Content::Rect { size, color } => {
+                    let area = Area::new(origin, size);
+                    if intersects(area, output_box) {
+                        let area = renderer.output.layout_area_to_buffer(area);
+                        renderer.render_colored_rect(area, color, transform_matrix);
+                    }
+                },
+                Content::Buffer { ref texture, size } => {
+                    let area = Area::new(origin, size);
+                    if intersects(area, output_box) {
+                        let area = renderer.output.layout_area_to_buffer(area);
+                        let matrix =
+                            matrix::project_box(area, WL_OUTPUT_TRANSFORM_NORMAL, 0.0, transform_matrix);
+                        renderer.render_texture_with_matrix(texture, matrix);
+                    }
+                },
+                _ => {
+                    for (surface_handle, area) in self.update_surfaces(index, origin) {
+                        if !intersects(area, output_box) {
+                            continue;
+                        }
+                        let area = renderer.output.layout_area_to_buffer(area);
+                        surface_handle
+                            .run(|surface| {
+                                let transform = surface.current_state().transform().invert();
+                                let matrix = matrix::project_box(area, transform, 0.0, transform_matrix);
+                                if let Some(texture) = surface.texture() {
+                                    renderer.render_texture_with_matrix(&texture, matrix);
+                                }
+                            })
+                            .ok();
+                    }
+                },
+            }
+        }
+    }
+
+    /// Finds the topmost surface accepting input at the given point in
+    /// output layout coordinates.
+    ///
+    /// Return value is the surface and the point in surface-local coordinates.
+    pub fn surface_at(&mut self, lx: f64, ly: f64) -> Option<(surface::Handle, f64, f64)> {
+        for (index, origin) in self.visible_nodes(ROOT).into_iter().rev() {
+            if !is_surface(&self.node(index).content) {
+                continue;
+            }
+            for (surface_handle, area) in self.update_surfaces(index, origin).into_iter().rev() {
+                let (sx, sy) = (lx - area.origin.x as f64, ly - area.origin.y as f64);
+                let accepts_input = surface_handle
+                    .run(|surface| surface.accepts_input(sx, sy))
+                    .unwrap_or(false);
+                if accepts_input {
+                    return Some((surface_handle, sx, sy));
+                }
+            }
+        }
+        None
+    }
+
+    /// Finds the topmost node drawing something at the given point in
+    /// output layout coordinates.
+    ///
+    /// Return value is the node and the point relative to the node's position.
+    pub fn node_at(&mut self, lx: f64, ly: f64) -> Option<(NodeId, f64, f64)> {
+        for (index, origin) in self.visible_nodes(ROOT).into_iter().rev() {
+            let hit = match self.node(index).content {
+                Content::Tree => false,
+                Content::Rect { size, .. } | Content::Buffer { size, .. } => {
+                    Area::new(origin, size).contains_point(lx, ly)
+                },
+                _ => self
+                    .update_surfaces(index, origin)
+                    .into_iter()
+                    .any(|(_, area)| area.contains_point(lx, ly))
+            };
+            if hit {
+                let node = NodeId {
+                    index,
+                    generation: self.nodes[index].generation
+                };
+                return Some((node, lx - origin.x as f64, ly - origin.y as f64));
+            }
+        }
+        None
+    }
+
+    fn add(&mut self, parent: NodeId, content: Content) -> HandleResult<NodeId> {
+        let parent = self.index(parent)?;
+        let node = Node {
+            parent: Some(parent),
+            children: Vec::new(),
+            position: Origin::default(),
+            enabled: true,
+            content,
+            listeners: Vec::new()
+        };
+        let index = match self.free.pop() {
+            Some(index) => {
+                self.nodes[index].node = Some(node);
+                index
+            },
+            None => {
+                self.nodes.push(Slot {
+                    generation: 0,
+                    node: Some(node)
+                });
+                self.nodes.len() - 1
+            }
+        };
+        self.node_mut(parent).children.push(index);
+        self.damage_subtree(index);
+        Ok(NodeId {
+            index,
+            generation: self.nodes[index].generation
+        })
+    }
+
+    fn index(&self, node: NodeId) -> HandleResult<usize> {
+        match self.nodes.get(node.index) {
+            Some(slot) if slot.generation == node.generation && slot.node.is_some() => Ok(node.index),
+            _ => Err(HandleErr::AlreadyDropped)
+        }
+    }
+
+    fn node(&self, index: usize) -> &Node {
+        self.nodes[index].node.as_ref().expect("Scene node was freed")
+    }
+
+    fn node_mut(&mut self, index: usize) -> &mut Node {
+        self.nodes[index].node.as_mut().expect("Scene node was freed")
+    }
+
+    fn free_subtree(&mut self, index: usize, textures: &mut Vec<Texture<'static>>) {
+        let slot = &mut self.nodes[index];
+        let node = slot.node.take().expect("Scene node was freed");
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free.push(index);
+        if let Content::Buffer { texture, .. } = node.content {
+            textures.push(texture);
+        }
+        for child in node.children {
+            self.free_subtree(child, textures);
+        }
+    }
+
+    /// Determines if `ancestor` is `index` or one of its ancestors.
+    fn is_ancestor(&self, ancestor: usize, mut index: usize) -> bool {
+        loop {
+            if index == ancestor {
+                return true;
+            }
+            match self.node(index).parent {
+                Some(parent) => index = parent,
+                None => return false
+            }
+        }
+    }
+
+    fn absolute_origin(&self, mut index: usize) -> Origin {
+        let mut origin = Origin::default();
+        loop {
+            let node = self.node(index);
+            origin.x += node.position.x;
+            origin.y += node.position.y;
+            match node.parent {
+                Some(parent) => index = parent,
+                None => return origin
+            }
+        }
+    }
+
+    fn is_visible(&self, mut index: usize) -> bool {
+        loop {
+            let node = self.node(index);
+            if !node.enabled {
+                return false;
+            }
+            match node.parent {
+                Some(parent) => index = parent,
+                None => return true
+            }
+        }
+    }
+
+    /// Gets the visible nodes below `index` (including itself) in the order
+    /// they are drawn, along with their positions in layout coordinates.
+    fn visible_nodes(&self, index: usize) -> Vec<(usize, Origin)> {
+        let mut nodes = Vec::new();
+        if self.is_visible(index) {
+            self.collect_visible(index, self.absolute_origin(index), &mut nodes);
+        }
+        nodes
+    }
+
+    fn collect_visible(&self, index: usize, origin: Origin, nodes: &mut Vec<(usize, Origin)>) {
+        nodes.push((index, origin));
+        for &child in &self.node(index).children {
+            let node = self.node(child);
+            if node.enabled {
+                let origin = Origin::new(origin.x + node.position.x, origin.y + node.position.y);
+                self.collect_visible(child, origin, nodes);
+            }
+        }
+    }
+
+    fn restack<F>(&mut self, index: usize, position: F) -> HandleResult<()>
+    where
+        F: FnOnce(&[usize]) -> usize
+    {
+        let parent = match self.node(index).parent {
+            Some(parent) => parent,
+            None => return Ok(())
+        };
+        {
+            let siblings = &mut self.node_mut(parent).children;
+            siblings.retain(|&child| child != index);
+            let position = position(siblings).min(siblings.len());
+            siblings.insert(position, index);
+        }
+        self.damage_subtree(index);
+        Ok(())
+    }
+
+    /// Damages everything drawn by the node and its descendants.
+    fn damage_subtree(&mut self, index: usize) {
+        for (index, origin) in self.visible_nodes(index) {
+            match self.node(index).content {
+                Content::Tree => {},
+                Content::Rect { size, .. } | Content::Buffer { size, .. } => {
+                    self.damage.add(Area::new(origin, size))
+                },
+                _ => {
+                    for (_, area) in self.update_surfaces(index, origin) {
+                        self.damage.add(area)
+                    }
+                },
+            }
+        }
+    }
+
+    /// Gets the surfaces drawn by a surface node, bottom to top, with their
+    /// areas in layout coordinates.
+    ///
+    /// This also keeps the node's listeners in sync with its surfaces.
+    fn update_surfaces(&mut self, index: usize, origin: Origin) -> Vec<(surface::Handle, Area)> {
+        let surfaces = surfaces(&self.node(index).content, origin);
+        let tracker = self.damage.clone();
+        let node = self.node_mut(index);
+        node.listeners.retain(|listener| {
+            listener.data.alive &&
+                surfaces
+                    .iter()
+                    .any(|(surface_handle, _)| unsafe { surface_handle.as_ptr() } == listener.data.surface)
+        });
+        for &(ref surface_handle, area) in &surfaces {
+            let surface_ptr = unsafe { surface_handle.as_ptr() };
+            if let Some(listener) = node
+                .listeners
+                .iter_mut()
+                .find(|listener| listener.data.surface == surface_ptr)
+            {
+                listener.data.area = area;
+                continue;
+            }
+            let mut listener = SurfaceListener::new(SurfaceDamage {
+                tracker: tracker.clone(),
+                surface: surface_ptr,
+                area,
+                alive: true
+            });
+            unsafe {
+                wl_signal_add(
+                    &mut (*surface_ptr).events.commit as *mut _ as _,
+                    listener.commit_listener() as _
+                );
+                wl_signal_add(
+                    &mut (*surface_ptr).events.destroy as *mut _ as _,
+                    listener.destroy_listener() as _
+                );
+            }
+            node.listeners.push(listener);
+        }
+        surfaces
+    }
+}
+
+impl Drop for Scene {
+    fn drop(&mut self) {
+        for slot in &mut self.nodes {
+            if let Some(Node {
+                content: Content::Buffer { ref texture, .. },
+                ..
+            }) = slot.node
+            {
+                unsafe { wlr_texture_destroy(texture.as_ptr()) }
+            }
+        }
+    }
+}
+
+impl DamageTracker {
+    fn add(&self, area: Area) {
+        if area.size.width <= 0 || area.size.height <= 0 {
+            return;
+        }
+        if let Ok(outputs) = self.layout.run(|layout| layout.outputs()) {
+            *self.outputs.borrow_mut() = outputs.into_iter().map(|(output, _)| output).collect();
+        }
+        let outputs = self.outputs.borrow().clone();
+        for output_handle in outputs {
+            self.add_to_output(output_handle, area)
+        }
+    }
+
+    fn add_to_output(&self, output_handle: output::Handle, area: Area) {
+        match output_handle.run(|output| damage_output(output, area)) {
+            Ok(()) | Err(HandleErr::AlreadyDropped) => {},
+            Err(HandleErr::AlreadyBorrowed) => self.deferred.borrow_mut().push((output_handle, area))
+        }
+    }
+
+    /// Retries the damage that was deferred because its output was borrowed.
+    fn flush(&self) {
+        let deferred = self.deferred.replace(Vec::new());
+        for (output_handle, area) in deferred {
+            self.add_to_output(output_handle, area)
+        }
+    }
+
+    /// Applies the damage deferred for an output that is already borrowed.
+    fn flush_output(&self, output: &mut Output) {
+        let handle = output.weak_reference();
+        self.deferred.borrow_mut().retain(|&(ref output_handle, area)| {
+            if *output_handle == handle {
+                damage_output(output, area);
+                false
+            } else {
+                true
+            }
+        });
+    }
+}
+
+/// Gets the surfaces drawn by a node, bottom to top, with their areas in
+/// layout coordinates.
+fn surfaces(content: &Content, origin: Origin) -> Vec<(surface::Handle, Area)> {
+    let mut surfaces = Vec::new();
+    {
+        let mut push = |surface_handle: surface::Handle, sx: i32, sy: i32| {
+            // NOTE The surface being iterated over is borrowed, so the size
+            // is read directly from the (still alive) wlr_surface.
+            let size = unsafe {
+                let surface_ptr = surface_handle.as_ptr();
+                Size::new((*surface_ptr).current.width, (*surface_ptr).current.height)
+            };
+            let area = Area::new(Origin::new(origin.x + sx, origin.y + sy), size);
+            surfaces.push((surface_handle, area));
+        };
+        match *content {
+            Content::Surface(ref surface_handle) => {
+                surface_handle
+                    .run(|surface| surface.for_each_surface(&mut push))
+                    .ok();
+            },
+            Content::XdgSurface(ref shell_handle) => {
+                if unsafe {
+                    shell_handle
+                        .as_ptr()
+                        .as_ref()
+                        .map(|shell| shell.mapped)
+                        .unwrap_or(false)
+                } {
+                    shell_handle.run(|shell| shell.for_each_surface(&mut push)).ok();
+                }
+            },
+            Content::XdgV6Surface(ref shell_handle) => {
+                if unsafe {
+                    shell_handle
+                        .as_ptr()
+                        .as_ref()
+                        .map(|shell| shell.mapped)
+                        .unwrap_or(false)
+                } {
+                    shell_handle.run(|shell| shell.for_each_surface(&mut push)).ok();
+                }
+            },
+            Content::Tree | Content::Rect { .. } | Content::Buffer { .. } => {}
+        }
+    }
+    surfaces
+}
+
+fn is_surface(content: &Content) -> bool {
+    match *content {
+        Content::Surface(_) | Content::XdgSurface(_) | Content::XdgV6Surface(_) => true,
+        Content::Tree | Content::Rect { .. } | Content::Buffer { .. } => false
+    }
+}
+
+fn intersects(area: Area, other: Area) -> bool {
+    match area.intersection(other) {
+        IntersectionResult::Intersection(_) => true,
+        IntersectionResult::NoIntersection => false
+    }
+}
+
+/// Gets the area of the output in layout coordinates.
+fn output_box(output: &Output) -> Area {
+    let (x, y) = output.layout_space_pos();
+    let (width, height) = output.effective_resolution();
+    Area::new(Origin::new(x, y), Size::new(width, height))
+}
+
+fn damage_output(output: &mut Output, area: Area) {
+    if let IntersectionResult::Intersection(area) = area.intersection(output_box(output)) {
+        let area = output.layout_area_to_buffer(area);
+        output.damage().add_area(area);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Makes a scene without any output to damage.
+    fn scene() -> Scene {
+        Scene::new(layout::Handle::new())
+    }
+
+    fn children(scene: &Scene, node: NodeId) -> Vec<usize> {
+        scene.node(scene.index(node).unwrap()).children.clone()
+    }
+
+    #[test]
+    fn restack() {
+        let mut scene = scene();
+        let root = scene.root();
+        let a = scene.add_tree(root).unwrap();
+        let b = scene.add_tree(root).unwrap();
+        let c = scene.add_tree(root).unwrap();
+        let nested = scene.add_tree(a).unwrap();
+        assert_eq!(children(&scene, root), vec![a.index, b.index, c.index]);
+        scene.raise_to_top(a).unwrap();
+        assert_eq!(children(&scene, root), vec![b.index, c.index, a.index]);
+        scene.lower_to_bottom(a).unwrap();
+        assert_eq!(children(&scene, root), vec![a.index, b.index, c.index]);
+        scene.place_above(a, b).unwrap();
+        assert_eq!(children(&scene, root), vec![b.index, a.index, c.index]);
+        scene.place_below(c, b).unwrap();
+        assert_eq!(children(&scene, root), vec![c.index, b.index, a.index]);
+        // Not siblings, nothing changes.
+        scene.place_above(nested, c).unwrap();
+        assert_eq!(children(&scene, root), vec![c.index, b.index, a.index]);
+        scene.raise_to_top(root).unwrap();
+        assert_eq!(children(&scene, root), vec![c.index, b.index, a.index]);
+    }
+
+    #[test]
+    fn reparent() {
+        let mut scene = scene();
+        let root = scene.root();
+        let a = scene.add_tree(root).unwrap();
+        let b = scene.add_tree(root).unwrap();
+        let child = scene.add_tree(a).unwrap();
+        scene.set_position(a, Origin::new(10, 20)).unwrap();
+        scene.set_position(b, Origin::new(100, 200)).unwrap();
+        scene.set_position(child, Origin::new(1, 2)).unwrap();
+        assert_eq!(scene.layout_position(child), Ok(Origin::new(11, 22)));
+        scene.reparent(child, b).unwrap();
+        assert_eq!(children(&scene, a), vec![]);
+        assert_eq!(children(&scene, b), vec![child.index]);
+        assert_eq!(scene.position(child), Ok(Origin::new(1, 2)));
+        assert_eq!(scene.layout_position(child), Ok(Origin::new(101, 202)));
+        // Moving a node below itself or moving the root is refused.
+        scene.reparent(b, child).unwrap();
+        scene.reparent(b, b).unwrap();
+        scene.reparent(root, a).unwrap();
+        assert_eq!(children(&scene, root), vec![a.index, b.index]);
+        assert_eq!(children(&scene, b), vec![child.index]);
+        assert_eq!(children(&scene, a), vec![]);
+    }
+
+    #[test]
+    fn remove() {
+        let mut scene = scene();
+        let root = scene.root();
+        let a = scene.add_tree(root).unwrap();
+        let b = scene.add_rect(root, Size::new(10, 10), [1.0; 4]).unwrap();
+        let child = scene.add_tree(a).unwrap();
+        let grandchild = scene.add_rect(child, Size::new(5, 5), [1.0; 4]).unwrap();
+        assert!(scene.remove(a).unwrap().is_empty());
+        assert_eq!(children(&scene, root), vec![b.index]);
+        for &node in &[a, child, grandchild] {
+            assert_eq!(scene.position(node), Err(HandleErr::AlreadyDropped));
+            assert_eq!(scene.remove(node).map(|_| ()), Err(HandleErr::AlreadyDropped));
+        }
+        assert!(scene.enabled(b).unwrap());
+        // Removing the root only removes its children.
+        scene.remove(root).unwrap();
+        assert_eq!(children(&scene, root), vec![]);
+        assert_eq!(scene.enabled(b), Err(HandleErr::AlreadyDropped));
+        assert!(scene.add_tree(root).is_ok());
+    }
+
+    #[test]
+    fn generation_reuse() {
+        let mut scene = scene();
+        let root = scene.root();
+        let old = scene.add_tree(root).unwrap();
+        scene.remove(old).unwrap();
+        let new = scene.add_tree(root).unwrap();
+        assert_eq!(new.index, old.index);
+        assert_ne!(new, old);
+        assert_eq!(scene.position(old), Err(HandleErr::AlreadyDropped));
+        assert_eq!(scene.add_tree(old), Err(HandleErr::AlreadyDropped));
+        assert_eq!(scene.position(new), Ok(Origin::default()));
+        assert_eq!(children(&scene, root), vec![new.index]);
+    }
+}
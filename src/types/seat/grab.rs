@@ -1,4 +1,53 @@
-use wlroots_sys::{wlr_seat_keyboard_grab, wlr_seat_pointer_grab, wlr_seat_touch_grab};
+//! Grabs of the pointer, keyboard and touch device of a seat.
+//!
+//! While a grab is active the seat gives it the input it's notified of,
+//! instead of sending it to the focused clients. The compositor can make its
+//! own grabs (e.g. to move a window with the pointer) by implementing one of
+//! the handler traits and starting it with `Seat::pointer_start_grab`,
+//! `Seat::keyboard_start_grab` or `Seat::touch_start_grab`:
+//!
+//! ```rust,no_run,ignore
+//! struct MoveGrab {
+//!     view: View
+//! }
+//!
+//! impl grab::PointerHandler for MoveGrab {
+//!     fn motion(&mut self, compositor_handle: compositor::Handle, seat: &Seat,
+//!               time: Duration, sx: f64, sy: f64) {
+//!         self.view.move_to(sx, sy)
+//!     }
+//!
+//!     fn button(&mut self, compositor_handle: compositor::Handle, seat: &Seat,
+//!               time: Duration, button: u32, state: u32) -> u32 {
+//!         seat.pointer_end_grab();
+//!         0
+//!     }
+//! }
+//!
+//! seat.pointer_start_grab(Box::new(MoveGrab { view }));
+//! ```
+//!
+//! The handler is dropped once the grab ends. The `Seat` given to the handler
+//! can be used to send the input on to the clients, e.g. with
+//! `Seat::send_motion`.
+
+use std::{panic, ptr, slice, time::Duration};
+
+use wlroots_sys::{
+    wlr_axis_orientation, wlr_axis_source, wlr_keyboard_grab_interface, wlr_keyboard_modifiers,
+    wlr_pointer_grab_interface, wlr_seat, wlr_seat_keyboard_grab, wlr_seat_pointer_grab, wlr_seat_touch_grab,
+    wlr_surface, wlr_touch_grab_interface, wlr_touch_point
+};
+use xkbcommon::xkb::Keycode;
+
+use crate::{
+    compositor,
+    input::keyboard,
+    libc::{c_void, size_t},
+    seat::{Seat, TouchPoint},
+    surface::Surface,
+    utils::{self, Handleable}
+};
 
 pub struct Pointer {
     grab: *mut wlr_seat_pointer_grab
@@ -12,6 +61,199 @@ pub struct Touch {
     grab: *mut wlr_seat_touch_grab
 }
 
+/// Handles the pointer input of a seat while it's grabbed.
+#[allow(unused_variables)]
+pub trait PointerHandler {
+    /// Called when the pointer enters a surface, e.g. through
+    /// `Seat::pointer_notify_enter`.
+    ///
+    /// `sx` and `sy` are surface-local.
+    fn enter(
+        &mut self,
+        compositor_handle: compositor::Handle,
+        seat: &Seat,
+        surface: &mut Surface,
+        sx: f64,
+        sy: f64
+    ) {
+    }
+
+    /// Called when the pointer moves, through `Seat::pointer_notify_motion`.
+    ///
+    /// `sx` and `sy` are local to the focused surface.
+    fn motion(
+        &mut self,
+        compositor_handle: compositor::Handle,
+        seat: &Seat,
+        time: Duration,
+        sx: f64,
+        sy: f64
+    ) {
+    }
+
+    /// Called when a button is pressed or released, through
+    /// `Seat::pointer_notify_button`.
+    ///
+    /// Returns the serial of the event sent to the client, if any.
+    fn button(
+        &mut self,
+        compositor_handle: compositor::Handle,
+        seat: &Seat,
+        time: Duration,
+        button: u32,
+        state: u32
+    ) -> u32 {
+        0
+    }
+
+    /// Called on scroll events, through `Seat::pointer_notify_axis`.
+    fn axis(
+        &mut self,
+        compositor_handle: compositor::Handle,
+        seat: &Seat,
+        time: Duration,
+        orientation: wlr_axis_orientation,
+        value: f64,
+        value_discrete: i32,
+        source: wlr_axis_source
+    ) {
+    }
+
+    /// Called when the grab ends, right before the handler is dropped.
+    ///
+    /// This is not called if the grab is ended from one of the handler's own
+    /// callbacks.
+    fn cancel(&mut self, compositor_handle: compositor::Handle, seat: &Seat) {}
+}
+
+/// Handles the keyboard input of a seat while it's grabbed.
+#[allow(unused_variables)]
+pub trait KeyboardHandler {
+    /// Called when the keyboard enters a surface, e.g. through
+    /// `Seat::keyboard_notify_enter`.
+    fn enter(
+        &mut self,
+        compositor_handle: compositor::Handle,
+        seat: &Seat,
+        surface: &mut Surface,
+        keycodes: &[Keycode],
+        modifiers: &keyboard::Modifiers
+    ) {
+    }
+
+    /// Called when a key is pressed or released, through
+    /// `Seat::keyboard_notify_key`.
+    fn key(
+        &mut self,
+        compositor_handle: compositor::Handle,
+        seat: &Seat,
+        time: Duration,
+        key: u32,
+        state: u32
+    ) {
+    }
+
+    /// Called when the modifiers change, through
+    /// `Seat::keyboard_notify_modifiers`.
+    fn modifiers(
+        &mut self,
+        compositor_handle: compositor::Handle,
+        seat: &Seat,
+        modifiers: &keyboard::Modifiers
+    ) {
+    }
+
+    /// Called when the grab ends, right before the handler is dropped.
+    ///
+    /// This is not called if the grab is ended from one of the handler's own
+    /// callbacks.
+    fn cancel(&mut self, compositor_handle: compositor::Handle, seat: &Seat) {}
+}
+
+/// Handles the touch input of a seat while it's grabbed.
+#[allow(unused_variables)]
+pub trait TouchHandler {
+    /// Called when a touch point is put down, through
+    /// `Seat::touch_notify_down`.
+    ///
+    /// Returns the serial of the event sent to the client, if any.
+    fn down(
+        &mut self,
+        compositor_handle: compositor::Handle,
+        seat: &Seat,
+        time: Duration,
+        point: &TouchPoint
+    ) -> u32 {
+        0
+    }
+
+    /// Called when a touch point is lifted, through `Seat::touch_notify_up`.
+    fn up(&mut self, compositor_handle: compositor::Handle, seat: &Seat, time: Duration, point: &TouchPoint) {
+    }
+
+    /// Called when a touch point moves, through `Seat::touch_notify_motion`.
+    fn motion(
+        &mut self,
+        compositor_handle: compositor::Handle,
+        seat: &Seat,
+        time: Duration,
+        point: &TouchPoint
+    ) {
+    }
+
+    /// Called when a touch point enters a surface, through
+    /// `Seat::touch_point_focus`.
+    fn enter(
+        &mut self,
+        compositor_handle: compositor::Handle,
+        seat: &Seat,
+        time: Duration,
+        point: &TouchPoint
+    ) {
+    }
+
+    /// Called when the grab ends, right before the handler is dropped.
+    ///
+    /// This is not called if the grab is ended from one of the handler's own
+    /// callbacks.
+    fn cancel(&mut self, compositor_handle: compositor::Handle, seat: &Seat) {}
+}
+
+/// A grab implemented by a handler, stored in the user data of its wlroots
+/// grab.
+struct Custom<G, H: ?Sized> {
+    grab: G,
+    handler: Box<H>,
+    /// Whether one of the handler's callbacks is running.
+    running: bool,
+    /// Set when the grab ends while one of the handler's callbacks is
+    /// running, so it's freed once that callback returns.
+    ended: bool
+}
+
+static POINTER_INTERFACE: wlr_pointer_grab_interface = wlr_pointer_grab_interface {
+    enter: Some(pointer_enter),
+    motion: Some(pointer_motion),
+    button: Some(pointer_button),
+    axis: Some(pointer_axis),
+    cancel: Some(pointer_cancel)
+};
+
+static KEYBOARD_INTERFACE: wlr_keyboard_grab_interface = wlr_keyboard_grab_interface {
+    enter: Some(keyboard_enter),
+    key: Some(keyboard_key),
+    modifiers: Some(keyboard_modifiers),
+    cancel: Some(keyboard_cancel)
+};
+
+static TOUCH_INTERFACE: wlr_touch_grab_interface = wlr_touch_grab_interface {
+    down: Some(touch_down),
+    up: Some(touch_up),
+    motion: Some(touch_motion),
+    enter: Some(touch_enter),
+    cancel: Some(touch_cancel)
+};
+
 #[allow(dead_code)]
 impl Pointer {
     pub(crate) unsafe fn as_ptr(&self) -> *mut wlr_seat_pointer_grab {
@@ -21,6 +263,20 @@ impl Pointer {
     pub(crate) unsafe fn from_ptr(grab: *mut wlr_seat_pointer_grab) -> Self {
         Pointer { grab }
     }
+
+    /// Makes a grab calling the handler, which is freed when it's cancelled.
+    pub(crate) unsafe fn from_handler(handler: Box<PointerHandler>) -> Self {
+        let grab = wlr_seat_pointer_grab {
+            interface: &POINTER_INTERFACE,
+            seat: ptr::null_mut(),
+            data: ptr::null_mut()
+        };
+        let custom = Box::into_raw(Box::new(Custom::new(grab, handler)));
+        (*custom).grab.data = custom as *mut c_void;
+        Pointer {
+            grab: &mut (*custom).grab
+        }
+    }
 }
 
 #[allow(dead_code)]
@@ -32,6 +288,20 @@ impl Keyboard {
     pub(crate) unsafe fn from_ptr(grab: *mut wlr_seat_keyboard_grab) -> Self {
         Keyboard { grab }
     }
+
+    /// Makes a grab calling the handler, which is freed when it's cancelled.
+    pub(crate) unsafe fn from_handler(handler: Box<KeyboardHandler>) -> Self {
+        let grab = wlr_seat_keyboard_grab {
+            interface: &KEYBOARD_INTERFACE,
+            seat: ptr::null_mut(),
+            data: ptr::null_mut()
+        };
+        let custom = Box::into_raw(Box::new(Custom::new(grab, handler)));
+        (*custom).grab.data = custom as *mut c_void;
+        Keyboard {
+            grab: &mut (*custom).grab
+        }
+    }
 }
 
 #[allow(dead_code)]
@@ -43,4 +313,285 @@ impl Touch {
     pub(crate) unsafe fn from_ptr(grab: *mut wlr_seat_touch_grab) -> Self {
         Touch { grab }
     }
+
+    /// Makes a grab calling the handler, which is freed when it's cancelled.
+    pub(crate) unsafe fn from_handler(handler: Box<TouchHandler>) -> Self {
+        let grab = wlr_seat_touch_grab {
+            interface: &TOUCH_INTERFACE,
+            seat: ptr::null_mut(),
+            data: ptr::null_mut()
+        };
+        let custom = Box::into_raw(Box::new(Custom::new(grab, handler)));
+        (*custom).grab.data = custom as *mut c_void;
+        Touch {
+            grab: &mut (*custom).grab
+        }
+    }
+}
+
+impl<G, H: ?Sized> Custom<G, H> {
+    fn new(grab: G, handler: Box<H>) -> Self {
+        Custom {
+            grab,
+            handler,
+            running: false,
+            ended: false
+        }
+    }
+}
+
+/// Calls the handler of the custom grab stored in `data`, returning
+/// `default` if it could not be called.
+unsafe fn call<G, H: ?Sized, R, F>(data: *mut c_void, seat: *mut wlr_seat, default: R, callback: F) -> R
+where
+    F: FnOnce(&mut H, compositor::Handle, &Seat) -> R
+{
+    let custom = data as *mut Custom<G, H>;
+    if (*custom).running {
+        wlr_log!(WLR_ERROR, "Seat grab was called recursively");
+        return default;
+    }
+    let compositor = match compositor::handle() {
+        Some(handle) => handle,
+        None => return default
+    };
+    let seat = match Seat::from_wlr_seat(seat) {
+        Some(seat) => seat,
+        None => return default
+    };
+    (*custom).running = true;
+    let handler = &mut *(*custom).handler;
+    let res = panic::catch_unwind(panic::AssertUnwindSafe(|| callback(handler, compositor, seat)));
+    (*custom).running = false;
+    if (*custom).ended {
+        drop(Box::from_raw(custom))
+    }
+    match res {
+        Ok(res) => res,
+        Err(err) => {
+            utils::handle_unwind::<()>(Err(err));
+            default
+        }
+    }
+}
+
+/// Cancels the custom grab stored in `data` and frees it, or marks it to be
+/// freed if one of its callbacks is running.
+unsafe fn cancel<G, H: ?Sized, F>(data: *mut c_void, seat: *mut wlr_seat, cancel: F)
+where
+    F: FnOnce(&mut H, compositor::Handle, &Seat)
+{
+    let custom = data as *mut Custom<G, H>;
+    if (*custom).running {
+        (*custom).ended = true;
+        return;
+    }
+    call::<G, H, _, _>(data, seat, (), cancel);
+    drop(Box::from_raw(custom))
+}
+
+unsafe fn surface_from_ptr(surface: *mut wlr_surface) -> Option<Surface> {
+    if surface.is_null() {
+        None
+    } else {
+        Surface::from_ptr(surface)
+    }
+}
+
+fn duration(time_msec: u32) -> Duration {
+    Duration::from_millis(u64::from(time_msec))
+}
+
+unsafe extern "C" fn pointer_enter(
+    grab: *mut wlr_seat_pointer_grab,
+    surface: *mut wlr_surface,
+    sx: f64,
+    sy: f64
+) {
+    let mut surface = match surface_from_ptr(surface) {
+        Some(surface) => surface,
+        None => return
+    };
+    call::<wlr_seat_pointer_grab, PointerHandler, _, _>(
+        (*grab).data,
+        (*grab).seat,
+        (),
+        |handler, compositor, seat| handler.enter(compositor, seat, &mut surface, sx, sy)
+    )
+}
+
+unsafe extern "C" fn pointer_motion(grab: *mut wlr_seat_pointer_grab, time_msec: u32, sx: f64, sy: f64) {
+    call::<wlr_seat_pointer_grab, PointerHandler, _, _>(
+        (*grab).data,
+        (*grab).seat,
+        (),
+        |handler, compositor, seat| handler.motion(compositor, seat, duration(time_msec), sx, sy)
+    )
+}
+
+unsafe extern "C" fn pointer_button(
+    grab: *mut wlr_seat_pointer_grab,
+    time_msec: u32,
+    button: u32,
+    state: u32
+) -> u32 {
+    call::<wlr_seat_pointer_grab, PointerHandler, _, _>(
+        (*grab).data,
+        (*grab).seat,
+        0,
+        |handler, compositor, seat| handler.button(compositor, seat, duration(time_msec), button, state)
+    )
+}
+
+unsafe extern "C" fn pointer_axis(
+    grab: *mut wlr_seat_pointer_grab,
+    time_msec: u32,
+    orientation: wlr_axis_orientation,
+    value: f64,
+    value_discrete: i32,
+    source: wlr_axis_source
+) {
+    call::<wlr_seat_pointer_grab, PointerHandler, _, _>(
+        (*grab).data,
+        (*grab).seat,
+        (),
+        |handler, compositor, seat| {
+            handler.axis(
+                compositor,
+                seat,
+                duration(time_msec),
+                orientation,
+                value,
+                value_discrete,
+                source
+            )
+        }
+    )
+}
+
+unsafe extern "C" fn pointer_cancel(grab: *mut wlr_seat_pointer_grab) {
+    cancel::<wlr_seat_pointer_grab, PointerHandler, _>(
+        (*grab).data,
+        (*grab).seat,
+        |handler, compositor, seat| handler.cancel(compositor, seat)
+    )
+}
+
+unsafe extern "C" fn keyboard_enter(
+    grab: *mut wlr_seat_keyboard_grab,
+    surface: *mut wlr_surface,
+    keycodes: *mut u32,
+    num_keycodes: size_t,
+    modifiers: *mut wlr_keyboard_modifiers
+) {
+    let mut surface = match surface_from_ptr(surface) {
+        Some(surface) => surface,
+        None => return
+    };
+    let keycodes: &[Keycode] = if keycodes.is_null() {
+        &[]
+    } else {
+        slice::from_raw_parts(keycodes, num_keycodes as usize)
+    };
+    let modifiers = modifiers
+        .as_ref()
+        .map(|modifiers| keyboard::Modifiers::from(*modifiers))
+        .unwrap_or_default();
+    call::<wlr_seat_keyboard_grab, KeyboardHandler, _, _>(
+        (*grab).data,
+        (*grab).seat,
+        (),
+        |handler, compositor, seat| handler.enter(compositor, seat, &mut surface, keycodes, &modifiers)
+    )
+}
+
+unsafe extern "C" fn keyboard_key(grab: *mut wlr_seat_keyboard_grab, time_msec: u32, key: u32, state: u32) {
+    call::<wlr_seat_keyboard_grab, KeyboardHandler, _, _>(
+        (*grab).data,
+        (*grab).seat,
+        (),
+        |handler, compositor, seat| handler.key(compositor, seat, duration(time_msec), key, state)
+    )
+}
+
+unsafe extern "C" fn keyboard_modifiers(
+    grab: *mut wlr_seat_keyboard_grab,
+    modifiers: *mut wlr_keyboard_modifiers
+) {
+    let modifiers = modifiers
+        .as_ref()
+        .map(|modifiers| keyboard::Modifiers::from(*modifiers))
+        .unwrap_or_default();
+    call::<wlr_seat_keyboard_grab, KeyboardHandler, _, _>(
+        (*grab).data,
+        (*grab).seat,
+        (),
+        |handler, compositor, seat| handler.modifiers(compositor, seat, &modifiers)
+    )
+}
+
+unsafe extern "C" fn keyboard_cancel(grab: *mut wlr_seat_keyboard_grab) {
+    cancel::<wlr_seat_keyboard_grab, KeyboardHandler, _>(
+        (*grab).data,
+        (*grab).seat,
+        |handler, compositor, seat| handler.cancel(compositor, seat)
+    )
+}
+
+unsafe extern "C" fn touch_down(
+    grab: *mut wlr_seat_touch_grab,
+    time_msec: u32,
+    point: *mut wlr_touch_point
+) -> u32 {
+    let point = TouchPoint::from_ptr(point);
+    call::<wlr_seat_touch_grab, TouchHandler, _, _>(
+        (*grab).data,
+        (*grab).seat,
+        0,
+        |handler, compositor, seat| handler.down(compositor, seat, duration(time_msec), &point)
+    )
+}
+
+unsafe extern "C" fn touch_up(grab: *mut wlr_seat_touch_grab, time_msec: u32, point: *mut wlr_touch_point) {
+    let point = TouchPoint::from_ptr(point);
+    call::<wlr_seat_touch_grab, TouchHandler, _, _>(
+        (*grab).data,
+        (*grab).seat,
+        (),
+        |handler, compositor, seat| handler.up(compositor, seat, duration(time_msec), &point)
+    )
+}
+
+unsafe extern "C" fn touch_motion(
+    grab: *mut wlr_seat_touch_grab,
+    time_msec: u32,
+    point: *mut wlr_touch_point
+) {
+    let point = TouchPoint::from_ptr(point);
+    call::<wlr_seat_touch_grab, TouchHandler, _, _>(
+        (*grab).data,
+        (*grab).seat,
+        (),
+        |handler, compositor, seat| handler.motion(compositor, seat, duration(time_msec), &point)
+    )
+}
+
+unsafe extern "C" fn touch_enter(
+    grab: *mut wlr_seat_touch_grab,
+    time_msec: u32,
+    point: *mut wlr_touch_point
+) {
+    let point = TouchPoint::from_ptr(point);
+    call::<wlr_seat_touch_grab, TouchHandler, _, _>(
+        (*grab).data,
+        (*grab).seat,
+        (),
+        |handler, compositor, seat| handler.enter(compositor, seat, duration(time_msec), &point)
+    )
+}
+
+unsafe extern "C" fn touch_cancel(grab: *mut wlr_seat_touch_grab) {
+    cancel::<wlr_seat_touch_grab, TouchHandler, _>((*grab).data, (*grab).seat, |handler, compositor, seat| {
+        handler.cancel(compositor, seat)
+    })
 }
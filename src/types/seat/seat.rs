@@ -14,8 +14,8 @@ use crate::libc;
 use crate::wayland_sys::server::{signal::wl_signal_add, WAYLAND_SERVER_HANDLE};
 pub use wlroots_sys::wayland_server::protocol::wl_seat::Capability;
 use wlroots_sys::{
-    wlr_axis_orientation, wlr_axis_source, wlr_seat, wlr_seat_create, wlr_seat_destroy,
-    wlr_seat_get_keyboard, wlr_seat_keyboard_clear_focus, wlr_seat_keyboard_end_grab,
+    wl_list, wlr_axis_orientation, wlr_axis_source, wlr_seat, wlr_seat_client_for_wl_client, wlr_seat_create,
+    wlr_seat_destroy, wlr_seat_get_keyboard, wlr_seat_keyboard_clear_focus, wlr_seat_keyboard_end_grab,
     wlr_seat_keyboard_enter, wlr_seat_keyboard_has_grab, wlr_seat_keyboard_notify_enter,
     wlr_seat_keyboard_notify_key, wlr_seat_keyboard_notify_modifiers, wlr_seat_keyboard_send_key,
     wlr_seat_keyboard_send_modifiers, wlr_seat_keyboard_start_grab, wlr_seat_pointer_clear_focus,
@@ -40,9 +40,12 @@ use crate::{
         touch_point::{TouchId, TouchPoint}
     },
     surface::Surface,
-    utils::{c_to_rust_string, safe_as_cstring, HandleErr, HandleResult, ToMs}
+    utils::{c_to_rust_string, safe_as_cstring, HandleErr, HandleResult, ToMs, UserData}
 };
 
+/// The opcode of the `wl_touch.cancel` event.
+const WL_TOUCH_CANCEL: u32 = 4;
+
 struct SeatState {
     /// A counter that will always have a strong count of 1.
     ///
@@ -50,7 +53,9 @@ struct SeatState {
     /// they cannot be upgraded.
     counter: Rc<Cell<bool>>,
     /// A raw pointer to the Seat on the heap.
-    seat: *mut Seat
+    seat: *mut Seat,
+    /// The user data attached to the seat.
+    user_data: UserData
 }
 
 #[derive(Debug, Clone)]
@@ -357,13 +362,24 @@ impl Seat {
             let handle = Rc::downgrade(&counter);
             let state = Box::new(SeatState {
                 counter,
-                seat: Box::into_raw(res)
+                seat: Box::into_raw(res),
+                user_data: UserData::new()
             });
             (*seat).data = Box::into_raw(state) as *mut libc::c_void;
             Handle { seat, handle }
         }
     }
 
+    /// Gets the `Seat` of a `wlr_seat` created with `Seat::create`, without
+    /// taking ownership of it.
+    pub(crate) unsafe fn from_wlr_seat<'seat>(seat: *mut wlr_seat) -> Option<&'seat Seat> {
+        let data = (*seat).data as *mut SeatState;
+        if data.is_null() {
+            return None;
+        }
+        (*data).seat.as_ref()
+    }
+
     /// Reconstruct the box from the wlr_seat.
     unsafe fn from_ptr(seat: *mut wlr_seat) -> Box<Seat> {
         let data = (*seat).data as *mut SeatState;
@@ -404,6 +420,20 @@ impl Seat {
         }
     }
 
+    /// Gets the user data attached to the seat.
+    ///
+    /// It is dropped when the seat is destroyed.
+    pub fn user_data(&self) -> &UserData {
+        unsafe { &(*((*self.data.0).data as *mut SeatState)).user_data }
+    }
+
+    /// Gets the user data attached to the seat mutably.
+    ///
+    /// It is dropped when the seat is destroyed.
+    pub fn user_data_mut(&mut self) -> &mut UserData {
+        unsafe { &mut (*((*self.data.0).data as *mut SeatState)).user_data }
+    }
+
     /// Gets the capabilities of this seat.
     pub fn capabilities(&self) -> Capability {
         unsafe { Capability::from_raw((*self.data.0).capabilities).expect("Invalid capabilities") }
@@ -489,10 +519,17 @@ impl Seat {
         }
     }
 
-    /// Start a grab of the pointer of this seat. The grabber is responsible for
-    /// handling all pointer events until the grab ends.
-    pub fn pointer_start_grab(&self, grab: grab::Pointer) {
-        unsafe { wlr_seat_pointer_start_grab(self.data.0, grab.as_ptr()) }
+    /// Start a grab of the pointer of this seat. The handler is responsible
+    /// for handling all pointer events until the grab ends.
+    ///
+    /// The current grab, if any, is ended first.
+    pub fn pointer_start_grab(&self, handler: Box<grab::PointerHandler>) {
+        unsafe {
+            // NOTE wlroots doesn't cancel the grab it replaces.
+            wlr_seat_pointer_end_grab(self.data.0);
+            let grab = grab::Pointer::from_handler(handler);
+            wlr_seat_pointer_start_grab(self.data.0, grab.as_ptr())
+        }
     }
 
     /// End the grab of the pointer of this seat. This reverts the grab back to
@@ -531,6 +568,12 @@ impl Seat {
         unsafe { wlr_seat_pointer_notify_button(self.data.0, time.to_ms(), button, state) }
     }
 
+    /// Gets the button that started the implicit grab of the pointer, which
+    /// is the last one pressed while no other button was.
+    pub fn pointer_grab_button(&self) -> u32 {
+        unsafe { (*self.data.0).pointer_state.grab_button }
+    }
+
     /// Notify the seat of an axis event.
     pub fn pointer_notify_axis(
         &self,
@@ -637,10 +680,17 @@ impl Seat {
         }
     }
 
-    /// Start a grab of the keyboard of this seat. The grabber is responsible
+    /// Start a grab of the keyboard of this seat. The handler is responsible
     /// for handling all keyboard events until the grab ends.
-    pub fn keyboard_start_grab(&self, grab: grab::Keyboard) {
-        unsafe { wlr_seat_keyboard_start_grab(self.data.0, grab.as_ptr()) }
+    ///
+    /// The current grab, if any, is ended first.
+    pub fn keyboard_start_grab(&self, handler: Box<grab::KeyboardHandler>) {
+        unsafe {
+            // NOTE wlroots doesn't cancel the grab it replaces.
+            wlr_seat_keyboard_end_grab(self.data.0);
+            let grab = grab::Keyboard::from_handler(handler);
+            wlr_seat_keyboard_start_grab(self.data.0, grab.as_ptr())
+        }
     }
 
     /// End the grab of the keyboard of this seat. This reverts the grab back to
@@ -682,10 +732,17 @@ impl Seat {
         unsafe { wlr_seat_touch_num_points(self.data.0) }
     }
 
-    /// Start a grab of the touch device of this seat. The grabber is
+    /// Start a grab of the touch device of this seat. The handler is
     /// responsible for handling all touch events until the grab ends.
-    pub fn touch_start_grab(&self, grab: grab::Touch) {
-        unsafe { wlr_seat_touch_start_grab(self.data.0, grab.as_ptr()) }
+    ///
+    /// The current grab, if any, is ended first.
+    pub fn touch_start_grab(&self, handler: Box<grab::TouchHandler>) {
+        unsafe {
+            // NOTE wlroots doesn't cancel the grab it replaces.
+            wlr_seat_touch_end_grab(self.data.0);
+            let grab = grab::Touch::from_handler(handler);
+            wlr_seat_touch_start_grab(self.data.0, grab.as_ptr())
+        }
     }
 
     /// End the grab of the touch device of this seat. This reverts the grab
@@ -699,6 +756,25 @@ impl Seat {
         unsafe { wlr_seat_touch_has_grab(self.data.0) }
     }
 
+    /// Determines if the client of the surface bound the touch capability of
+    /// the seat, i.e. if it can receive touch events.
+    pub fn client_accepts_touch(&self, surface: &Surface) -> bool {
+        unsafe {
+            let client = ffi_dispatch!(
+                WAYLAND_SERVER_HANDLE,
+                wl_resource_get_client,
+                (*surface.as_ptr()).resource as _
+            );
+            let seat_client = wlr_seat_client_for_wl_client(self.data.0, client as _);
+            !seat_client.is_null() &&
+                ffi_dispatch!(
+                    WAYLAND_SERVER_HANDLE,
+                    wl_list_empty,
+                    &(*seat_client).touches as *const _ as _
+                ) == 0
+        }
+    }
+
     // Get the active touch point with the given `touch_id`. If the touch point does
     // not exist or is no longer active, returns None.
     pub fn get_touch_point(&self, touch_id: TouchId) -> Option<TouchPoint> {
@@ -789,6 +865,32 @@ impl Seat {
         unsafe { wlr_seat_touch_send_up(self.data.0, time.to_ms(), touch_id.into()) }
     }
 
+    /// Send a touch cancel event to the client of the touch point given by
+    /// the `touch_id`, which makes it forget about all of its touch points.
+    ///
+    /// This does not remove the touch point, wlroots only removes it when it
+    /// is notified of the touch point being up.
+    pub fn touch_send_cancel(&self, touch_id: TouchId) {
+        unsafe {
+            let point = wlr_seat_touch_get_point(self.data.0, touch_id.into());
+            if point.is_null() || (*point).client.is_null() {
+                return;
+            }
+            let touches = &mut (*(*point).client).touches as *mut wl_list;
+            let mut link = (*touches).next;
+            while link != touches {
+                let resource = ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_resource_from_link, link as _);
+                ffi_dispatch!(
+                    WAYLAND_SERVER_HANDLE,
+                    wl_resource_post_event,
+                    resource,
+                    WL_TOUCH_CANCEL
+                );
+                link = (*link).next;
+            }
+        }
+    }
+
     /// Send a touch motion event for the touch point given by the `touch_id`.
     ///
     /// The event will go to the client for the surface given in the
@@ -855,7 +957,31 @@ impl Drop for Seat {
     fn drop(&mut self) {
         let seat_ptr = self.data.0;
         unsafe {
-            let data = Box::from_raw((*seat_ptr).data as *mut SeatState);
+            let data_ptr = (*seat_ptr).data as *mut SeatState;
+            let manager = (*data_ptr).seat;
+            // Stop listening to the grabs before ending them, the user
+            // shouldn't be told about grabs ending while the seat is dropped.
+            for listener in &[
+                (*manager).pointer_grab_begin_listener(),
+                (*manager).pointer_grab_end_listener(),
+                (*manager).keyboard_grab_begin_listener(),
+                (*manager).keyboard_grab_end_listener(),
+                (*manager).touch_grab_begin_listener(),
+                (*manager).touch_grab_end_listener()
+            ] {
+                ffi_dispatch!(
+                    WAYLAND_SERVER_HANDLE,
+                    wl_list_remove,
+                    &mut (**listener).link as *mut _ as _
+                );
+            }
+            // End the grabs while the seat state is still around, so that the
+            // handlers of custom grabs are cancelled with a usable `Seat` and
+            // then dropped.
+            wlr_seat_pointer_end_grab(seat_ptr);
+            wlr_seat_keyboard_end_grab(seat_ptr);
+            wlr_seat_touch_end_grab(seat_ptr);
+            let data = Box::from_raw(data_ptr);
             let mut manager = Box::from_raw(data.seat);
             assert_eq!(
                 Rc::strong_count(&data.counter),
@@ -863,36 +989,6 @@ impl Drop for Seat {
                 "Seat had more than 1 reference count"
             );
             (*seat_ptr).data = ptr::null_mut();
-            ffi_dispatch!(
-                WAYLAND_SERVER_HANDLE,
-                wl_list_remove,
-                &mut (*manager.pointer_grab_begin_listener()).link as *mut _ as _
-            );
-            ffi_dispatch!(
-                WAYLAND_SERVER_HANDLE,
-                wl_list_remove,
-                &mut (*manager.pointer_grab_end_listener()).link as *mut _ as _
-            );
-            ffi_dispatch!(
-                WAYLAND_SERVER_HANDLE,
-                wl_list_remove,
-                &mut (*manager.keyboard_grab_begin_listener()).link as *mut _ as _
-            );
-            ffi_dispatch!(
-                WAYLAND_SERVER_HANDLE,
-                wl_list_remove,
-                &mut (*manager.keyboard_grab_end_listener()).link as *mut _ as _
-            );
-            ffi_dispatch!(
-                WAYLAND_SERVER_HANDLE,
-                wl_list_remove,
-                &mut (*manager.touch_grab_begin_listener()).link as *mut _ as _
-            );
-            ffi_dispatch!(
-                WAYLAND_SERVER_HANDLE,
-                wl_list_remove,
-                &mut (*manager.touch_grab_end_listener()).link as *mut _ as _
-            );
             ffi_dispatch!(
                 WAYLAND_SERVER_HANDLE,
                 wl_list_remove,
@@ -1,5 +1,7 @@
 use wlroots_sys::wlr_touch_point;
 
+use crate::surface;
+
 #[derive(Clone)]
 pub struct TouchPoint {
     touch_point: *mut wlr_touch_point
@@ -17,12 +19,51 @@ impl Into<i32> for TouchId {
     }
 }
 
+impl TouchId {
+    /// Wraps the id of a touch event from wlroots.
+    pub(crate) fn new(touch_id: i32) -> Self {
+        TouchId(touch_id)
+    }
+}
+
 impl TouchPoint {
     /// Get the touch id associated for this point.
     pub fn touch_id(&self) -> TouchId {
         unsafe { TouchId((*self.touch_point).touch_id) }
     }
 
+    /// Get the surface the touch point was put down on, if it still exists.
+    pub fn surface(&self) -> Option<surface::Handle> {
+        unsafe {
+            let surface = (*self.touch_point).surface;
+            if surface.is_null() {
+                None
+            } else {
+                Some(surface::Handle::from_ptr(surface))
+            }
+        }
+    }
+
+    /// Get the surface that currently has the focus of the touch point, if
+    /// any.
+    pub fn focus_surface(&self) -> Option<surface::Handle> {
+        unsafe {
+            let surface = (*self.touch_point).focus_surface;
+            if surface.is_null() {
+                None
+            } else {
+                Some(surface::Handle::from_ptr(surface))
+            }
+        }
+    }
+
+    /// Get the location of the touch point, local to its surface.
+    ///
+    /// Return format is (sx, sy).
+    pub fn location(&self) -> (f64, f64) {
+        unsafe { ((*self.touch_point).sx, (*self.touch_point).sy) }
+    }
+
     #[allow(dead_code)]
     pub(crate) unsafe fn as_ptr(&self) -> *mut wlr_touch_point {
         self.touch_point
@@ -1,2 +1,4 @@
+pub mod move_resize;
+pub mod window;
 pub mod xdg_shell;
 pub mod xdg_shell_v6;
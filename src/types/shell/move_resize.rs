@@ -0,0 +1,397 @@
+//! Interactive moves and resizes of toplevels.
+//!
+//! When a client asks to be moved or resized (e.g. because its title bar
+//! was dragged), start a `MoveResize` with the layout position of the
+//! toplevel. It grabs the pointer of the seat until the button that was
+//! pressed to start it is released, sending the new sizes to the client as
+//! the cursor moves:
+//!
+//! ```rust,no_run,ignore
+//! fn resize_request(&mut self, compositor_handle: compositor::Handle,
+//!                   surface_handle: surface::Handle, shell_handle: xdg_shell::Handle,
+//!                   event: &xdg_shell::event::Resize) {
+//!     let view = &self.views[&shell_handle];
+//!     let cursor = self.cursor.run(|cursor| cursor.coords()).unwrap();
+//!     self.seat.run(|seat| {
+//!         self.move_resize = Some(MoveResize::start_resize(seat, window::Handle::XdgShell(shell_handle),
+//!                                                          view.position, event.edges(), cursor));
+//!     }).unwrap();
+//! }
+//!
+//! // Then, in the cursor motion callback, instead of notifying the seat:
+//! match self.move_resize {
+//!     Some(ref mut move_resize) if move_resize.is_active() => {
+//!         view.position = move_resize.motion(cursor_x, cursor_y);
+//!     }
+//!     _ => seat.pointer_notify_motion(time, sx, sy)
+//! }
+//! ```
+
+use std::{cell::RefCell, rc::Rc, time::Duration};
+
+use wlroots_sys::wlr_button_state::WLR_BUTTON_RELEASED;
+
+use crate::{
+    area::{Area, Origin, Size},
+    compositor,
+    seat::{grab, Seat},
+    shell::window,
+    utils::edges::Edges
+};
+
+/// An interactive move or resize of a toplevel.
+///
+/// It ends when the pointer button that started it is released, when the
+/// grab is replaced by another one, or with `end`.
+#[derive(Debug)]
+pub struct MoveResize {
+    state: Rc<RefCell<State>>
+}
+
+#[derive(Debug)]
+struct State {
+    window: window::Handle,
+    /// The edges being dragged, none for a move.
+    edges: Edges,
+    active: bool,
+    /// The button whose release ends the operation.
+    button: u32,
+    /// The cursor position when the operation started, and now.
+    grab_cursor: (f64, f64),
+    cursor: (f64, f64),
+    /// The layout position and size of the toplevel when the operation
+    /// started.
+    start_position: (f64, f64),
+    start_size: Size,
+    /// The size last sent to the client.
+    requested_size: Size
+}
+
+/// The pointer grab held while the operation is active.
+struct Grab {
+    state: Rc<RefCell<State>>
+}
+
+impl MoveResize {
+    /// Starts moving a toplevel at the given layout position, with the cursor
+    /// at the given layout coordinates.
+    pub fn start_move(seat: &Seat, window: window::Handle, position: (f64, f64), cursor: (f64, f64)) -> Self {
+        MoveResize::start(seat, window, position, Edges::WLR_EDGE_NONE, cursor)
+    }
+
+    /// Starts resizing a toplevel at the given layout position by dragging
+    /// the given edges, with the cursor at the given layout coordinates.
+    pub fn start_resize(
+        seat: &Seat,
+        window: window::Handle,
+        position: (f64, f64),
+        edges: Edges,
+        cursor: (f64, f64)
+    ) -> Self {
+        let move_resize = MoveResize::start(seat, window, position, edges, cursor);
+        if !edges.is_empty() {
+            set_resizing(&move_resize.state.borrow().window, true);
+        }
+        move_resize
+    }
+
+    fn start(
+        seat: &Seat,
+        window: window::Handle,
+        position: (f64, f64),
+        edges: Edges,
+        cursor: (f64, f64)
+    ) -> Self {
+        let size = size(&window).unwrap_or_default();
+        let state = Rc::new(RefCell::new(State {
+            window,
+            edges,
+            active: true,
+            button: seat.pointer_grab_button(),
+            grab_cursor: cursor,
+            cursor,
+            start_position: position,
+            start_size: size,
+            requested_size: size
+        }));
+        seat.pointer_start_grab(Box::new(Grab { state: state.clone() }));
+        MoveResize { state }
+    }
+
+    /// Determines if the operation is still going on.
+    pub fn is_active(&self) -> bool {
+        self.state.borrow().active
+    }
+
+    /// Gets the window being moved or resized.
+    pub fn window(&self) -> window::Handle {
+        self.state.borrow().window.clone()
+    }
+
+    /// Gets the edges being dragged, which are none for a move.
+    pub fn edges(&self) -> Edges {
+        self.state.borrow().edges
+    }
+
+    /// Feeds the new layout coordinates of the cursor, sending the new size
+    /// to the client if it changed.
+    ///
+    /// Returns the layout position the toplevel should be drawn at.
+    pub fn motion(&mut self, cursor_x: f64, cursor_y: f64) -> (f64, f64) {
+        let mut state = self.state.borrow_mut();
+        if state.active {
+            state.cursor = (cursor_x, cursor_y);
+            if state.edges.is_empty() {
+                // Xwayland windows need to know where they are to place
+                // their popups.
+                if let window::Handle::Xwayland(_) = state.window {
+                    let size = size(&state.window).unwrap_or(state.start_size);
+                    configure(&state.window, state.position(), size);
+                }
+            } else {
+                let size = state.target_size(size_limits(&state.window));
+                if size != state.requested_size {
+                    state.requested_size = size;
+                    let position = state.resize_position(size);
+                    configure(&state.window, position, size);
+                }
+            }
+        }
+        state.position()
+    }
+
+    /// Gets the layout position the toplevel should be drawn at.
+    ///
+    /// While resizing from the left or top edges this follows the size the
+    /// client committed, so the opposite edges stay in place.
+    pub fn position(&self) -> (f64, f64) {
+        self.state.borrow().position()
+    }
+
+    /// Ends the operation, releasing the pointer grab.
+    pub fn end(&self, seat: &Seat) {
+        let was_active = self.is_active();
+        self.state.borrow_mut().finish();
+        if was_active {
+            seat.pointer_end_grab()
+        }
+    }
+}
+
+impl State {
+    fn delta(&self) -> (f64, f64) {
+        (
+            self.cursor.0 - self.grab_cursor.0,
+            self.cursor.1 - self.grab_cursor.1
+        )
+    }
+
+    fn position(&self) -> (f64, f64) {
+        if self.edges.is_empty() {
+            let (dx, dy) = self.delta();
+            return (self.start_position.0 + dx, self.start_position.1 + dy);
+        }
+        let size = size(&self.window).unwrap_or(self.requested_size);
+        self.resize_position(size)
+    }
+
+    /// The position keeping the edges that aren't dragged in place at the
+    /// given size.
+    fn resize_position(&self, size: Size) -> (f64, f64) {
+        let (mut x, mut y) = self.start_position;
+        if self.edges.contains(Edges::WLR_EDGE_LEFT) {
+            x += f64::from(self.start_size.width - size.width);
+        }
+        if self.edges.contains(Edges::WLR_EDGE_TOP) {
+            y += f64::from(self.start_size.height - size.height);
+        }
+        (x, y)
+    }
+
+    /// The size following the cursor, within the minimum and maximum sizes
+    /// of the toplevel.
+    fn target_size(&self, (min, max): (Size, Size)) -> Size {
+        let (dx, dy) = self.delta();
+        let mut width = f64::from(self.start_size.width);
+        let mut height = f64::from(self.start_size.height);
+        if self.edges.contains(Edges::WLR_EDGE_LEFT) {
+            width -= dx;
+        } else if self.edges.contains(Edges::WLR_EDGE_RIGHT) {
+            width += dx;
+        }
+        if self.edges.contains(Edges::WLR_EDGE_TOP) {
+            height -= dy;
+        } else if self.edges.contains(Edges::WLR_EDGE_BOTTOM) {
+            height += dy;
+        }
+        Size::new(
+            clamp(width.round() as i32, min.width, max.width),
+            clamp(height.round() as i32, min.height, max.height)
+        )
+    }
+
+    fn finish(&mut self) {
+        if !self.active {
+            return;
+        }
+        self.active = false;
+        if !self.edges.is_empty() {
+            set_resizing(&self.window, false)
+        }
+    }
+}
+
+/// The size of the window's geometry.
+fn size(window_handle: &window::Handle) -> Option<Size> {
+    window_handle.run(|window| window.geometry().size).ok()
+}
+
+/// The minimum and maximum sizes of the window, where 0 means there is no
+/// limit.
+fn size_limits(window_handle: &window::Handle) -> (Size, Size) {
+    window_handle
+        .run(|window| (window.min_size(), window.max_size()))
+        .unwrap_or_default()
+}
+
+fn set_resizing(window_handle: &window::Handle, resizing: bool) {
+    window_handle.run(|window| window.set_resizing(resizing)).ok();
+}
+
+/// Sends a new size to the window, and for Xwayland its position too.
+fn configure(window_handle: &window::Handle, position: (f64, f64), size: Size) {
+    let origin = Origin::new(position.0.round() as i32, position.1.round() as i32);
+    window_handle.configure(Area::new(origin, size)).ok();
+}
+
+impl grab::PointerHandler for Grab {
+    fn button(&mut self, _: compositor::Handle, seat: &Seat, _: Duration, button: u32, state: u32) -> u32 {
+        if state == WLR_BUTTON_RELEASED as u32 && button == self.state.borrow().button {
+            self.state.borrow_mut().finish();
+            seat.pointer_end_grab();
+        }
+        0
+    }
+
+    fn cancel(&mut self, _: compositor::Handle, _: &Seat) {
+        self.state.borrow_mut().finish()
+    }
+}
+
+/// Clamps a dimension between its limits, where a limit of 0 means there is
+/// none. Dimensions are at least 1.
+fn clamp(value: i32, min: i32, max: i32) -> i32 {
+    let value = value.max(min).max(1);
+    if max > 0 {
+        value.min(max.max(min))
+    } else {
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(edges: Edges, cursor: (f64, f64)) -> State {
+        State {
+            window: window::Handle::XdgShell(Default::default()),
+            edges,
+            active: true,
+            button: 0x110,
+            grab_cursor: (100.0, 100.0),
+            cursor,
+            start_position: (10.0, 20.0),
+            start_size: Size::new(200, 100),
+            requested_size: Size::new(200, 100)
+        }
+    }
+
+    #[test]
+    fn clamp() {
+        // (value, min, max, clamped)
+        let cases = [
+            (50, 0, 0, 50),
+            (50, 60, 0, 60),
+            (50, 0, 40, 40),
+            (50, 10, 100, 50),
+            (-5, 0, 0, 1),
+            (0, 0, 0, 1),
+            // A maximum below the minimum is ignored.
+            (50, 60, 40, 60)
+        ];
+        for &(value, min, max, expected) in &cases {
+            assert_eq!(
+                super::clamp(value, min, max),
+                expected,
+                "clamp({}, {}, {})",
+                value,
+                min,
+                max
+            );
+        }
+    }
+
+    #[test]
+    fn target_size() {
+        let none = (Size::default(), Size::default());
+        let cases = [
+            (Edges::WLR_EDGE_RIGHT, (150.0, 80.0), none, Size::new(250, 100)),
+            (Edges::WLR_EDGE_LEFT, (150.0, 80.0), none, Size::new(150, 100)),
+            (Edges::WLR_EDGE_BOTTOM, (150.0, 130.4), none, Size::new(200, 130)),
+            (Edges::WLR_EDGE_TOP, (150.0, 130.6), none, Size::new(200, 69)),
+            (
+                Edges::WLR_EDGE_BOTTOM | Edges::WLR_EDGE_RIGHT,
+                (120.0, 110.0),
+                none,
+                Size::new(220, 110)
+            ),
+            (
+                Edges::WLR_EDGE_TOP | Edges::WLR_EDGE_LEFT,
+                (120.0, 110.0),
+                none,
+                Size::new(180, 90)
+            ),
+            // Shrinking past the window is at least 1.
+            (Edges::WLR_EDGE_RIGHT, (-500.0, 100.0), none, Size::new(1, 100)),
+            (
+                Edges::WLR_EDGE_RIGHT | Edges::WLR_EDGE_BOTTOM,
+                (500.0, -500.0),
+                (Size::new(50, 60), Size::new(300, 0)),
+                Size::new(300, 60)
+            )
+        ];
+        for &(edges, cursor, limits, expected) in &cases {
+            assert_eq!(
+                state(edges, cursor).target_size(limits),
+                expected,
+                "{:?} {:?}",
+                edges,
+                cursor
+            );
+        }
+    }
+
+    #[test]
+    fn resize_position() {
+        let cases = [
+            (Edges::WLR_EDGE_RIGHT, Size::new(250, 150), (10.0, 20.0)),
+            (Edges::WLR_EDGE_BOTTOM, Size::new(250, 150), (10.0, 20.0)),
+            (Edges::WLR_EDGE_LEFT, Size::new(250, 150), (-40.0, 20.0)),
+            (Edges::WLR_EDGE_TOP, Size::new(250, 150), (10.0, -30.0)),
+            (
+                Edges::WLR_EDGE_TOP | Edges::WLR_EDGE_LEFT,
+                Size::new(150, 50),
+                (60.0, 70.0)
+            )
+        ];
+        for &(edges, size, expected) in &cases {
+            assert_eq!(
+                state(edges, (0.0, 0.0)).resize_position(size),
+                expected,
+                "{:?}",
+                edges
+            );
+        }
+    }
+}
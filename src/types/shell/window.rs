@@ -0,0 +1,1003 @@
+//! A common interface to the toplevel windows of every shell.
+//!
+//! XDG shell, XDG shell v6 and Xwayland toplevels all implement `Window`,
+//! and a window `Handle` refers to any of them. To get the events of a
+//! window from a single `Handler`, return the matching adapter when the
+//! shell surface appears:
+//!
+//! ```rust,no_run,ignore
+//! struct View;
+//!
+//! impl window::Handler for View {
+//!     fn on_event(&mut self, compositor_handle: compositor::Handle,
+//!                 window_handle: window::Handle, event: window::Event) {
+//!         match event {
+//!             window::Event::Map => {
+//!                 window_handle.run(|window| window.set_activated(true)).unwrap();
+//!             }
+//!             window::Event::FullscreenRequest(fullscreen) => {
+//!                 window_handle.run(|window| window.set_fullscreen(fullscreen)).unwrap();
+//!             }
+//!             _ => {}
+//!         }
+//!     }
+//! }
+//!
+//! fn new_xdg_surface(compositor_handle: compositor::Handle,
+//!                    xdg_shell_handle: xdg_shell::Handle) -> xdg_shell::NewSurfaceResult {
+//!     (Some(window::xdg_shell_handler(Box::new(View))), None)
+//! }
+//!
+//! fn new_xwayland_surface(compositor_handle: compositor::Handle,
+//!                         xwayland_surface: xwayland::surface::Handle)
+//!                         -> Option<Box<xwayland::surface::Handler>> {
+//!     Some(window::xwayland_handler(Box::new(View)))
+//! }
+//! ```
+
+use std::{cell::RefCell, rc::Rc};
+
+use wlroots_sys::wlr_surface;
+
+use crate::{
+    area::{Area, Origin, Size},
+    compositor, seat,
+    shell::{
+        xdg_shell::{self, ShellState},
+        xdg_shell_v6
+    },
+    surface,
+    utils::{c_to_rust_string, edges::Edges, HandleErr, HandleResult, Handleable},
+    xwayland
+};
+
+/// A toplevel window of any shell.
+pub trait Window {
+    /// Get the title of the window, if there is one.
+    fn title(&self) -> Option<String>;
+
+    /// Get the app id of the window, if there is one.
+    ///
+    /// For Xwayland windows this is their class.
+    fn app_id(&self) -> Option<String>;
+
+    /// Get the geometry of the window.
+    ///
+    /// For XDG shell windows this is the window geometry relative to the
+    /// surface, or the size of the surface if the client did not set one.
+    /// For Xwayland windows it is in layout coordinates.
+    fn geometry(&self) -> Area;
+
+    /// Get the surface of the window, if it has one.
+    fn surface(&self) -> Option<surface::Handle>;
+
+    /// Get the minimum size of the window, where 0 means there is none.
+    fn min_size(&self) -> Size;
+
+    /// Get the maximum size of the window, where 0 means there is none.
+    fn max_size(&self) -> Size;
+
+    /// Request that the window be the given size.
+    ///
+    /// Returns the configure serial, for XDG shell windows.
+    fn set_size(&mut self, width: u32, height: u32) -> Option<u32>;
+
+    /// Request that the window show itself as activated or not.
+    ///
+    /// Returns the configure serial, for XDG shell windows.
+    fn set_activated(&mut self, activated: bool) -> Option<u32>;
+
+    /// Request that the window consider itself maximized or not.
+    ///
+    /// Returns the configure serial, for XDG shell windows.
+    fn set_maximized(&mut self, maximized: bool) -> Option<u32>;
+
+    /// Request that the window consider itself fullscreen or not.
+    ///
+    /// Returns the configure serial, for XDG shell windows.
+    fn set_fullscreen(&mut self, fullscreen: bool) -> Option<u32>;
+
+    /// Request that the window consider itself being resized or not.
+    ///
+    /// Xwayland windows have no such state, so this does nothing for them.
+    fn set_resizing(&mut self, resizing: bool) -> Option<u32>;
+
+    /// Request that the window closes.
+    fn close(&mut self);
+}
+
+/// A handle to a toplevel window of any shell.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum Handle {
+    XdgShell(xdg_shell::Handle),
+    XdgShellV6(xdg_shell_v6::Handle),
+    Xwayland(xwayland::surface::Handle)
+}
+
+/// The seat and serial of a request to start an interactive move or resize.
+///
+/// Compare the serial to the grab serial of the seat's pointer to make sure
+/// the request comes from a button press the client actually got.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GrabRequest {
+    pub seat: seat::Handle,
+    pub serial: u32
+}
+
+/// An event of a window, from any shell.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// The window is ready to be shown.
+    Map,
+    /// The window should not be shown anymore, but may be mapped again later.
+    Unmap,
+    /// The surface of the window committed a new state.
+    Commit,
+    /// The window is being destroyed.
+    Destroyed,
+    /// The client did not answer a ping in time.
+    PingTimeout,
+    /// The client asks to start an interactive move.
+    ///
+    /// Xwayland requests have no seat nor serial.
+    MoveRequest(Option<GrabRequest>),
+    /// The client asks to start an interactive resize by dragging the edges.
+    ///
+    /// Xwayland requests have no seat nor serial.
+    ResizeRequest(Edges, Option<GrabRequest>),
+    /// The client asks to be maximized, or not.
+    MaximizeRequest(bool),
+    /// The client asks to be minimized.
+    MinimizeRequest,
+    /// The client asks to be fullscreen, or not.
+    FullscreenRequest(bool),
+    /// The client asks for the window menu to be shown at the given
+    /// coordinates, relative to the window geometry.
+    ShowWindowMenuRequest(u32, u32),
+    /// An Xwayland client asks for its window to be placed at the given
+    /// layout coordinates.
+    ConfigureRequest(Area),
+    /// The title of the window changed.
+    ///
+    /// Only emitted for Xwayland windows, the XDG shells don't report it.
+    TitleChanged,
+    /// The app id of the window changed.
+    ///
+    /// Only emitted for Xwayland windows, the XDG shells don't report it.
+    AppIdChanged,
+    /// A popup of an XDG shell window appeared.
+    ///
+    /// The popup itself is given to the `new_surface` callback of the shell
+    /// manager, like any other XDG surface.
+    NewPopup
+}
+
+/// Handles the events of a window.
+pub trait Handler {
+    /// Called for every event of the window.
+    fn on_event(&mut self, compositor_handle: compositor::Handle, window_handle: Handle, event: Event);
+}
+
+impl Handle {
+    /// Makes a window handle from an XDG shell handle, if it's a toplevel.
+    pub fn from_xdg_shell(handle: xdg_shell::Handle) -> Option<Self> {
+        let is_toplevel = handle
+            .run(|shell_surface| match shell_surface.state() {
+                Some(&mut ShellState::TopLevel(_)) => true,
+                _ => false
+            })
+            .unwrap_or(false);
+        if is_toplevel {
+            Some(Handle::XdgShell(handle))
+        } else {
+            None
+        }
+    }
+
+    /// Makes a window handle from an XDG shell v6 handle, if it's a
+    /// toplevel.
+    pub fn from_xdg_shell_v6(handle: xdg_shell_v6::Handle) -> Option<Self> {
+        let is_toplevel = handle
+            .run(|shell_surface| match shell_surface.state() {
+                Some(&mut xdg_shell_v6::ShellState::TopLevel(_)) => true,
+                _ => false
+            })
+            .unwrap_or(false);
+        if is_toplevel {
+            Some(Handle::XdgShellV6(handle))
+        } else {
+            None
+        }
+    }
+
+    /// Runs a function with the window the handle refers to.
+    ///
+    /// Handles to XDG shell surfaces that aren't toplevels act as if the
+    /// window was dropped.
+    pub fn run<F, R>(&self, runner: F) -> HandleResult<R>
+    where
+        F: FnOnce(&mut Window) -> R
+    {
+        match *self {
+            Handle::XdgShell(ref handle) => handle
+                .run(|shell_surface| match shell_surface.state() {
+                    Some(&mut ShellState::TopLevel(ref mut toplevel)) => Ok(runner(toplevel)),
+                    _ => Err(HandleErr::AlreadyDropped)
+                })
+                .and_then(|result| result),
+            Handle::XdgShellV6(ref handle) => handle
+                .run(|shell_surface| match shell_surface.state() {
+                    Some(&mut xdg_shell_v6::ShellState::TopLevel(ref mut toplevel)) => Ok(runner(toplevel)),
+                    _ => Err(HandleErr::AlreadyDropped)
+                })
+                .and_then(|result| result),
+            Handle::Xwayland(ref handle) => handle.run(|surface| runner(surface))
+        }
+    }
+
+    /// Asks the window to take the size of the area, and Xwayland windows
+    /// to also move to its origin, in layout coordinates.
+    ///
+    /// Returns the configure serial, for XDG shell windows.
+    ///
+    /// Sizes are at least 1. X11 coordinates and sizes are 16 bits, so they
+    /// are clamped to fit for Xwayland windows.
+    pub fn configure(&self, area: Area) -> HandleResult<Option<u32>> {
+        let (width, height) = (area.size.width.max(1), area.size.height.max(1));
+        match *self {
+            Handle::Xwayland(ref handle) => handle.run(|surface| {
+                surface.configure(
+                    x11_coord(area.origin.x),
+                    x11_coord(area.origin.y),
+                    x11_size(width as u32),
+                    x11_size(height as u32)
+                );
+                None
+            }),
+            _ => self.run(|window| window.set_size(width as u32, height as u32))
+        }
+    }
+}
+
+/// Makes an XDG shell handler passing the events of a toplevel to a window
+/// handler.
+///
+/// It should only be used for toplevels, as popups are not windows.
+pub fn xdg_shell_handler(handler: Box<Handler>) -> Box<xdg_shell::Handler> {
+    Box::new(XdgShellAdapter { handler })
+}
+
+/// Makes an XDG shell v6 handler passing the events of a toplevel to a
+/// window handler.
+///
+/// It should only be used for toplevels, as popups are not windows.
+pub fn xdg_shell_v6_handler(handler: Box<Handler>) -> Box<xdg_shell_v6::Handler> {
+    Box::new(XdgShellV6Adapter { handler })
+}
+
+/// Makes an Xwayland surface handler passing its events to a window handler.
+///
+/// Commits are passed on by the surface handler it returns when the window
+/// is mapped.
+pub fn xwayland_handler(handler: Box<Handler>) -> Box<xwayland::surface::Handler> {
+    Box::new(XwaylandAdapter {
+        handler: Rc::new(RefCell::new(handler))
+    })
+}
+
+struct XdgShellAdapter {
+    handler: Box<Handler>
+}
+
+struct XdgShellV6Adapter {
+    handler: Box<Handler>
+}
+
+struct XwaylandAdapter {
+    handler: Rc<RefCell<Box<Handler>>>
+}
+
+/// Passes the commits of a mapped Xwayland surface on.
+struct XwaylandSurfaceAdapter {
+    handler: Rc<RefCell<Box<Handler>>>,
+    window_handle: Handle
+}
+
+impl XdgShellAdapter {
+    fn notify(
+        &mut self,
+        compositor_handle: compositor::Handle,
+        xdg_shell_handle: xdg_shell::Handle,
+        event: Event
+    ) {
+        self.handler
+            .on_event(compositor_handle, Handle::XdgShell(xdg_shell_handle), event)
+    }
+}
+
+impl xdg_shell::Handler for XdgShellAdapter {
+    fn on_commit(
+        &mut self,
+        compositor_handle: compositor::Handle,
+        _: surface::Handle,
+        handle: xdg_shell::Handle
+    ) {
+        self.notify(compositor_handle, handle, Event::Commit)
+    }
+
+    fn destroyed(&mut self, compositor_handle: compositor::Handle, handle: xdg_shell::Handle) {
+        self.notify(compositor_handle, handle, Event::Destroyed)
+    }
+
+    fn new_popup(
+        &mut self,
+        compositor_handle: compositor::Handle,
+        _: surface::Handle,
+        handle: xdg_shell::Handle
+    ) {
+        self.notify(compositor_handle, handle, Event::NewPopup)
+    }
+
+    fn ping_timeout(
+        &mut self,
+        compositor_handle: compositor::Handle,
+        _: surface::Handle,
+        handle: xdg_shell::Handle
+    ) {
+        self.notify(compositor_handle, handle, Event::PingTimeout)
+    }
+
+    fn maximize_request(
+        &mut self,
+        compositor_handle: compositor::Handle,
+        _: surface::Handle,
+        handle: xdg_shell::Handle
+    ) {
+        let maximized = handle
+            .run(|shell_surface| match shell_surface.state() {
+                Some(&mut ShellState::TopLevel(ref toplevel)) => toplevel.client_pending_state().maximized,
+                _ => false
+            })
+            .unwrap_or(false);
+        self.notify(compositor_handle, handle, Event::MaximizeRequest(maximized))
+    }
+
+    fn minimize_request(
+        &mut self,
+        compositor_handle: compositor::Handle,
+        _: surface::Handle,
+        handle: xdg_shell::Handle
+    ) {
+        self.notify(compositor_handle, handle, Event::MinimizeRequest)
+    }
+
+    fn move_request(
+        &mut self,
+        compositor_handle: compositor::Handle,
+        _: surface::Handle,
+        handle: xdg_shell::Handle,
+        event: &xdg_shell::event::Move
+    ) {
+        let request = GrabRequest {
+            seat: event.seat(),
+            serial: event.serial()
+        };
+        self.notify(compositor_handle, handle, Event::MoveRequest(Some(request)))
+    }
+
+    fn resize_request(
+        &mut self,
+        compositor_handle: compositor::Handle,
+        _: surface::Handle,
+        handle: xdg_shell::Handle,
+        event: &xdg_shell::event::Resize
+    ) {
+        let request = GrabRequest {
+            seat: event.seat(),
+            serial: event.serial()
+        };
+        self.notify(
+            compositor_handle,
+            handle,
+            Event::ResizeRequest(event.edges(), Some(request))
+        )
+    }
+
+    fn fullscreen_request(
+        &mut self,
+        compositor_handle: compositor::Handle,
+        _: surface::Handle,
+        handle: xdg_shell::Handle,
+        event: &xdg_shell::event::SetFullscreen
+    ) {
+        self.notify(
+            compositor_handle,
+            handle,
+            Event::FullscreenRequest(event.fullscreen())
+        )
+    }
+
+    fn show_window_menu_request(
+        &mut self,
+        compositor_handle: compositor::Handle,
+        _: surface::Handle,
+        handle: xdg_shell::Handle,
+        event: &xdg_shell::event::ShowWindowMenu
+    ) {
+        let (x, y) = event.coords();
+        self.notify(compositor_handle, handle, Event::ShowWindowMenuRequest(x, y))
+    }
+
+    fn map_request(
+        &mut self,
+        compositor_handle: compositor::Handle,
+        _: surface::Handle,
+        handle: xdg_shell::Handle
+    ) {
+        self.notify(compositor_handle, handle, Event::Map)
+    }
+
+    fn unmap_request(
+        &mut self,
+        compositor_handle: compositor::Handle,
+        _: surface::Handle,
+        handle: xdg_shell::Handle
+    ) {
+        self.notify(compositor_handle, handle, Event::Unmap)
+    }
+}
+
+impl XdgShellV6Adapter {
+    fn notify(
+        &mut self,
+        compositor_handle: compositor::Handle,
+        xdg_shell_v6_handle: xdg_shell_v6::Handle,
+        event: Event
+    ) {
+        self.handler
+            .on_event(compositor_handle, Handle::XdgShellV6(xdg_shell_v6_handle), event)
+    }
+}
+
+impl xdg_shell_v6::Handler for XdgShellV6Adapter {
+    fn on_commit(
+        &mut self,
+        compositor_handle: compositor::Handle,
+        _: surface::Handle,
+        handle: xdg_shell_v6::Handle
+    ) {
+        self.notify(compositor_handle, handle, Event::Commit)
+    }
+
+    fn destroyed(&mut self, compositor_handle: compositor::Handle, handle: xdg_shell_v6::Handle) {
+        self.notify(compositor_handle, handle, Event::Destroyed)
+    }
+
+    fn new_popup(
+        &mut self,
+        compositor_handle: compositor::Handle,
+        _: surface::Handle,
+        handle: xdg_shell_v6::Handle
+    ) {
+        self.notify(compositor_handle, handle, Event::NewPopup)
+    }
+
+    fn ping_timeout(
+        &mut self,
+        compositor_handle: compositor::Handle,
+        _: surface::Handle,
+        handle: xdg_shell_v6::Handle
+    ) {
+        self.notify(compositor_handle, handle, Event::PingTimeout)
+    }
+
+    fn maximize_request(
+        &mut self,
+        compositor_handle: compositor::Handle,
+        _: surface::Handle,
+        handle: xdg_shell_v6::Handle
+    ) {
+        let maximized = handle
+            .run(|shell_surface| match shell_surface.state() {
+                Some(&mut xdg_shell_v6::ShellState::TopLevel(ref toplevel)) => {
+                    toplevel.client_pending_state().maximized
+                },
+                _ => false
+            })
+            .unwrap_or(false);
+        self.notify(compositor_handle, handle, Event::MaximizeRequest(maximized))
+    }
+
+    fn minimize_request(
+        &mut self,
+        compositor_handle: compositor::Handle,
+        _: surface::Handle,
+        handle: xdg_shell_v6::Handle
+    ) {
+        self.notify(compositor_handle, handle, Event::MinimizeRequest)
+    }
+
+    fn move_request(
+        &mut self,
+        compositor_handle: compositor::Handle,
+        _: surface::Handle,
+        handle: xdg_shell_v6::Handle,
+        event: &xdg_shell_v6::event::Move
+    ) {
+        let request = GrabRequest {
+            seat: event.seat(),
+            serial: event.serial()
+        };
+        self.notify(compositor_handle, handle, Event::MoveRequest(Some(request)))
+    }
+
+    fn resize_request(
+        &mut self,
+        compositor_handle: compositor::Handle,
+        _: surface::Handle,
+        handle: xdg_shell_v6::Handle,
+        event: &xdg_shell_v6::event::Resize
+    ) {
+        let request = GrabRequest {
+            seat: event.seat(),
+            serial: event.serial()
+        };
+        self.notify(
+            compositor_handle,
+            handle,
+            Event::ResizeRequest(event.edges(), Some(request))
+        )
+    }
+
+    fn fullscreen_request(
+        &mut self,
+        compositor_handle: compositor::Handle,
+        _: surface::Handle,
+        handle: xdg_shell_v6::Handle,
+        event: &xdg_shell_v6::event::SetFullscreen
+    ) {
+        self.notify(
+            compositor_handle,
+            handle,
+            Event::FullscreenRequest(event.fullscreen())
+        )
+    }
+
+    fn show_window_menu_request(
+        &mut self,
+        compositor_handle: compositor::Handle,
+        _: surface::Handle,
+        handle: xdg_shell_v6::Handle,
+        event: &xdg_shell_v6::event::ShowWindowMenu
+    ) {
+        let (x, y) = event.coords();
+        self.notify(compositor_handle, handle, Event::ShowWindowMenuRequest(x, y))
+    }
+
+    fn map_request(
+        &mut self,
+        compositor_handle: compositor::Handle,
+        _: surface::Handle,
+        handle: xdg_shell_v6::Handle
+    ) {
+        self.notify(compositor_handle, handle, Event::Map)
+    }
+
+    fn unmap_request(
+        &mut self,
+        compositor_handle: compositor::Handle,
+        _: surface::Handle,
+        handle: xdg_shell_v6::Handle
+    ) {
+        self.notify(compositor_handle, handle, Event::Unmap)
+    }
+}
+
+/// Passes an event to a shared window handler.
+fn notify_shared(
+    handler: &RefCell<Box<Handler>>,
+    compositor_handle: compositor::Handle,
+    window_handle: Handle,
+    event: Event
+) {
+    match handler.try_borrow_mut() {
+        Ok(mut handler) => handler.on_event(compositor_handle, window_handle, event),
+        Err(_) => wlr_log!(
+            WLR_ERROR,
+            "Window handler is already borrowed, dropping {:?}",
+            event
+        )
+    }
+}
+
+impl xwayland::surface::Handler for XwaylandAdapter {
+    fn destroyed(
+        &mut self,
+        compositor_handle: compositor::Handle,
+        _: Option<surface::Handle>,
+        handle: xwayland::surface::Handle
+    ) {
+        notify_shared(
+            &self.handler,
+            compositor_handle,
+            Handle::Xwayland(handle),
+            Event::Destroyed
+        )
+    }
+
+    fn on_configure(
+        &mut self,
+        compositor_handle: compositor::Handle,
+        _: Option<surface::Handle>,
+        handle: xwayland::surface::Handle,
+        configure: &xwayland::event::Configure
+    ) {
+        let (x, y) = configure.coords();
+        let (width, height) = configure.dimensions();
+        let area = Area::new(
+            Origin::new(i32::from(x), i32::from(y)),
+            Size::new(i32::from(width), i32::from(height))
+        );
+        notify_shared(
+            &self.handler,
+            compositor_handle,
+            Handle::Xwayland(handle),
+            Event::ConfigureRequest(area)
+        )
+    }
+
+    fn on_move(
+        &mut self,
+        compositor_handle: compositor::Handle,
+        _: Option<surface::Handle>,
+        handle: xwayland::surface::Handle,
+        _: &xwayland::event::Move
+    ) {
+        notify_shared(
+            &self.handler,
+            compositor_handle,
+            Handle::Xwayland(handle),
+            Event::MoveRequest(None)
+        )
+    }
+
+    fn on_resize(
+        &mut self,
+        compositor_handle: compositor::Handle,
+        _: Option<surface::Handle>,
+        handle: xwayland::surface::Handle,
+        event: &xwayland::event::Resize
+    ) {
+        notify_shared(
+            &self.handler,
+            compositor_handle,
+            Handle::Xwayland(handle),
+            Event::ResizeRequest(event.edges(), None)
+        )
+    }
+
+    fn on_maximize(
+        &mut self,
+        compositor_handle: compositor::Handle,
+        _: Option<surface::Handle>,
+        handle: xwayland::surface::Handle
+    ) {
+        let maximized = handle
+            .run(|surface| surface.maximized_vert() && surface.maximized_horz())
+            .unwrap_or(false);
+        notify_shared(
+            &self.handler,
+            compositor_handle,
+            Handle::Xwayland(handle),
+            Event::MaximizeRequest(maximized)
+        )
+    }
+
+    fn on_fullscreen(
+        &mut self,
+        compositor_handle: compositor::Handle,
+        _: Option<surface::Handle>,
+        handle: xwayland::surface::Handle
+    ) {
+        let fullscreen = handle.run(|surface| surface.fullscreen()).unwrap_or(false);
+        notify_shared(
+            &self.handler,
+            compositor_handle,
+            Handle::Xwayland(handle),
+            Event::FullscreenRequest(fullscreen)
+        )
+    }
+
+    fn on_map(
+        &mut self,
+        compositor_handle: compositor::Handle,
+        _: Option<surface::Handle>,
+        handle: xwayland::surface::Handle
+    ) -> Option<Box<surface::Handler>> {
+        let window_handle = Handle::Xwayland(handle);
+        notify_shared(
+            &self.handler,
+            compositor_handle,
+            window_handle.clone(),
+            Event::Map
+        );
+        Some(Box::new(XwaylandSurfaceAdapter {
+            handler: self.handler.clone(),
+            window_handle
+        }))
+    }
+
+    fn on_unmap(
+        &mut self,
+        compositor_handle: compositor::Handle,
+        _: Option<surface::Handle>,
+        handle: xwayland::surface::Handle
+    ) {
+        notify_shared(
+            &self.handler,
+            compositor_handle,
+            Handle::Xwayland(handle),
+            Event::Unmap
+        )
+    }
+
+    fn title_set(
+        &mut self,
+        compositor_handle: compositor::Handle,
+        _: Option<surface::Handle>,
+        handle: xwayland::surface::Handle
+    ) {
+        notify_shared(
+            &self.handler,
+            compositor_handle,
+            Handle::Xwayland(handle),
+            Event::TitleChanged
+        )
+    }
+
+    fn class_set(
+        &mut self,
+        compositor_handle: compositor::Handle,
+        _: Option<surface::Handle>,
+        handle: xwayland::surface::Handle
+    ) {
+        notify_shared(
+            &self.handler,
+            compositor_handle,
+            Handle::Xwayland(handle),
+            Event::AppIdChanged
+        )
+    }
+
+    fn ping_timeout(
+        &mut self,
+        compositor_handle: compositor::Handle,
+        _: Option<surface::Handle>,
+        handle: xwayland::surface::Handle
+    ) {
+        notify_shared(
+            &self.handler,
+            compositor_handle,
+            Handle::Xwayland(handle),
+            Event::PingTimeout
+        )
+    }
+}
+
+impl surface::Handler for XwaylandSurfaceAdapter {
+    fn on_commit(&mut self, compositor_handle: compositor::Handle, _: surface::Handle) {
+        notify_shared(
+            &self.handler,
+            compositor_handle,
+            self.window_handle.clone(),
+            Event::Commit
+        )
+    }
+}
+
+impl Window for xdg_shell::TopLevel {
+    fn title(&self) -> Option<String> {
+        unsafe { c_to_rust_string((*self.as_ptr()).title) }
+    }
+
+    fn app_id(&self) -> Option<String> {
+        unsafe { c_to_rust_string((*self.as_ptr()).app_id) }
+    }
+
+    fn geometry(&self) -> Area {
+        unsafe {
+            let base = (*self.as_ptr()).base;
+            xdg_geometry(Area::from_box((*base).geometry), (*base).surface)
+        }
+    }
+
+    fn surface(&self) -> Option<surface::Handle> {
+        unsafe { surface_handle((*(*self.as_ptr()).base).surface) }
+    }
+
+    fn min_size(&self) -> Size {
+        let state = self.current_state();
+        Size::new(state.min_width as i32, state.min_height as i32)
+    }
+
+    fn max_size(&self) -> Size {
+        let state = self.current_state();
+        Size::new(state.max_width as i32, state.max_height as i32)
+    }
+
+    fn set_size(&mut self, width: u32, height: u32) -> Option<u32> {
+        Some(xdg_shell::TopLevel::set_size(self, width, height))
+    }
+
+    fn set_activated(&mut self, activated: bool) -> Option<u32> {
+        Some(xdg_shell::TopLevel::set_activated(self, activated))
+    }
+
+    fn set_maximized(&mut self, maximized: bool) -> Option<u32> {
+        Some(xdg_shell::TopLevel::set_maximized(self, maximized))
+    }
+
+    fn set_fullscreen(&mut self, fullscreen: bool) -> Option<u32> {
+        Some(xdg_shell::TopLevel::set_fullscreen(self, fullscreen))
+    }
+
+    fn set_resizing(&mut self, resizing: bool) -> Option<u32> {
+        Some(xdg_shell::TopLevel::set_resizing(self, resizing))
+    }
+
+    fn close(&mut self) {
+        xdg_shell::TopLevel::close(self)
+    }
+}
+
+impl Window for xdg_shell_v6::TopLevel {
+    fn title(&self) -> Option<String> {
+        unsafe { c_to_rust_string((*self.as_ptr()).title) }
+    }
+
+    fn app_id(&self) -> Option<String> {
+        unsafe { c_to_rust_string((*self.as_ptr()).app_id) }
+    }
+
+    fn geometry(&self) -> Area {
+        unsafe {
+            let base = (*self.as_ptr()).base;
+            xdg_geometry(Area::from_box((*base).geometry), (*base).surface)
+        }
+    }
+
+    fn surface(&self) -> Option<surface::Handle> {
+        unsafe { surface_handle((*(*self.as_ptr()).base).surface) }
+    }
+
+    fn min_size(&self) -> Size {
+        let state = self.current_state();
+        Size::new(state.min_width as i32, state.min_height as i32)
+    }
+
+    fn max_size(&self) -> Size {
+        let state = self.current_state();
+        Size::new(state.max_width as i32, state.max_height as i32)
+    }
+
+    fn set_size(&mut self, width: u32, height: u32) -> Option<u32> {
+        Some(xdg_shell_v6::TopLevel::set_size(self, width, height))
+    }
+
+    fn set_activated(&mut self, activated: bool) -> Option<u32> {
+        Some(xdg_shell_v6::TopLevel::set_activated(self, activated))
+    }
+
+    fn set_maximized(&mut self, maximized: bool) -> Option<u32> {
+        Some(xdg_shell_v6::TopLevel::set_maximized(self, maximized))
+    }
+
+    fn set_fullscreen(&mut self, fullscreen: bool) -> Option<u32> {
+        Some(xdg_shell_v6::TopLevel::set_fullscreen(self, fullscreen))
+    }
+
+    fn set_resizing(&mut self, resizing: bool) -> Option<u32> {
+        Some(xdg_shell_v6::TopLevel::set_resizing(self, resizing))
+    }
+
+    fn close(&mut self) {
+        xdg_shell_v6::TopLevel::close(self)
+    }
+}
+
+impl Window for xwayland::surface::Surface {
+    fn title(&self) -> Option<String> {
+        xwayland::surface::Surface::title(self)
+    }
+
+    fn app_id(&self) -> Option<String> {
+        self.class()
+    }
+
+    fn geometry(&self) -> Area {
+        xwayland::surface::Surface::geometry(self)
+    }
+
+    fn surface(&self) -> Option<surface::Handle> {
+        xwayland::surface::Surface::surface(self)
+    }
+
+    fn min_size(&self) -> Size {
+        unsafe {
+            if (*self.as_ptr()).size_hints.is_null() {
+                return Size::default();
+            }
+        }
+        let (width, height) = self.size_hints().min_dimensions();
+        Size::new(width.max(0), height.max(0))
+    }
+
+    fn max_size(&self) -> Size {
+        unsafe {
+            if (*self.as_ptr()).size_hints.is_null() {
+                return Size::default();
+            }
+        }
+        let (width, height) = self.size_hints().max_dimensions();
+        Size::new(width.max(0), height.max(0))
+    }
+
+    fn set_size(&mut self, width: u32, height: u32) -> Option<u32> {
+        let (x, y) = self.coords();
+        self.configure(x, y, x11_size(width), x11_size(height));
+        None
+    }
+
+    fn set_activated(&mut self, activated: bool) -> Option<u32> {
+        xwayland::surface::Surface::set_activated(self, activated);
+        None
+    }
+
+    fn set_maximized(&mut self, maximized: bool) -> Option<u32> {
+        xwayland::surface::Surface::set_maximized(self, maximized);
+        None
+    }
+
+    fn set_fullscreen(&mut self, fullscreen: bool) -> Option<u32> {
+        xwayland::surface::Surface::set_fullscreen(self, fullscreen);
+        None
+    }
+
+    fn set_resizing(&mut self, _: bool) -> Option<u32> {
+        None
+    }
+
+    fn close(&mut self) {
+        xwayland::surface::Surface::close(self)
+    }
+}
+
+/// Falls back to the size of the surface when an XDG surface did not set
+/// its window geometry.
+unsafe fn xdg_geometry(geometry: Area, surface: *mut wlr_surface) -> Area {
+    if geometry.size.width > 0 && geometry.size.height > 0 || surface.is_null() {
+        return geometry;
+    }
+    let current = (*surface).current;
+    Area::new(Origin::default(), Size::new(current.width, current.height))
+}
+
+unsafe fn surface_handle(surface: *mut wlr_surface) -> Option<surface::Handle> {
+    if surface.is_null() {
+        None
+    } else {
+        Some(surface::Handle::from_ptr(surface))
+    }
+}
+
+/// Clamps a coordinate to the 16 bits X11 uses.
+fn x11_coord(value: i32) -> i16 {
+    value
+        .max(i32::from(i16::min_value()))
+        .min(i32::from(i16::max_value())) as i16
+}
+
+/// Clamps a size to the 16 bits X11 uses.
+fn x11_size(value: u32) -> u16 {
+    value.min(u32::from(u16::max_value())) as u16
+}
@@ -9,11 +9,14 @@ use std::{
 
 use crate::libc::c_void;
 use wlroots_sys::{
-    wlr_surface, wlr_xdg_popup_v6, wlr_xdg_surface_v6, wlr_xdg_surface_v6_for_each_surface,
-    wlr_xdg_surface_v6_ping, wlr_xdg_surface_v6_role, wlr_xdg_surface_v6_send_close,
-    wlr_xdg_surface_v6_surface_at, wlr_xdg_toplevel_v6, wlr_xdg_toplevel_v6_set_activated,
-    wlr_xdg_toplevel_v6_set_fullscreen, wlr_xdg_toplevel_v6_set_maximized, wlr_xdg_toplevel_v6_set_resizing,
-    wlr_xdg_toplevel_v6_set_size, wlr_xdg_toplevel_v6_state
+    wlr_box, wlr_positioner_v6_invert_x, wlr_positioner_v6_invert_y, wlr_surface, wlr_xdg_popup_v6,
+    wlr_xdg_popup_v6_get_anchor_point, wlr_xdg_popup_v6_get_toplevel_coords,
+    wlr_xdg_popup_v6_unconstrain_from_box, wlr_xdg_positioner_v6, wlr_xdg_positioner_v6_get_geometry,
+    wlr_xdg_surface_v6, wlr_xdg_surface_v6_for_each_surface, wlr_xdg_surface_v6_ping,
+    wlr_xdg_surface_v6_role, wlr_xdg_surface_v6_send_close, wlr_xdg_surface_v6_surface_at,
+    wlr_xdg_toplevel_v6, wlr_xdg_toplevel_v6_set_activated, wlr_xdg_toplevel_v6_set_fullscreen,
+    wlr_xdg_toplevel_v6_set_maximized, wlr_xdg_toplevel_v6_set_resizing, wlr_xdg_toplevel_v6_set_size,
+    wlr_xdg_toplevel_v6_state
 };
 
 pub use crate::events::xdg_shell_v6_events as event;
@@ -21,16 +24,16 @@ pub use crate::manager::xdg_shell_v6_handler::*;
 pub(crate) use crate::manager::xdg_shell_v6_manager::Manager;
 pub use crate::manager::xdg_shell_v6_manager::NewSurfaceResult;
 use crate::{
-    area::Area,
+    area::{Area, Origin, Size},
     seat, surface,
-    utils::{self, c_to_rust_string, HandleErr, HandleResult, Handleable}
+    utils::{self, c_to_rust_string, HandleErr, HandleResult, Handleable, UserData}
 };
 
 pub mod manager {
     //! XDG shell v6 resources are managed by the XDG shell v6 resource manager.
     //!
     //! To manage XDG shells v6 from clients implement a function with
-    //! [`NewSurface`](./type.NewSurface.html) as the signature.
+    //! [`NewSurface`](./trait.NewSurface.html) as the signature.
     //!
     //! Pass that function to the
     //! [`xdg_shell_v6::Builder`](./struct.Builder.html) which is then
@@ -61,7 +64,8 @@ impl Clone for OptionalShellState {
 pub(crate) struct SurfaceState {
     pub(crate) shell: Option<NonNull<XdgShellV6>>,
     handle: Weak<Cell<bool>>,
-    shell_state: Option<ShellState>
+    shell_state: Option<ShellState>,
+    user_data: UserData
 }
 
 #[derive(Debug, Eq, PartialEq, Hash)]
@@ -76,6 +80,12 @@ pub struct Popup {
     popup: NonNull<wlr_xdg_popup_v6>
 }
 
+/// The rules the client gave for placing a popup relative to its parent.
+#[derive(Debug, Clone, Copy)]
+pub struct Positioner {
+    positioner: wlr_xdg_positioner_v6
+}
+
 /// A tagged enum of the different roles used by the xdg shell.
 ///
 /// Uses the tag to disambiguate the union in `wlr_xdg_surface_v6`.
@@ -108,7 +118,8 @@ impl Surface {
             shell_state: match state {
                 None => None,
                 Some(ref state) => Some(state.clone())
-            }
+            },
+            user_data: UserData::new()
         });
         (*shell_surface.as_ptr()).data = Box::into_raw(shell_state) as *mut _;
         Surface {
@@ -138,6 +149,20 @@ impl Surface {
         self.state.as_mut()
     }
 
+    /// Gets the user data attached to the XDG shell surface.
+    ///
+    /// It is dropped when the XDG shell surface is destroyed.
+    pub fn user_data(&self) -> &UserData {
+        unsafe { &(*((*self.shell_surface.as_ptr()).data as *mut SurfaceState)).user_data }
+    }
+
+    /// Gets the user data attached to the XDG shell surface mutably.
+    ///
+    /// It is dropped when the XDG shell surface is destroyed.
+    pub fn user_data_mut(&mut self) -> &mut UserData {
+        unsafe { &mut (*((*self.shell_surface.as_ptr()).data as *mut SurfaceState)).user_data }
+    }
+
     /// Determines if this XDG shell surface has been configured or not.
     pub fn configured(&self) -> bool {
         unsafe { (*self.shell_surface.as_ptr()).configured }
@@ -435,9 +460,110 @@ impl Popup {
         }
     }
 
+    /// Get the geometry of the popup, relative to its parent.
+    ///
+    /// This is where the client asked for the popup to be placed, until
+    /// `unconstrain` adjusts it.
     pub fn geometry(&self) -> Area {
         unsafe { Area::from_box((*self.popup.as_ptr()).geometry) }
     }
+
+    /// Get the rules the client gave for placing the popup.
+    pub fn positioner(&self) -> Positioner {
+        unsafe {
+            Positioner {
+                positioner: (*self.popup.as_ptr()).positioner
+            }
+        }
+    }
+
+    /// Get the anchor point of the popup, relative to its toplevel.
+    pub fn anchor_point(&self) -> (i32, i32) {
+        unsafe {
+            let (mut x, mut y) = (0, 0);
+            wlr_xdg_popup_v6_get_anchor_point(self.popup.as_ptr(), &mut x, &mut y);
+            (x, y)
+        }
+    }
+
+    /// Converts coordinates local to the popup to coordinates relative to
+    /// its toplevel.
+    pub fn toplevel_coords(&self, popup_sx: i32, popup_sy: i32) -> (i32, i32) {
+        unsafe {
+            let (mut x, mut y) = (0, 0);
+            wlr_xdg_popup_v6_get_toplevel_coords(self.popup.as_ptr(), popup_sx, popup_sy, &mut x, &mut y);
+            (x, y)
+        }
+    }
+
+    /// Moves, flips or resizes the popup as its constraint adjustment allows
+    /// so that it fits in the given area, relative to its toplevel.
+    ///
+    /// The area is usually the box of the output the toplevel is on, from
+    /// `output::layout::Layout::get_box`, offset by the toplevel's layout
+    /// position. The new placement is sent to the client with the popup's
+    /// next configure, and `geometry` returns it from then on.
+    pub fn unconstrain(&mut self, toplevel_area: Area) {
+        let mut toplevel_box: wlr_box = toplevel_area.into();
+        unsafe { wlr_xdg_popup_v6_unconstrain_from_box(self.popup.as_ptr(), &mut toplevel_box) }
+    }
+}
+
+impl Positioner {
+    /// Get the rectangle the popup is anchored to, relative to the parent's
+    /// window geometry.
+    pub fn anchor_rect(&self) -> Area {
+        Area::from_box(self.positioner.anchor_rect)
+    }
+
+    /// Get the edges of the anchor rectangle the popup is anchored to.
+    ///
+    /// This is a bitmask of `zxdg_positioner_v6_anchor` values.
+    pub fn anchor(&self) -> u32 {
+        self.positioner.anchor as u32
+    }
+
+    /// Get the direction the popup extends in from its anchor point.
+    ///
+    /// This is a bitmask of `zxdg_positioner_v6_gravity` values.
+    pub fn gravity(&self) -> u32 {
+        self.positioner.gravity as u32
+    }
+
+    /// Get how the popup may be moved or resized when it doesn't fit.
+    ///
+    /// This is a bitmask of `zxdg_positioner_v6_constraint_adjustment`
+    /// values.
+    pub fn constraint_adjustment(&self) -> u32 {
+        self.positioner.constraint_adjustment as u32
+    }
+
+    /// Get the size the popup should have.
+    pub fn size(&self) -> Size {
+        Size::new(self.positioner.size.width, self.positioner.size.height)
+    }
+
+    /// Get the offset of the popup from its anchor point.
+    pub fn offset(&self) -> Origin {
+        Origin::new(self.positioner.offset.x, self.positioner.offset.y)
+    }
+
+    /// Get the geometry of the popup placed by these rules without taking
+    /// any constraints into account, relative to the parent.
+    pub fn geometry(&self) -> Area {
+        let mut positioner = self.positioner;
+        unsafe { Area::from_box(wlr_xdg_positioner_v6_get_geometry(&mut positioner)) }
+    }
+
+    /// Flips the anchor and gravity of the rules horizontally.
+    pub fn invert_x(&mut self) {
+        unsafe { wlr_positioner_v6_invert_x(&mut self.positioner) }
+    }
+
+    /// Flips the anchor and gravity of the rules vertically.
+    pub fn invert_y(&mut self) {
+        unsafe { wlr_positioner_v6_invert_y(&mut self.positioner) }
+    }
 }
 
 impl ShellState {
@@ -1,8 +1,10 @@
+mod output_tracker;
 pub mod subsurface;
 pub(crate) mod subsurface_manager;
 #[allow(clippy::module_inception)]
 mod surface;
 mod surface_state;
 
+pub use self::output_tracker::*;
 pub use self::surface::*;
 pub use self::surface_state::*;
@@ -0,0 +1,297 @@
+//! Keeps track of which outputs surfaces are shown on.
+
+use std::{cell::RefCell, rc::Rc, time::Duration};
+
+use crate::libc;
+use crate::wayland_sys::server::{signal::wl_signal_add, WAYLAND_SERVER_HANDLE};
+
+use crate::{
+    area::{Area, Origin},
+    output::{self, layout, Output},
+    surface,
+    utils::{current_time, Handleable}
+};
+
+/// Sends surfaces the output enter, output leave and frame done events
+/// based on where the compositor places them in an output layout.
+///
+/// Clients use the enter and leave events to pick the scale of their
+/// buffers, and wait for frame done before drawing their next frame.
+///
+/// Frame done is sent automatically once `output::Handler::on_frame` returns,
+/// to the surfaces shown on that output.
+///
+/// Events are also sent to the subsurfaces of tracked surfaces.
+pub struct OutputTracker {
+    layout: layout::Handle,
+    surfaces: Rc<RefCell<Vec<TrackedSurface>>>,
+    /// Listeners on the frame events of the outputs in the layout.
+    listeners: Vec<Box<FrameListener>>
+}
+
+#[derive(Debug)]
+struct TrackedSurface {
+    surface: surface::Handle,
+    /// Where the surface is, in output layout coordinates.
+    area: Area,
+    /// The outputs the surface has been sent an enter event for.
+    outputs: Vec<output::Handle>
+}
+
+struct FrameDone {
+    layout: layout::Handle,
+    surfaces: Rc<RefCell<Vec<TrackedSurface>>>,
+    output: output::Handle,
+    alive: bool
+}
+
+wayland_listener!(FrameListener, FrameDone, [
+    frame_listener => frame_notify: |this: &mut FrameListener, _data: *mut libc::c_void,| unsafe {
+        let FrameDone { ref layout, ref surfaces, ref output, .. } = this.data;
+        let mut surfaces = match surfaces.try_borrow_mut() {
+            Ok(surfaces) => surfaces,
+            Err(_) => {
+                wlr_log!(WLR_ERROR, "Output tracker was borrowed during a frame event");
+                return
+            }
+        };
+        output.run(|output| frame_done(layout, &mut surfaces, output, current_time())).ok();
+    };
+    destroy_listener => destroy_notify: |this: &mut FrameListener, _data: *mut libc::c_void,| unsafe {
+        this.data.alive = false;
+        ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                      wl_list_remove,
+                      &mut (*this.frame_listener()).link as *mut _ as _);
+        ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                      wl_list_remove,
+                      &mut (*this.destroy_listener()).link as *mut _ as _);
+    };
+]);
+
+impl Drop for FrameListener {
+    fn drop(&mut self) {
+        if !self.data.alive {
+            return;
+        }
+        unsafe {
+            ffi_dispatch!(
+                WAYLAND_SERVER_HANDLE,
+                wl_list_remove,
+                &mut (*self.frame_listener()).link as *mut _ as _
+            );
+            ffi_dispatch!(
+                WAYLAND_SERVER_HANDLE,
+                wl_list_remove,
+                &mut (*self.destroy_listener()).link as *mut _ as _
+            );
+        }
+    }
+}
+
+impl OutputTracker {
+    /// Makes a tracker for surfaces placed in the given output layout.
+    pub fn new(layout: layout::Handle) -> Self {
+        OutputTracker {
+            layout,
+            surfaces: Rc::new(RefCell::new(Vec::new())),
+            listeners: Vec::new()
+        }
+    }
+
+    /// Sets where the surface is in output layout coordinates, sending it
+    /// enter and leave events for the outputs it started or stopped
+    /// intersecting.
+    ///
+    /// The surface is tracked from now on if it wasn't already.
+    ///
+    /// Outputs that are borrowed (e.g. because this is called from their
+    /// `on_frame` callback) are checked when they send frame done.
+    pub fn place(&mut self, surface: surface::Handle, area: Area) {
+        let index = {
+            let mut surfaces = self.surfaces.borrow_mut();
+            match surfaces.iter().position(|tracked| tracked.surface == surface) {
+                Some(index) => index,
+                None => {
+                    surfaces.push(TrackedSurface {
+                        surface,
+                        area,
+                        outputs: Vec::new()
+                    });
+                    surfaces.len() - 1
+                }
+            }
+        };
+        self.surfaces.borrow_mut()[index].area = area;
+        self.update_surface(index);
+    }
+
+    /// Stops tracking the surface, sending it leave events for every output
+    /// it was on.
+    pub fn remove(&mut self, surface: &surface::Handle) {
+        let mut surfaces = self.surfaces.borrow_mut();
+        let index = match surfaces.iter().position(|tracked| tracked.surface == *surface) {
+            Some(index) => index,
+            None => return
+        };
+        let tracked = surfaces.remove(index);
+        for output_handle in &tracked.outputs {
+            output_handle
+                .run(|output| send_to_tree(&tracked.surface, |surface| surface.send_leave(output)))
+                .ok();
+        }
+    }
+
+    /// Recomputes the outputs of every tracked surface.
+    ///
+    /// Call this after outputs have been added to, moved in or removed from
+    /// the output layout.
+    pub fn update(&mut self) {
+        self.surfaces
+            .borrow_mut()
+            .retain(|tracked| tracked.surface.is_alive());
+        let len = self.surfaces.borrow().len();
+        for index in 0..len {
+            self.update_surface(index);
+        }
+    }
+
+    /// Gets the outputs the surface is shown on.
+    pub fn outputs(&self, surface: &surface::Handle) -> Vec<output::Handle> {
+        self.surfaces
+            .borrow()
+            .iter()
+            .find(|tracked| tracked.surface == *surface)
+            .map(|tracked| tracked.outputs.clone())
+            .unwrap_or_else(Vec::new)
+    }
+
+    fn update_surface(&mut self, index: usize) {
+        let layout_outputs = match self.layout.run(|layout| layout.outputs()) {
+            Ok(outputs) => outputs,
+            Err(_) => return
+        };
+        self.listen_to(&layout_outputs);
+        let layout = &self.layout;
+        let mut surfaces = self.surfaces.borrow_mut();
+        let tracked = &mut surfaces[index];
+        // Outputs that were removed from the layout no longer show the surface.
+        let removed = tracked
+            .outputs
+            .iter()
+            .filter(|output_handle| !layout_outputs.iter().any(|(handle, _)| handle == *output_handle))
+            .cloned()
+            .collect::<Vec<_>>();
+        for output_handle in removed {
+            match output_handle.run(|output| set_entered(tracked, output, &output_handle, false)) {
+                Ok(()) => {},
+                Err(_) => tracked.outputs.retain(|handle| *handle != output_handle)
+            }
+        }
+        let area = tracked.area;
+        for (output_handle, _) in layout_outputs {
+            output_handle
+                .run(|output| {
+                    if let Ok(intersects) = layout.run(|layout| layout.intersects(output, area)) {
+                        set_entered(tracked, output, &output_handle, intersects)
+                    }
+                })
+                .ok();
+        }
+    }
+
+    /// Listens to the frame events of the outputs in the layout, and only to
+    /// those.
+    fn listen_to(&mut self, layout_outputs: &[(output::Handle, Origin)]) {
+        self.listeners.retain(|listener| {
+            listener.data.alive &&
+                layout_outputs
+                    .iter()
+                    .any(|(handle, _)| *handle == listener.data.output)
+        });
+        for (output_handle, _) in layout_outputs {
+            if self
+                .listeners
+                .iter()
+                .any(|listener| listener.data.output == *output_handle)
+            {
+                continue;
+            }
+            let mut listener = FrameListener::new(FrameDone {
+                layout: self.layout.clone(),
+                surfaces: self.surfaces.clone(),
+                output: output_handle.clone(),
+                alive: true
+            });
+            // NOTE Added after the listener of the output's handler, so frame
+            // done is sent once `on_frame` returned.
+            unsafe {
+                let output_ptr = output_handle.as_ptr();
+                wl_signal_add(
+                    &mut (*output_ptr).events.frame as *mut _ as _,
+                    listener.frame_listener() as _
+                );
+                wl_signal_add(
+                    &mut (*output_ptr).events.destroy as *mut _ as _,
+                    listener.destroy_listener() as _
+                );
+            }
+            self.listeners.push(listener);
+        }
+    }
+}
+
+/// Sends frame done to every tracked surface shown on the output.
+///
+/// The enter and leave events for this output are brought up to date first.
+fn frame_done(
+    layout: &layout::Handle,
+    surfaces: &mut Vec<TrackedSurface>,
+    output: &mut Output,
+    when: Duration
+) {
+    surfaces.retain(|tracked| tracked.surface.is_alive());
+    let output_handle = output.weak_reference();
+    for tracked in surfaces {
+        let area = tracked.area;
+        let intersects = layout
+            .run(|layout| layout.intersects(output, area))
+            .unwrap_or_else(|_| tracked.outputs.contains(&output_handle));
+        set_entered(tracked, output, &output_handle, intersects);
+        if intersects {
+            send_to_tree(&tracked.surface, |surface| surface.send_frame_done(when));
+        }
+    }
+}
+
+/// Sends the surface an enter or leave event for the output if it changed.
+fn set_entered(
+    tracked: &mut TrackedSurface,
+    output: &mut Output,
+    output_handle: &output::Handle,
+    entered: bool
+) {
+    if tracked.outputs.contains(output_handle) == entered {
+        return;
+    }
+    if entered {
+        send_to_tree(&tracked.surface, |surface| surface.send_enter(output));
+        tracked.outputs.push(output_handle.clone());
+    } else {
+        send_to_tree(&tracked.surface, |surface| surface.send_leave(output));
+        tracked.outputs.retain(|handle| handle != output_handle);
+    }
+}
+
+/// Runs the function on the surface and each of its subsurfaces.
+fn send_to_tree<F>(surface_handle: &surface::Handle, mut func: F)
+where
+    F: FnMut(&mut surface::Surface)
+{
+    let mut tree = Vec::new();
+    surface_handle
+        .run(|surface| surface.for_each_surface(|surface_handle, _, _| tree.push(surface_handle)))
+        .ok();
+    for surface_handle in tree {
+        surface_handle.run(|surface| func(surface)).ok();
+    }
+}
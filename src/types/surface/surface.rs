@@ -1,6 +1,6 @@
 //! TODO Documentation
 
-use crate::libc::{self, c_double, clock_t};
+use crate::libc::{self, c_double, c_void, clock_t};
 use std::{
     cell::Cell,
     panic,
@@ -12,9 +12,10 @@ use std::{
 use crate::wayland_sys::server::signal::wl_signal_add;
 use crate::wayland_sys::server::WAYLAND_SERVER_HANDLE;
 use wlroots_sys::{
-    timespec, wlr_subsurface, wlr_surface, wlr_surface_get_root_surface, wlr_surface_get_texture,
-    wlr_surface_has_buffer, wlr_surface_is_xdg_surface, wlr_surface_point_accepts_input,
-    wlr_surface_send_enter, wlr_surface_send_frame_done, wlr_surface_send_leave, wlr_surface_surface_at
+    timespec, wlr_subsurface, wlr_surface, wlr_surface_for_each_surface, wlr_surface_get_root_surface,
+    wlr_surface_get_texture, wlr_surface_has_buffer, wlr_surface_is_xdg_surface,
+    wlr_surface_point_accepts_input, wlr_surface_send_enter, wlr_surface_send_frame_done,
+    wlr_surface_send_leave, wlr_surface_surface_at
 };
 
 use crate::{
@@ -26,7 +27,7 @@ use crate::{
         subsurface::{self, InternalSubsurface, Subsurface},
         subsurface_manager::SubsurfaceManager
     },
-    utils::{self, c_to_rust_string, HandleErr, HandleResult, Handleable}
+    utils::{self, c_to_rust_string, HandleErr, HandleResult, Handleable, UserData}
 };
 
 pub type Handle = utils::Handle<Weak<Box<SubsurfaceManager>>, wlr_surface, Surface>;
@@ -111,7 +112,9 @@ pub(crate) struct InternalState {
     /// Weak reference to the manager for the list of subsurfaces.
     /// This is here so that we can reconstruct the Surface from a
     /// surface::Handle.
-    subsurfaces_manager: Weak<Box<SubsurfaceManager>>
+    subsurfaces_manager: Weak<Box<SubsurfaceManager>>,
+    /// The user data attached to the surface.
+    user_data: UserData
 }
 
 /// A Wayland object that represents the data that we display on the screen.
@@ -155,7 +158,8 @@ impl Surface {
         let state = InternalState {
             surface: None,
             handle,
-            subsurfaces_manager: weak_manager
+            subsurfaces_manager: weak_manager,
+            user_data: UserData::new()
         };
         (*surface.as_ptr()).data = Box::into_raw(Box::new(state)) as _;
         Surface {
@@ -208,6 +212,20 @@ impl Surface {
         self.subsurfaces_manager.subsurfaces()
     }
 
+    /// Gets the user data attached to the surface.
+    ///
+    /// It is dropped when the surface is destroyed.
+    pub fn user_data(&self) -> &UserData {
+        unsafe { &(*((*self.surface.as_ptr()).data as *mut InternalState)).user_data }
+    }
+
+    /// Gets the user data attached to the surface mutably.
+    ///
+    /// It is dropped when the surface is destroyed.
+    pub fn user_data_mut(&mut self) -> &mut UserData {
+        unsafe { &mut (*((*self.surface.as_ptr()).data as *mut InternalState)).user_data }
+    }
+
     /// Get the texture of this surface.
     ///
     /// Returns None if no buffer is currently attached or if something went
@@ -269,6 +287,31 @@ impl Surface {
         }
     }
 
+    /// Call the iterator for this surface and each of its subsurfaces, with
+    /// the surface-local coordinates of each surface relative to this one.
+    ///
+    /// Surfaces are visited from bottom to top.
+    pub fn for_each_surface<F>(&self, mut iterator: F)
+    where
+        F: FnMut(Handle, i32, i32)
+    {
+        let mut iterator_ref: &mut FnMut(Handle, i32, i32) = &mut iterator;
+        unsafe {
+            unsafe extern "C" fn c_iterator(
+                wlr_surface: *mut wlr_surface,
+                sx: i32,
+                sy: i32,
+                data: *mut c_void
+            ) {
+                let iterator_fn = &mut *(data as *mut &mut FnMut(Handle, i32, i32));
+                let surface = Handle::from_ptr(wlr_surface);
+                iterator_fn(surface, sx, sy);
+            }
+            let iterator_ptr: *mut c_void = &mut iterator_ref as *mut _ as *mut c_void;
+            wlr_surface_for_each_surface(self.surface.as_ptr(), Some(c_iterator), iterator_ptr);
+        }
+    }
+
     /// Get the top of the subsurface tree for this surface.
     pub fn get_root_surface(&self) -> Option<Handle> {
         unsafe {
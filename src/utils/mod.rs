@@ -9,10 +9,12 @@ pub mod region;
 mod handle;
 mod string;
 mod time;
+mod user_data;
 
 pub use self::handle::*;
 pub(crate) use self::string::{c_to_rust_string, safe_as_cstring};
 pub use self::time::{current_time, ToMs};
+pub use self::user_data::UserData;
 
 /// Handle unwinding from a panic, used in conjunction with
 /// `::std::panic::catch_unwind`.
@@ -24,10 +26,11 @@ pub(crate) unsafe fn handle_unwind<T>(res: ::std::thread::Result<T>) {
     match res {
         Ok(_) => {},
         Err(err) => {
-            if crate::compositor::COMPOSITOR_PTR == 0 as *mut _ {
+            let compositor = crate::compositor::compositor_ptr();
+            if compositor.is_null() {
                 ::std::process::abort();
             }
-            (&mut *crate::compositor::COMPOSITOR_PTR).save_panic_error(err);
+            (&mut *compositor).save_panic_error(err);
             crate::compositor::terminate()
         }
     }
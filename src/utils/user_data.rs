@@ -0,0 +1,139 @@
+//! Typed user data attached to wlroots objects.
+
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    fmt
+};
+
+/// User data attached to a wlroots object, holding at most one value of each
+/// type.
+///
+/// The values are dropped along with the object they are attached to, e.g.
+/// when a client destroys its surface or when an output is unplugged:
+///
+/// ```rust,no_run,ignore
+/// struct Workspace(u32);
+///
+/// output_handle.run(|output| output.user_data_mut().insert(Workspace(1))).unwrap();
+/// // Later on
+/// output_handle.run(|output| output.user_data().get::<Workspace>().map(|workspace| workspace.0))
+/// ```
+#[derive(Default)]
+pub struct UserData {
+    values: HashMap<TypeId, Box<Any>>
+}
+
+impl UserData {
+    pub fn new() -> Self {
+        UserData::default()
+    }
+
+    /// Attaches a value, replacing the value of the same type if there was
+    /// one.
+    ///
+    /// Returns the replaced value.
+    pub fn insert<T: Any>(&mut self, value: T) -> Option<T> {
+        self.values
+            .insert(TypeId::of::<T>(), Box::new(value))
+            .and_then(|value| value.downcast().ok())
+            .map(|value| *value)
+    }
+
+    /// Gets the value of type `T`, if there is one.
+    pub fn get<T: Any>(&self) -> Option<&T> {
+        self.values
+            .get(&TypeId::of::<T>())
+            .and_then(|value| value.downcast_ref())
+    }
+
+    /// Gets the value of type `T` mutably, if there is one.
+    pub fn get_mut<T: Any>(&mut self) -> Option<&mut T> {
+        self.values
+            .get_mut(&TypeId::of::<T>())
+            .and_then(|value| value.downcast_mut())
+    }
+
+    /// Gets the value of type `T`, attaching the one made by `default` first
+    /// if there is none.
+    pub fn get_or_insert_with<T: Any, F>(&mut self, default: F) -> &mut T
+    where
+        F: FnOnce() -> T
+    {
+        self.values
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(default()))
+            .downcast_mut()
+            .expect("User data was stored with the wrong type")
+    }
+
+    /// Detaches the value of type `T`, if there is one.
+    pub fn remove<T: Any>(&mut self) -> Option<T> {
+        self.values
+            .remove(&TypeId::of::<T>())
+            .and_then(|value| value.downcast().ok())
+            .map(|value| *value)
+    }
+
+    /// Determines if there is a value of type `T`.
+    pub fn contains<T: Any>(&self) -> bool {
+        self.values.contains_key(&TypeId::of::<T>())
+    }
+
+    /// Drops every value.
+    pub fn clear(&mut self) {
+        self.values.clear()
+    }
+}
+
+impl fmt::Debug for UserData {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "UserData with {} values", self.values.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Workspace(u32);
+
+    #[test]
+    fn insert() {
+        let mut user_data = UserData::new();
+        assert_eq!(user_data.insert(Workspace(1)), None);
+        assert_eq!(user_data.insert(5u8), None);
+        assert_eq!(user_data.insert(Workspace(2)), Some(Workspace(1)));
+        assert_eq!(user_data.get::<Workspace>(), Some(&Workspace(2)));
+        assert_eq!(user_data.get::<u8>(), Some(&5));
+        assert_eq!(user_data.get::<u16>(), None);
+        *user_data.get_mut::<u8>().unwrap() += 1;
+        assert_eq!(user_data.get::<u8>(), Some(&6));
+    }
+
+    #[test]
+    fn get_or_insert_with() {
+        let mut user_data = UserData::new();
+        user_data.get_or_insert_with(|| Workspace(1)).0 += 1;
+        assert_eq!(user_data.get::<Workspace>(), Some(&Workspace(2)));
+        let workspace = user_data.get_or_insert_with::<Workspace, _>(|| panic!("Value was attached already"));
+        assert_eq!(*workspace, Workspace(2));
+    }
+
+    #[test]
+    fn remove_and_contains() {
+        let mut user_data = UserData::new();
+        assert!(!user_data.contains::<Workspace>());
+        assert_eq!(user_data.remove::<Workspace>(), None);
+        user_data.insert(Workspace(1));
+        user_data.insert(5u8);
+        assert!(user_data.contains::<Workspace>());
+        assert_eq!(user_data.remove::<Workspace>(), Some(Workspace(1)));
+        assert!(!user_data.contains::<Workspace>());
+        assert_eq!(user_data.remove::<Workspace>(), None);
+        assert!(user_data.contains::<u8>());
+        user_data.clear();
+        assert!(!user_data.contains::<u8>());
+    }
+}
@@ -2,7 +2,7 @@
 //! and server.
 //!
 //! To manage XWayland clients (and run an XServer) implement a function
-//! with [`NewSurface`](./type.NewSurface.html) as the signature.
+//! with [`NewSurface`](./trait.NewSurface.html) as the signature.
 //!
 //! Pass that function to the [`xwayland::Builder`](./struct.Builder.html)
 //! which is then passed to the `compositor::Builder`.
@@ -15,18 +15,21 @@ use wlroots_sys::wlr_xwayland_surface;
 
 use crate::{compositor, utils::Handleable, xwayland};
 
-/// Callback that's triggered when the XWayland library is ready.
-pub type OnReady = fn(compositor::Handle);
+callback_trait! {
+    /// Callback that's triggered when the XWayland library is ready.
+    pub trait OnReady = FnMut(compositor_handle: compositor::Handle);
+}
 
-/// Callback that's triggered when a new surface is presented to the X
-/// server.
-pub type NewSurface = fn(
-    compositor_handle: compositor::Handle,
-    xwayland_surface: xwayland::surface::Handle
-) -> Option<Box<xwayland::surface::Handler>>;
+callback_trait! {
+    /// Callback that's triggered when a new surface is presented to the X
+    /// server.
+    pub trait NewSurface = FnMut(
+        compositor_handle: compositor::Handle,
+        xwayland_surface: xwayland::surface::Handle
+    ) -> Option<Box<xwayland::surface::Handler>>;
+}
 
 wayland_listener_static! {
-    static mut MANAGER;
     (Manager, Builder): [
         (OnReady, on_ready_listener, xwayland_ready) => (ready_notify, xwayland_ready):
         |manager: &mut Manager, _data: *mut libc::c_void,|
@@ -36,7 +39,7 @@ wayland_listener_static! {
                 None => return
             };
 
-            if let Some(xwayland_ready) = manager.xwayland_ready {
+            if let Some(ref mut xwayland_ready) = manager.xwayland_ready {
                 xwayland_ready(compositor)
             }
         };
@@ -50,7 +53,7 @@ wayland_listener_static! {
                 None => return
             };
             let shell_surface = xwayland::surface::Surface::new(surface_ptr);
-            let xwayland_handler = manager.surface_added
+            let xwayland_handler = manager.surface_added.as_mut()
                 .and_then(|f| f(compositor, shell_surface.weak_reference()));
             let mut shell = xwayland::surface::Shell::new((shell_surface, xwayland_handler));
 
@@ -10,7 +10,7 @@ use crate::xwayland;
 #[allow(dead_code)]
 pub struct Server {
     xwayland: *mut wlr_xwayland,
-    manager: &'static mut xwayland::manager::Manager
+    manager: Box<xwayland::manager::Manager>
 }
 
 impl Server {
@@ -20,11 +20,22 @@ impl Server {
         builder: xwayland::manager::Builder,
         lazy: bool
     ) -> Self {
+        Server::try_new(display, compositor, builder, lazy)
+            .unwrap_or_else(|| panic!("Could not start XWayland server"))
+    }
+
+    /// Like `new`, but returns `None` if the server could not be started.
+    pub(crate) unsafe fn try_new(
+        display: *mut wl_display,
+        compositor: *mut wlr_compositor,
+        builder: xwayland::manager::Builder,
+        lazy: bool
+    ) -> Option<Self> {
         let xwayland = wlr_xwayland_create(display, compositor, lazy);
         if xwayland.is_null() {
-            panic!("Could not start XWayland server")
+            return None;
         }
-        let manager = xwayland::manager::Manager::build(builder);
+        let mut manager = xwayland::manager::Manager::build(builder);
         wl_signal_add(
             &mut (*xwayland).events.ready as *mut _ as _,
             (&mut manager.on_ready_listener) as *mut _ as _
@@ -33,7 +44,7 @@ impl Server {
             &mut (*xwayland).events.new_surface as *mut _ as _,
             (&mut manager.new_surface_listener) as *mut _ as _
         );
-        Server { xwayland, manager }
+        Some(Server { xwayland, manager })
     }
 
     /// Get the PID of the XWayland server.
@@ -86,6 +97,9 @@ impl Server {
 
 impl Drop for Server {
     fn drop(&mut self) {
-        unsafe { wlr_xwayland_destroy(self.xwayland) }
+        unsafe {
+            self.manager.remove_listeners();
+            wlr_xwayland_destroy(self.xwayland)
+        }
     }
 }
@@ -8,8 +8,9 @@ use crate::libc::{self, int16_t, size_t, uint16_t};
 
 use crate::wayland_sys::server::WAYLAND_SERVER_HANDLE;
 use wlroots_sys::{
-    pid_t, wl_event_source, wlr_xwayland_surface, wlr_xwayland_surface_activate,
-    wlr_xwayland_surface_configure, xcb_atom_t, xcb_window_t
+    pid_t, wl_event_source, wlr_xwayland_surface, wlr_xwayland_surface_activate, wlr_xwayland_surface_close,
+    wlr_xwayland_surface_configure, wlr_xwayland_surface_set_fullscreen, wlr_xwayland_surface_set_maximized,
+    xcb_atom_t, xcb_window_t
 };
 
 pub use crate::xwayland::hints::{Hints, SizeHints};
@@ -17,7 +18,7 @@ use crate::{
     area::{Area, Origin, Size},
     compositor,
     surface::{self, InternalState},
-    utils::{self, c_to_rust_string, HandleErr, HandleResult, Handleable},
+    utils::{self, c_to_rust_string, HandleErr, HandleResult, Handleable, UserData},
     xwayland
 };
 
@@ -393,7 +394,8 @@ wayland_listener!(pub(crate) Shell, (Surface, Option<Box<Handler>>), [
 
 pub(crate) struct State {
     pub(crate) shell: Option<NonNull<Shell>>,
-    handle: Weak<Cell<bool>>
+    handle: Weak<Cell<bool>>,
+    user_data: UserData
 }
 
 /// An Xwayland user interface component. It has an absolute position in
@@ -419,7 +421,8 @@ impl Surface {
         let liveliness = Rc::new(Cell::new(false));
         let state = Box::new(State {
             shell: None,
-            handle: Rc::downgrade(&liveliness)
+            handle: Rc::downgrade(&liveliness),
+            user_data: UserData::new()
         });
         (*shell_surface.as_ptr()).data = Box::into_raw(state) as *mut _;
         Surface {
@@ -438,6 +441,20 @@ impl Surface {
         unsafe { (*self.shell_surface.as_ptr()).surface_id }
     }
 
+    /// Gets the user data attached to the XWayland surface.
+    ///
+    /// It is dropped when the XWayland surface is destroyed.
+    pub fn user_data(&self) -> &UserData {
+        unsafe { &(*((*self.shell_surface.as_ptr()).data as *mut State)).user_data }
+    }
+
+    /// Gets the user data attached to the XWayland surface mutably.
+    ///
+    /// It is dropped when the XWayland surface is destroyed.
+    pub fn user_data_mut(&mut self) -> &mut UserData {
+        unsafe { &mut (*((*self.shell_surface.as_ptr()).data as *mut State)).user_data }
+    }
+
     /// Get the Wayland surface associated with this Surface. If the shell
     /// surface is not mapped, then it has no surface, and this will return
     /// None.
@@ -641,6 +658,28 @@ impl Surface {
             wlr_xwayland_surface_activate(self.shell_surface.as_ptr(), active);
         }
     }
+
+    /// Tell the window whether it is maximized, both vertically and
+    /// horizontally.
+    pub fn set_maximized(&self, maximized: bool) {
+        unsafe {
+            wlr_xwayland_surface_set_maximized(self.shell_surface.as_ptr(), maximized);
+        }
+    }
+
+    /// Tell the window whether it is fullscreen.
+    pub fn set_fullscreen(&self, fullscreen: bool) {
+        unsafe {
+            wlr_xwayland_surface_set_fullscreen(self.shell_surface.as_ptr(), fullscreen);
+        }
+    }
+
+    /// Ask the window to close.
+    pub fn close(&self) {
+        unsafe {
+            wlr_xwayland_surface_close(self.shell_surface.as_ptr());
+        }
+    }
 }
 
 impl Drop for Surface {
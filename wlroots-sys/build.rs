@@ -21,9 +21,11 @@ fn main() {
         .whitelisted_type(r"^wlr_.*$")
         .whitelisted_type(r"^xkb_.*$")
         .whitelisted_type(r"^XKB_.*$")
+        .whitelisted_type(r"^libinput_config_.*$")
         .whitelisted_function(r"^_?pixman_.*$")
         .whitelisted_function(r"^_?wlr_.*$")
         .whitelisted_function(r"^xkb_.*$")
+        .whitelisted_function(r"^libinput_device_config_.*$")
         .ctypes_prefix("libc")
         .clang_arg("-Iwlroots/include")
         .clang_arg("-Iwlroots/include/wlr")
@@ -43,6 +45,11 @@ fn main() {
     if cfg!(feature = "unstable") {
         builder = builder.clang_arg("-DWLR_USE_UNSTABLE");
     }
+    // NOTE Not a meson option, the header only exists in newer wlroots than
+    // the bundled one.
+    if cfg!(feature = "output_power_management") {
+        builder = builder.clang_arg("-DWLR_HAS_OUTPUT_POWER_MANAGEMENT");
+    }
     if !cfg!(feature = "static") {
         // config.h won't exist, so make a dummy file.
         // We don't need it because of the following -D defines.
@@ -161,25 +168,36 @@ fn generate_protocol_headers() -> io::Result<PathBuf> {
     for entry in protocols {
         let entry = entry?;
         for entry in fs::read_dir(entry.path())? {
-            let entry = entry?;
-            let path = entry.path();
-            let mut filename = entry.file_name().into_string().unwrap();
-            if filename.ends_with(".xml") {
-                let new_length = filename.len() - 4;
-                filename.truncate(new_length);
-            }
-            filename.push_str("-protocol");
-            Command::new("wayland-scanner")
-                .arg("server-header")
-                .arg(path.clone())
-                .arg(format!("{}/{}.h", out_path.to_str().unwrap(), filename))
-                .status()
-                .unwrap();
+            generate_protocol_header(&out_path, entry?)?;
         }
     }
+    // Some wlroots headers include the headers of protocols that are only
+    // shipped with wlroots, so generate those as well.
+    for entry in fs::read_dir("wlroots/protocol")? {
+        generate_protocol_header(&out_path, entry?)?;
+    }
     Ok(out_path)
 }
 
+/// Generates the server header for the protocol described by the entry,
+/// if it is a protocol XML file.
+fn generate_protocol_header(out_path: &Path, entry: fs::DirEntry) -> io::Result<()> {
+    let path = entry.path();
+    let mut filename = entry.file_name().into_string().unwrap();
+    if !filename.ends_with(".xml") {
+        return Ok(());
+    }
+    let new_length = filename.len() - 4;
+    filename.truncate(new_length);
+    filename.push_str("-protocol");
+    Command::new("wayland-scanner")
+        .arg("server-header")
+        .arg(path)
+        .arg(format!("{}/{}.h", out_path.to_str().unwrap(), filename))
+        .status()?;
+    Ok(())
+}
+
 fn generate_protocols() {
     let output_dir_str = env::var("OUT_DIR").unwrap();
 
@@ -45,6 +45,9 @@
 #include <wlr/types/wlr_output.h>
 #include <wlr/types/wlr_output_layout.h>
 #include <wlr/types/wlr_output_damage.h>
+#ifdef WLR_HAS_OUTPUT_POWER_MANAGEMENT
+#include <wlr/types/wlr_output_power_management_v1.h>
+#endif
 #include <wlr/types/wlr_pointer.h>
 #include <wlr/types/wlr_region.h>
 #include <wlr/types/wlr_server_decoration.h>